@@ -55,6 +55,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.7,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     println!("⚙️  创建 Sequential Executor...");
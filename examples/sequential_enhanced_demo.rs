@@ -55,6 +55,9 @@ async fn demo_file_operations() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.6,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     let executor = SequentialExecutor::new(model, config);
@@ -83,6 +86,9 @@ async fn demo_code_generation() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.6,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     let executor = SequentialExecutor::new(model, config);
@@ -111,6 +117,9 @@ async fn demo_complex_task() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.7,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     let executor = SequentialExecutor::new(model, config);
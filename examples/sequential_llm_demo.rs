@@ -43,6 +43,9 @@ async fn demo_simple_task() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.6,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     let executor = SequentialExecutor::new(model, config);
@@ -75,6 +78,9 @@ async fn demo_complex_task() -> Result<(), Box<dyn std::error::Error>> {
         min_confidence_threshold: 0.7,
         enable_auto_rollback: true,
         verbose_logging: true,
+        compile_repair: None,
+        strategy: Default::default(),
+        budget: None,
     };
 
     let executor = SequentialExecutor::new(model, config);
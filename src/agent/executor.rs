@@ -5,7 +5,9 @@
 
 use crate::errors::AgentError;
 use crate::parser;
-use crate::execution::{read_file, list_files, run_command};
+use crate::execution::{read_file, write_file, list_files, run_command_streaming, BudgetTracker};
+use crate::security::ResourceLimits;
+use crate::tools::ToolRegistry;
 use crate::types::ExecutionResult;
 
 /// Task Executor
@@ -34,9 +36,25 @@ impl TaskExecutor {
     ///
     /// An `ExecutionResult` containing the outcome of the execution
     pub async fn execute_task(
+        &self,
+        task_request: &str,
+        task_understanding: &str,
+    ) -> Result<ExecutionResult, AgentError> {
+        self.execute_task_with_budget(task_request, task_understanding, &BudgetTracker::unlimited(), &ToolRegistry::new())
+            .await
+    }
+
+    /// Execute a task, counting each tool call against the run budget
+    ///
+    /// Returns `AgentError::BudgetExceeded` instead of calling a tool once the
+    /// budget is exhausted. A tool outside the task scope of `tools` is not
+    /// called; the result reports it as a failure instead.
+    pub async fn execute_task_with_budget(
         &self,
         _task_request: &str,
         task_understanding: &str,
+        budget: &BudgetTracker,
+        tools: &ToolRegistry,
     ) -> Result<ExecutionResult, AgentError> {
         tracing::info!("Executing task based on understanding: {}", task_understanding);
 
@@ -45,17 +63,38 @@ impl TaskExecutor {
 
         // Pattern 1: Read file
         if lower_understanding.contains("read") && lower_understanding.contains("file") {
+            if let Err(e) = tools.check_scope("read_file") {
+                return Ok(Self::out_of_scope(e));
+            }
+            Self::charge_tool_call(budget)?;
             return self.execute_read_file(task_understanding).await;
         }
 
-        // Pattern 2: List files
+        // Pattern 2: Write file
+        if lower_understanding.contains("write") && lower_understanding.contains("file") {
+            if let Err(e) = tools.check_scope("write_file") {
+                return Ok(Self::out_of_scope(e));
+            }
+            Self::charge_tool_call(budget)?;
+            return self.execute_write_file(task_understanding, tools).await;
+        }
+
+        // Pattern 3: List files
         if lower_understanding.contains("list") && lower_understanding.contains("file") {
+            if let Err(e) = tools.check_scope("list_files") {
+                return Ok(Self::out_of_scope(e));
+            }
+            Self::charge_tool_call(budget)?;
             return self.execute_list_files(task_understanding).await;
         }
 
-        // Pattern 3: Run command
+        // Pattern 4: Run command
         if lower_understanding.contains("run") && lower_understanding.contains("command") {
-            return self.execute_run_command(task_understanding).await;
+            if let Err(e) = tools.check_scope("run_command") {
+                return Ok(Self::out_of_scope(e));
+            }
+            Self::charge_tool_call(budget)?;
+            return self.execute_run_command(task_understanding, tools).await;
         }
 
         // Default: Return the understanding as the result
@@ -67,6 +106,21 @@ impl TaskExecutor {
         })
     }
 
+    fn out_of_scope(error: crate::errors::ToolError) -> ExecutionResult {
+        ExecutionResult {
+            success: false,
+            summary: "Tool not allowed for this task".to_string(),
+            details: error.to_string(),
+            execution_time: 0,
+        }
+    }
+
+    fn charge_tool_call(budget: &BudgetTracker) -> Result<(), AgentError> {
+        budget.check()?;
+        budget.record_tool_call();
+        Ok(())
+    }
+
     /// Execute file reading operation
     async fn execute_read_file(&self, task_understanding: &str) -> Result<ExecutionResult, AgentError> {
         if let Some(file_path) = parser::extract_file_path(task_understanding) {
@@ -98,6 +152,48 @@ impl TaskExecutor {
         }
     }
 
+    /// Execute file writing operation
+    ///
+    /// The write is recorded in the change journal of `tools`, if one is set,
+    /// so the task can be undone like writes made through the `write_file` tool.
+    async fn execute_write_file(&self, task_understanding: &str, tools: &ToolRegistry) -> Result<ExecutionResult, AgentError> {
+        let (Some(file_path), Some(content)) = (
+            parser::extract_file_path(task_understanding),
+            parser::extract_quoted_text(task_understanding),
+        ) else {
+            return Ok(ExecutionResult {
+                success: false,
+                summary: "Could not extract file path and content".to_string(),
+                details: "Please specify a file path and the content in quotes".to_string(),
+                execution_time: 1,
+            });
+        };
+
+        let journal = tools.journal();
+        if let Some(journal) = &journal {
+            journal.record_before(&file_path).await?;
+        }
+        let written = write_file(&file_path, &content).await;
+        if let Some(journal) = &journal {
+            journal.record_after(&file_path).await?;
+        }
+
+        match written {
+            Ok(()) => Ok(ExecutionResult {
+                success: true,
+                summary: format!("Successfully wrote file: {}", file_path),
+                details: format!("Wrote {} bytes", content.len()),
+                execution_time: 1,
+            }),
+            Err(e) => Ok(ExecutionResult {
+                success: false,
+                summary: format!("Failed to write file: {}", file_path),
+                details: format!("Error: {}", e),
+                execution_time: 1,
+            }),
+        }
+    }
+
     /// Execute file listing operation
     async fn execute_list_files(&self, task_understanding: &str) -> Result<ExecutionResult, AgentError> {
         let path = parser::extract_directory_path(task_understanding)
@@ -124,9 +220,18 @@ impl TaskExecutor {
     }
 
     /// Execute command running operation
-    async fn execute_run_command(&self, task_understanding: &str) -> Result<ExecutionResult, AgentError> {
+    ///
+    /// Output lines are forwarded to the output observer of `tools` as they arrive.
+    async fn execute_run_command(&self, task_understanding: &str, tools: &ToolRegistry) -> Result<ExecutionResult, AgentError> {
         if let Some(command) = parser::extract_command(task_understanding) {
-            match run_command(&command).await {
+            let observer = tools.output_observer();
+            let task_id = tools.task();
+            let on_line = |line: &_| {
+                if let Some(observer) = &observer {
+                    observer.on_output(task_id.as_deref(), "run_command", line);
+                }
+            };
+            match run_command_streaming(&command, &ResourceLimits::default(), on_line).await {
                 Ok(output) => {
                     Ok(ExecutionResult {
                         success: true,
@@ -11,9 +11,10 @@ pub use planner::TaskPlanner;
 
 use crate::config::AgentConfig;
 use crate::errors::AgentError;
+use crate::execution::{BudgetLimits, BudgetTracker, BudgetedModel, ChangeJournal, JournalStore};
 use crate::models::LanguageModel;
-use crate::planning::PlanningEngine;
-use crate::tools::ToolRegistry;
+use crate::planning::{PlanningEngine, RepoMapBuilder};
+use crate::tools::{ModelSummarizer, OutputProcessor, ToolFilter, ToolRegistry};
 use crate::types::{Task, TaskResult, TaskStatus};
 use std::sync::Arc;
 
@@ -34,6 +35,8 @@ use std::sync::Arc;
 /// - **Task Planner**: Creates execution plans based on understanding
 /// - **Task Executor**: Executes the planned tasks
 /// - **Tool Registry**: Manages available tools for task execution
+/// - **Budget Tracker**: Enforces run-level limits on tokens, cost, time, tool calls and steps
+/// - **Change Journal**: Records the files each task changed so it can be undone
 pub struct TaskAgent {
     model: Arc<dyn LanguageModel>,
     tools: Arc<ToolRegistry>,  // Removed Mutex - ToolRegistry has internal locking
@@ -41,6 +44,8 @@ pub struct TaskAgent {
     planning_engine: PlanningEngine,
     _planner: TaskPlanner,  // Future: Use for advanced planning
     executor: TaskExecutor,
+    budget: Arc<BudgetTracker>,
+    journal_store: Option<JournalStore>,
     _error_handler: crate::errors::ErrorHandler,
 }
 
@@ -64,19 +69,40 @@ impl TaskAgent {
             config.execution.retry_delay_seconds,
         );
 
-        // Convert Box to Arc for shared ownership
-        let model_arc: Arc<dyn LanguageModel> = model.into();
-        let planning_engine = PlanningEngine::new(Arc::clone(&model_arc));
+        // Convert Box to Arc for shared ownership, metering every call against the run budget
+        let budget = Arc::new(BudgetTracker::new(BudgetLimits::from_config(&config)));
+        let model_arc: Arc<dyn LanguageModel> =
+            Arc::new(BudgetedModel::new(model.into(), Arc::clone(&budget)));
+        let mut planning_engine = PlanningEngine::new(Arc::clone(&model_arc));
+        if config.execution.repo_map.enabled {
+            planning_engine = planning_engine.with_repo_map(
+                RepoMapBuilder::new(&config.execution.repo_map.root)
+                    .with_token_budget(config.execution.repo_map.max_tokens),
+            );
+        }
         let planner = TaskPlanner::new();
         let executor = TaskExecutor::new();
+        let journal_store = JournalStore::from_config(&config);
+
+        let tools = Arc::new(ToolRegistry::with_filter(ToolFilter::from_config(&config.tools)));  // No Mutex needed
+        if config.tools.output.enabled {
+            // Summaries are model calls too, so they count against the run budget
+            let mut processor = OutputProcessor::new(config.tools.output.clone());
+            if config.tools.output.summarize_above_chars.is_some() {
+                processor = processor.with_summarizer(Arc::new(ModelSummarizer::new(Arc::clone(&model_arc))));
+            }
+            tools.set_output_processor(Some(Arc::new(processor)));
+        }
 
         Self {
             model: model_arc,
-            tools: Arc::new(ToolRegistry::new()),  // No Mutex needed
+            tools,
             config,
             planning_engine,
             _planner: planner,
             executor,
+            budget,
+            journal_store,
             _error_handler,
         }
     }
@@ -115,9 +141,55 @@ impl TaskAgent {
     /// # }
     /// ```
     pub async fn process_task(&mut self, request: &str) -> Result<TaskResult, AgentError> {
+        let limits = BudgetLimits::from_config(&self.config);
+        self.process_task_with_limits(request, limits).await
+    }
+
+    /// Process a task under explicit budget limits
+    ///
+    /// When a limit is reached the run stops gracefully: the returned
+    /// `TaskResult` has `success == false`, keeps whatever plan was produced,
+    /// and reports usage in `budget`.
+    pub async fn process_task_with_limits(
+        &mut self,
+        request: &str,
+        limits: BudgetLimits,
+    ) -> Result<TaskResult, AgentError> {
         let task_id = uuid::Uuid::new_v4().to_string();
+        self.process_task_as(&task_id, request, limits).await
+    }
+
+    /// Process a task under a caller-chosen task id
+    ///
+    /// File changes made through the tool registry are journaled under
+    /// `task_id`, which is what `undo` and the revert endpoint take.
+    pub async fn process_task_as(
+        &mut self,
+        task_id: &str,
+        request: &str,
+        limits: BudgetLimits,
+    ) -> Result<TaskResult, AgentError> {
+        self.process_task_scoped(task_id, request, limits, None).await
+    }
+
+    /// Process a task that may only use the tools in `allowed_tools`
+    ///
+    /// Tools outside the list (names may be glob patterns) are hidden from
+    /// the model and refused with a `PermissionDenied` error for the duration
+    /// of the task. `None` allows every registered tool.
+    pub async fn process_task_scoped(
+        &mut self,
+        task_id: &str,
+        request: &str,
+        limits: BudgetLimits,
+        allowed_tools: Option<&[String]>,
+    ) -> Result<TaskResult, AgentError> {
+        self.budget.reset(limits);
+        self.tools.set_scope(allowed_tools.map(|tools| ToolFilter::allow_only(tools.iter().cloned())));
+        self.tools.set_task(Some(task_id.to_string()));
+
         let task = Task {
-            id: task_id.clone(),
+            id: task_id.to_string(),
             request: request.to_string(),
             status: TaskStatus::Pending,
             created_at: chrono::Utc::now(),
@@ -125,7 +197,27 @@ impl TaskAgent {
             result: None,
         };
 
-        self.execute_task_internal(task).await
+        let journal = self
+            .journal_store
+            .as_ref()
+            .map(|store| Arc::new(ChangeJournal::new(store, task_id)));
+        self.tools.set_journal(journal.clone());
+
+        let result = self.execute_task_internal(task).await;
+
+        self.tools.set_journal(None);
+        self.tools.set_scope(None);
+        self.tools.finish_task(task_id).await;
+        if let Some(journal) = journal {
+            if let Err(e) = journal.save().await {
+                tracing::warn!("Failed to save change journal for task {}: {}", task_id, e);
+            }
+        }
+
+        result.map(|mut result| {
+            result.task_id = Some(task_id.to_string());
+            result
+        })
     }
 
     /// Internal task execution workflow
@@ -134,7 +226,13 @@ impl TaskAgent {
         task.updated_at = chrono::Utc::now();
 
         // 1. Understanding phase - analyze task requirements
-        let plan = self.planning_engine.analyze_task(&task.request).await?;
+        let plan = match self.budget.run(self.planning_engine.analyze_task(&task.request)).await {
+            Ok(plan) => plan,
+            Err(error @ AgentError::BudgetExceeded { .. }) => {
+                return Ok(self.budget_exhausted_result(error, None));
+            }
+            Err(error) => return Err(error),
+        };
 
         tracing::info!(
             "Task plan created: {} steps estimated",
@@ -142,7 +240,15 @@ impl TaskAgent {
         );
 
         // 2. Execution phase - delegate to executor
-        let execution_result = self.executor.execute_task(&task.request, &plan.understanding).await?;
+        let execution = self.executor.execute_task_with_budget(&task.request, &plan.understanding, &self.budget, &self.tools);
+        let execution_result = match self.budget.run(execution).await {
+            Ok(result) => result,
+            Err(error @ AgentError::BudgetExceeded { .. }) => {
+                return Ok(self.budget_exhausted_result(error, Some(plan)));
+            }
+            Err(error) => return Err(error),
+        };
+        self.budget.record_step();
 
         // 3. Build result
         task.status = if execution_result.success {
@@ -158,9 +264,30 @@ impl TaskAgent {
             details: Some(execution_result.details),
             execution_time: Some(execution_result.execution_time),
             task_plan: Some(plan),
+            budget: Some(self.budget.usage()),
+            task_id: Some(task.id),
         })
     }
 
+    /// Build the result for a run stopped by its budget
+    fn budget_exhausted_result(&self, error: AgentError, plan: Option<crate::types::TaskPlan>) -> TaskResult {
+        let usage = self.budget.usage();
+        tracing::warn!("Task stopped: {}", error);
+
+        TaskResult {
+            success: false,
+            summary: error.to_string(),
+            details: Some(format!(
+                "Stopped after {} model calls ({} tokens), {} tool calls and {} steps in {} ms",
+                usage.model_calls, usage.total_tokens, usage.tool_calls, usage.steps, usage.elapsed_ms
+            )),
+            execution_time: Some(usage.elapsed_ms / 1000),
+            task_plan: plan,
+            budget: Some(usage),
+            task_id: None,
+        }
+    }
+
     /// Register a tool with the agent
     ///
     /// Tools extend the agent's capabilities by providing specific operations
@@ -195,6 +322,16 @@ impl TaskAgent {
         self.tools.register(tool).await;
     }
 
+    /// Register the built-in tools, configured plugins and MCP server tools
+    ///
+    /// The CLI and the service both build their agents through this, so they
+    /// offer the same tools; the registry's filter decides which are kept.
+    pub async fn register_default_tools(&self) {
+        crate::tools::register_builtin_tools(&self.tools, &self.config).await;
+        crate::tools::register_configured_plugins(&self.tools, &self.config.tools).await;
+        crate::mcp::register_configured_mcp_servers(&self.tools, &self.config.tools).await;
+    }
+
     /// Get reference to the tools registry
     pub fn get_tools(&self) -> Arc<ToolRegistry> {
         Arc::clone(&self.tools)
@@ -219,6 +356,16 @@ impl TaskAgent {
     pub fn get_config(&self) -> &AgentConfig {
         &self.config
     }
+
+    /// Get the budget tracker for the current run
+    pub fn get_budget(&self) -> &Arc<BudgetTracker> {
+        &self.budget
+    }
+
+    /// Get the change journal store, if journaling is enabled
+    pub fn get_journal_store(&self) -> Option<&JournalStore> {
+        self.journal_store.as_ref()
+    }
 }
 
 /// Factory function to create an agent with default tools
@@ -232,9 +379,32 @@ pub fn create_agent_with_default_tools(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::MockModel;
+    use crate::models::{MockModel, ModelResponse, ToolDefinition};
+    use crate::test_support::TempDir;
     use crate::tools::ReadFileTool;
 
+    /// Answers every prompt with the same text
+    struct FixedModel(String);
+
+    #[async_trait::async_trait]
+    impl LanguageModel for FixedModel {
+        async fn complete(&self, _prompt: &str) -> Result<ModelResponse, crate::errors::ModelError> {
+            Ok(ModelResponse::text(self.0.clone()))
+        }
+
+        async fn complete_with_tools(&self, prompt: &str, _tools: &[ToolDefinition]) -> Result<ModelResponse, crate::errors::ModelError> {
+            self.complete(prompt).await
+        }
+
+        fn model_name(&self) -> &str {
+            "fixed"
+        }
+
+        fn supports_tools(&self) -> bool {
+            false
+        }
+    }
+
     #[tokio::test]
     async fn test_agent_creation() {
         let model = Box::new(MockModel::new("test".to_string()));
@@ -258,5 +428,44 @@ mod tests {
         assert!(agent.has_tool("read_file").await);
         assert_eq!(agent.tool_count().await, 1);
     }
+
+    #[tokio::test]
+    async fn test_process_task_stops_on_budget() {
+        let model = Box::new(MockModel::new("test".to_string()));
+        let mut agent = TaskAgent::new(model, AgentConfig::default());
+
+        let limits = BudgetLimits {
+            max_tokens: Some(1),
+            ..BudgetLimits::default()
+        };
+        let result = agent.process_task_with_limits("List files", limits).await.unwrap();
+
+        assert!(!result.success);
+        assert!(result.task_plan.is_some());
+        let usage = result.budget.unwrap();
+        assert_eq!(usage.exhausted, Some(crate::execution::BudgetResource::Tokens));
+        assert_eq!(usage.tool_calls, 0);
+    }
+
+    #[tokio::test]
+    async fn test_task_writes_are_journaled_and_reverted() {
+        let dir = TempDir::new("agent_journal");
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "before").unwrap();
+
+        let mut config = AgentConfig::default();
+        config.execution.journal.dir = dir.join("journal").to_string_lossy().to_string();
+        let understanding = format!("UNDERSTANDING: Write \"after\" to file {}", notes.display());
+        let mut agent = TaskAgent::new(Box::new(FixedModel(understanding)), config);
+
+        let result = agent.process_task_as("task-1", "Update the notes", BudgetLimits::default()).await.unwrap();
+        assert!(result.success, "{}", result.summary);
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "after");
+
+        let report = agent.get_journal_store().unwrap().revert(Some("task-1")).await.unwrap();
+        assert_eq!(report.restored_files, vec![notes.to_string_lossy().to_string()]);
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "before");
+        assert!(dir.join("journal").join(".gitignore").exists());
+    }
 }
 
@@ -126,12 +126,24 @@ impl TaskPlanner {
         if lower.contains("write") && lower.contains("file") {
             tools.push("write_file".to_string());
         }
+        if (lower.contains("edit") || lower.contains("patch")) && lower.contains("file") {
+            tools.push("edit_file".to_string());
+        }
         if lower.contains("list") && lower.contains("file") {
             tools.push("list_files".to_string());
         }
         if lower.contains("run") && lower.contains("command") {
             tools.push("run_command".to_string());
         }
+        if lower.contains("cargo") || ((lower.contains("build") || lower.contains("test")) && lower.contains("rust")) {
+            tools.push("cargo".to_string());
+        }
+        if lower.contains("git") || lower.contains("commit") {
+            tools.push("git_status".to_string());
+            if lower.contains("commit") {
+                tools.push("git_commit".to_string());
+            }
+        }
 
         tools
     }
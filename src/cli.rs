@@ -44,6 +44,20 @@ pub enum Commands {
         #[arg(short, long, default_value = "config.toml")]
         config: String,
     },
+    /// Undo the file changes of a task (the latest one by default)
+    Undo {
+        /// Task id to undo
+        task_id: Option<String>,
+        /// Configuration file
+        #[arg(short, long, default_value = "config.toml")]
+        config: String,
+    },
+    /// Serve the agent's tools and a run_task tool over MCP (stdio)
+    McpServe {
+        /// Configuration file
+        #[arg(short, long, default_value = "config.toml")]
+        config: String,
+    },
 }
 
 impl Cli {
@@ -62,6 +76,12 @@ impl Cli {
             Commands::Config { config } => {
                 Self::handle_config(config).await
             }
+            Commands::Undo { task_id, config } => {
+                Self::handle_undo(task_id, config).await
+            }
+            Commands::McpServe { config } => {
+                Self::handle_mcp_serve(config).await
+            }
         }
     }
 
@@ -81,6 +101,7 @@ impl Cli {
 
         println!("🤖 Initializing AI agent...");
         let mut agent = create_agent(&config).await?;
+        agent.get_tools().set_output_observer(Some(std::sync::Arc::new(print_output_line)));
         let tool_count = agent.tool_count().await;
         println!("✅ Agent initialized with {} tools", tool_count);
 
@@ -97,6 +118,9 @@ impl Cli {
             Ok(task_result) => {
                 println!("✅ Task Status: SUCCESS");
                 println!("⏱️  Execution Time: {}s", task_result.execution_time.unwrap_or(0));
+                if let Some(task_id) = &task_result.task_id {
+                    println!("🆔 Task ID: {} (revert with `undo {}`)", task_id, task_id);
+                }
 
                 match output.as_str() {
                     "json" => {
@@ -158,6 +182,7 @@ impl Cli {
         let config = AgentConfig::load_with_fallback(&config_path)
             .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))?;
         let mut agent = create_agent(&config).await?;
+        agent.get_tools().set_output_observer(Some(std::sync::Arc::new(print_output_line)));
 
         println!("AI-Native Code Agent - Interactive Mode");
         println!("Type 'exit' or 'quit' to exit");
@@ -225,6 +250,42 @@ impl Cli {
         Ok(())
     }
 
+    async fn handle_undo(task_id: Option<String>, config_path: String) -> anyhow::Result<()> {
+        let config = AgentConfig::load_with_fallback(&config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))?;
+        let store = crate::execution::JournalStore::from_config(&config)
+            .ok_or_else(|| anyhow::anyhow!("Change journal is disabled (execution.journal.enabled = false)"))?;
+
+        let report = store.revert(task_id.as_deref()).await
+            .map_err(|e| anyhow::anyhow!("Undo failed: {}", e))?;
+
+        println!("↩️  Reverted task {}", report.task_id);
+        for path in &report.restored_files {
+            println!("  restored  {}", path);
+        }
+        for path in &report.deleted_files {
+            println!("  deleted   {}", path);
+        }
+        if report.restored_files.is_empty() && report.deleted_files.is_empty() {
+            println!("  (no file changes recorded)");
+        }
+        Ok(())
+    }
+
+    async fn handle_mcp_serve(config_path: String) -> anyhow::Result<()> {
+        // stdout carries the protocol, so nothing else may be printed here
+        let config = AgentConfig::load_with_fallback(&config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))?;
+        let agent = create_agent(&config).await?;
+        tracing::info!("Serving {} tools over MCP on stdio", agent.tool_count().await);
+
+        crate::mcp::McpServer::new(agent.get_tools())
+            .with_agent(agent)
+            .serve_stdio()
+            .await
+            .map_err(|e| anyhow::anyhow!("MCP server failed: {}", e))
+    }
+
     fn print_help() {
         println!("Available commands:");
         println!("  exit, quit  - Exit the program");
@@ -247,6 +308,14 @@ impl Cli {
     }
 }
 
+/// Print command output live while a tool runs, stderr lines to stderr
+fn print_output_line(_task_id: Option<&str>, _tool: &str, line: &crate::execution::OutputLine) {
+    match line.stream {
+        crate::execution::OutputStream::Stdout => println!("  │ {}", line.line),
+        crate::execution::OutputStream::Stderr => eprintln!("  │ {}", line.line),
+    }
+}
+
 /// Create an agent with the given configuration
 async fn create_agent(config: &AgentConfig) -> anyhow::Result<crate::agent::TaskAgent> {
     // Create unified model from configuration
@@ -255,11 +324,8 @@ async fn create_agent(config: &AgentConfig) -> anyhow::Result<crate::agent::Task
 
     let agent = crate::agent::TaskAgent::new(model, config.clone());
 
-    // Register basic tools
-    agent.register_tool(crate::tools::ReadFileTool).await;
-    agent.register_tool(crate::tools::WriteFileTool).await;
-    agent.register_tool(crate::tools::RunCommandTool).await;
-    agent.register_tool(crate::tools::ListFilesTool).await;
+    // Register basic tools, configured plugins and MCP server tools
+    agent.register_default_tools().await;
 
     Ok(agent)
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 //! Configuration management for the AI-Native Code Agent
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Main agent configuration
@@ -49,6 +50,116 @@ pub struct ExecutionConfig {
     pub timeout_seconds: u64,
     pub max_retries: u32,
     pub retry_delay_seconds: u64,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub repo_map: RepoMapConfig,
+    #[serde(default)]
+    pub index: IndexConfig,
+}
+
+/// Repository map given to the planner
+///
+/// The files under `root` are ranked by relevance to the request and their
+/// top-level symbols listed, within roughly `max_tokens` tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoMapConfig {
+    pub enabled: bool,
+    pub root: String,
+    pub max_tokens: usize,
+}
+
+impl Default for RepoMapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            root: ".".to_string(),
+            max_tokens: 1024,
+        }
+    }
+}
+
+/// Workspace index used by `retrieve_context`
+///
+/// Text files under `root` are split into `chunk_lines`-line chunks for BM25
+/// retrieval, and `top_k` snippets are returned unless a caller asks for a
+/// different number. The index is kept in `cache_dir` and only changed files
+/// are re-read when it is refreshed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub enabled: bool,
+    pub root: String,
+    pub cache_dir: String,
+    pub chunk_lines: usize,
+    pub top_k: usize,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            root: ".".to_string(),
+            cache_dir: ".agent-runner/index".to_string(),
+            chunk_lines: 40,
+            top_k: 5,
+        }
+    }
+}
+
+/// Run-level budget configuration
+///
+/// `max_steps` and `timeout_seconds` from [`ExecutionConfig`] bound steps and
+/// wall time; the limits here cover model tokens, estimated cost and tool calls.
+/// Unset limits are unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_tool_calls: Option<u32>,
+    pub prompt_cost_per_1k_tokens: f64,
+    pub completion_cost_per_1k_tokens: f64,
+    /// Fraction of any limit at which the model is told to wrap up
+    pub warning_threshold: f32,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            max_cost_usd: None,
+            max_tool_calls: None,
+            prompt_cost_per_1k_tokens: 0.0,
+            completion_cost_per_1k_tokens: 0.0,
+            warning_threshold: 0.8,
+        }
+    }
+}
+
+/// Change journal configuration
+///
+/// Each task records the files it changed under `dir` so it can be undone
+/// with `undo` or `POST /api/v1/tasks/:id/revert`. `dir` is relative to the
+/// workspace the agent runs in; a `.gitignore` written there keeps the
+/// journal out of version control.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: ".agent-runner/journal".to_string(),
+        }
+    }
 }
 
 /// Safety configuration
@@ -64,8 +175,84 @@ pub struct SafetyConfig {
 pub struct ToolConfig {
     pub auto_discovery: bool,
     pub custom_tools_path: Option<String>,
+    /// Tool names or globs to register; empty registers every tool
     pub enabled_tools: Vec<String>,
     pub disabled_tools: Vec<String>,
+    /// MCP servers whose tools are imported into the registry
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,
+    /// Compaction of large tool output before it reaches the model
+    #[serde(default)]
+    pub output: ToolOutputConfig,
+}
+
+/// Tool output compaction
+///
+/// Output longer than `max_chars` is kept as an artifact the model can page
+/// through with `read_artifact`. The model sees it with repeated lines
+/// collapsed and, if still too long, summarized by the model (when longer
+/// than `summarize_above_chars`) or cut to its first and last lines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolOutputConfig {
+    pub enabled: bool,
+    pub max_chars: usize,
+    pub head_lines: usize,
+    pub tail_lines: usize,
+    pub dedupe_lines: bool,
+    /// Summarize with the model above this size; never when unset
+    pub summarize_above_chars: Option<usize>,
+    /// Artifacts kept before the oldest are dropped
+    pub max_artifacts: usize,
+}
+
+impl Default for ToolOutputConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_chars: 16_000,
+            head_lines: 100,
+            tail_lines: 100,
+            dedupe_lines: true,
+            summarize_above_chars: None,
+            max_artifacts: 50,
+        }
+    }
+}
+
+/// MCP server configuration
+///
+/// Set `command` to launch the server and talk to it over stdio, or `url` to
+/// use the streamable HTTP transport. A stdio server that exits is started
+/// again on the next call, at most `max_restarts` times in a row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerConfig {
+    /// Prefix of the imported tool names
+    pub name: String,
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub url: Option<String>,
+    /// Extra HTTP headers, e.g. for authorization
+    pub headers: HashMap<String, String>,
+    pub timeout_secs: u64,
+    pub max_restarts: u32,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: None,
+            headers: HashMap::new(),
+            timeout_secs: 60,
+            max_restarts: 3,
+        }
+    }
 }
 
 /// Logging configuration
@@ -170,6 +357,10 @@ impl AgentConfig {
                 timeout_seconds: 300,
                 max_retries: 3,
                 retry_delay_seconds: 2,
+                budget: BudgetConfig::default(),
+                journal: JournalConfig::default(),
+                repo_map: RepoMapConfig::default(),
+                index: IndexConfig::default(),
             },
             safety: SafetyConfig {
                 enable_safety_checks: true,
@@ -184,13 +375,10 @@ impl AgentConfig {
             tools: ToolConfig {
                 auto_discovery: true,
                 custom_tools_path: None,
-                enabled_tools: vec![
-                    "read_file".to_string(),
-                    "write_file".to_string(),
-                    "run_command".to_string(),
-                    "list_files".to_string(),
-                ],
+                enabled_tools: vec![],
                 disabled_tools: vec![],
+                mcp_servers: vec![],
+                output: ToolOutputConfig::default(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -229,6 +417,10 @@ impl Default for AgentConfig {
                 timeout_seconds: 300,
                 max_retries: 3,
                 retry_delay_seconds: 2,
+                budget: BudgetConfig::default(),
+                journal: JournalConfig::default(),
+                repo_map: RepoMapConfig::default(),
+                index: IndexConfig::default(),
             },
             safety: SafetyConfig {
                 enable_safety_checks: true,
@@ -240,6 +432,8 @@ impl Default for AgentConfig {
                 custom_tools_path: None,
                 enabled_tools: vec![],
                 disabled_tools: vec![],
+                mcp_servers: vec![],
+                output: ToolOutputConfig::default(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
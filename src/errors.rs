@@ -26,6 +26,19 @@ pub enum AgentError {
     #[error("Execution error: {0}")]
     ExecutionError(String),
 
+    #[error("Budget exceeded: {resource} limit of {limit} reached (used {used})")]
+    BudgetExceeded {
+        resource: String,
+        used: String,
+        limit: String,
+    },
+
+    #[error("Aborted: {0}")]
+    Aborted(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Unknown error: {0}")]
     UnknownError(String),
 }
@@ -106,6 +119,9 @@ pub enum FileOperationError {
 
     #[error("File already exists: {path}")]
     AlreadyExists { path: String },
+
+    #[error("Binary file: {path} ({size} bytes, {kind}); first bytes: {preview}")]
+    BinaryFile { path: String, size: u64, kind: String, preview: String },
 }
 
 /// Command operation specific errors
@@ -0,0 +1,485 @@
+//! Run-level Budgets
+//!
+//! Tracks model tokens, estimated cost, wall time, tool calls and steps for a
+//! single run and stops execution gracefully once any limit is reached.
+//!
+//! - [`BudgetTracker`] holds the limits and accumulated usage for one run
+//! - [`BudgetedModel`] wraps a [`LanguageModel`], records every call and tells
+//!   the model when a limit is getting close so it can wrap up
+
+use crate::config::AgentConfig;
+use crate::errors::{AgentError, ModelError};
+use crate::models::{LanguageModel, ModelResponse, ToolDefinition};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Limits & Usage
+// ============================================================================
+
+/// A resource limited by a run budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetResource {
+    Tokens,
+    Cost,
+    WallTime,
+    ToolCalls,
+    Steps,
+}
+
+impl fmt::Display for BudgetResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BudgetResource::Tokens => "tokens",
+            BudgetResource::Cost => "cost",
+            BudgetResource::WallTime => "wall time",
+            BudgetResource::ToolCalls => "tool calls",
+            BudgetResource::Steps => "steps",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Limits for a single run; `None` means unlimited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetLimits {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_duration: Option<Duration>,
+    pub max_tool_calls: Option<u32>,
+    pub max_steps: Option<u32>,
+    pub prompt_cost_per_1k_tokens: f64,
+    pub completion_cost_per_1k_tokens: f64,
+    /// Fraction of any limit at which the model is warned (0.0 - 1.0)
+    pub warning_threshold: f32,
+}
+
+impl Default for BudgetLimits {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            max_cost_usd: None,
+            max_duration: None,
+            max_tool_calls: None,
+            max_steps: None,
+            prompt_cost_per_1k_tokens: 0.0,
+            completion_cost_per_1k_tokens: 0.0,
+            warning_threshold: 0.8,
+        }
+    }
+}
+
+impl BudgetLimits {
+    /// Limits with nothing enforced
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Build limits from the agent configuration.
+    ///
+    /// `execution.max_steps` and `execution.timeout_seconds` bound steps and
+    /// wall time (0 disables them); the rest comes from `execution.budget`.
+    pub fn from_config(config: &AgentConfig) -> Self {
+        let execution = &config.execution;
+        let budget = &execution.budget;
+
+        Self {
+            max_tokens: budget.max_tokens,
+            max_cost_usd: budget.max_cost_usd,
+            max_duration: (execution.timeout_seconds > 0)
+                .then(|| Duration::from_secs(execution.timeout_seconds)),
+            max_tool_calls: budget.max_tool_calls,
+            max_steps: (execution.max_steps > 0).then_some(execution.max_steps),
+            prompt_cost_per_1k_tokens: budget.prompt_cost_per_1k_tokens,
+            completion_cost_per_1k_tokens: budget.completion_cost_per_1k_tokens,
+            warning_threshold: budget.warning_threshold,
+        }
+    }
+}
+
+/// Snapshot of what a run has consumed so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    pub elapsed_ms: u64,
+    pub model_calls: u32,
+    pub tool_calls: u32,
+    pub steps: u32,
+    /// The resource that stopped the run, if any
+    pub exhausted: Option<BudgetResource>,
+}
+
+// ============================================================================
+// Tracker
+// ============================================================================
+
+/// Accumulates usage for one run and enforces its limits.
+///
+/// The wall-time clock starts when the tracker is created or [`reset`](Self::reset).
+pub struct BudgetTracker {
+    state: Mutex<BudgetState>,
+}
+
+struct BudgetState {
+    limits: BudgetLimits,
+    started_at: Instant,
+    usage: BudgetUsage,
+}
+
+impl BudgetTracker {
+    pub fn new(limits: BudgetLimits) -> Self {
+        Self {
+            state: Mutex::new(BudgetState {
+                limits,
+                started_at: Instant::now(),
+                usage: BudgetUsage::default(),
+            }),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(BudgetLimits::unlimited())
+    }
+
+    /// Start a new run with the given limits, clearing previous usage
+    pub fn reset(&self, limits: BudgetLimits) {
+        let mut state = self.lock();
+        state.limits = limits;
+        state.started_at = Instant::now();
+        state.usage = BudgetUsage::default();
+    }
+
+    pub fn limits(&self) -> BudgetLimits {
+        self.lock().limits.clone()
+    }
+
+    /// Record a model call. Providers that do not report usage are estimated
+    /// at roughly four characters per token.
+    pub fn record_model_call(&self, prompt: &str, response: &ModelResponse) {
+        let (prompt_tokens, completion_tokens) = match &response.usage {
+            Some(usage) => (usage.prompt_tokens as u64, usage.completion_tokens as u64),
+            None => (estimate_tokens(prompt), estimate_tokens(&response.content)),
+        };
+
+        let mut state = self.lock();
+        let cost = prompt_tokens as f64 / 1000.0 * state.limits.prompt_cost_per_1k_tokens
+            + completion_tokens as f64 / 1000.0 * state.limits.completion_cost_per_1k_tokens;
+
+        let usage = &mut state.usage;
+        usage.model_calls += 1;
+        usage.prompt_tokens += prompt_tokens;
+        usage.completion_tokens += completion_tokens;
+        usage.total_tokens += prompt_tokens + completion_tokens;
+        usage.cost_usd += cost;
+    }
+
+    pub fn record_tool_call(&self) {
+        self.lock().usage.tool_calls += 1;
+    }
+
+    pub fn record_step(&self) {
+        self.lock().usage.steps += 1;
+    }
+
+    /// Current usage, including elapsed wall time
+    pub fn usage(&self) -> BudgetUsage {
+        let state = self.lock();
+        let mut usage = state.usage.clone();
+        usage.elapsed_ms = state.started_at.elapsed().as_millis() as u64;
+        usage
+    }
+
+    /// Wall time left before the run must stop, if a duration limit is set
+    pub fn remaining_time(&self) -> Option<Duration> {
+        let state = self.lock();
+        state
+            .limits
+            .max_duration
+            .map(|max| max.saturating_sub(state.started_at.elapsed()))
+    }
+
+    /// Fail with [`AgentError::BudgetExceeded`] if any limit has been reached
+    pub fn check(&self) -> Result<(), AgentError> {
+        let mut state = self.lock();
+        let exhausted = consumption(&state)
+            .into_iter()
+            .find(|entry| entry.used >= entry.limit);
+
+        match exhausted {
+            Some(entry) => {
+                state.usage.exhausted = Some(entry.resource);
+                Err(entry.into_error())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Mark a resource as exhausted and build the matching error
+    pub fn exhaust(&self, resource: BudgetResource) -> AgentError {
+        let mut state = self.lock();
+        state.usage.exhausted = Some(resource);
+        consumption(&state)
+            .into_iter()
+            .find(|entry| entry.resource == resource)
+            .map(Consumption::into_error)
+            .unwrap_or_else(|| AgentError::BudgetExceeded {
+                resource: resource.to_string(),
+                used: "unknown".to_string(),
+                limit: "unknown".to_string(),
+            })
+    }
+
+    /// Run a future within the remaining wall time, checking the budget first
+    pub async fn run<T, F>(&self, future: F) -> Result<T, AgentError>
+    where
+        F: Future<Output = Result<T, AgentError>>,
+    {
+        self.check()?;
+        match self.remaining_time() {
+            Some(remaining) => tokio::time::timeout(remaining, future)
+                .await
+                .unwrap_or_else(|_| Err(self.exhaust(BudgetResource::WallTime))),
+            None => future.await,
+        }
+    }
+
+    /// Notice to append to prompts once usage passes the warning threshold
+    pub fn prompt_notice(&self) -> Option<String> {
+        let state = self.lock();
+        let threshold = state.limits.warning_threshold as f64;
+        let near: Vec<String> = consumption(&state)
+            .into_iter()
+            .filter(|entry| entry.limit > 0.0 && entry.used / entry.limit >= threshold)
+            .map(|entry| {
+                format!(
+                    "- {}: {} of {} used",
+                    entry.resource,
+                    entry.format(entry.used),
+                    entry.format(entry.limit)
+                )
+            })
+            .collect();
+
+        if near.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "# Budget Notice\nThis run is close to its budget:\n{}\nFinish the most important work first and keep the response short.",
+            near.join("\n")
+        ))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for BudgetTracker {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+struct Consumption {
+    resource: BudgetResource,
+    used: f64,
+    limit: f64,
+}
+
+impl Consumption {
+    fn format(&self, value: f64) -> String {
+        match self.resource {
+            BudgetResource::Cost => format!("${:.4}", value),
+            BudgetResource::WallTime => format!("{:.1}s", value),
+            _ => format!("{}", value as u64),
+        }
+    }
+
+    fn into_error(self) -> AgentError {
+        AgentError::BudgetExceeded {
+            resource: self.resource.to_string(),
+            used: self.format(self.used),
+            limit: self.format(self.limit),
+        }
+    }
+}
+
+fn consumption(state: &BudgetState) -> Vec<Consumption> {
+    let limits = &state.limits;
+    let usage = &state.usage;
+    let mut entries = Vec::new();
+
+    if let Some(max) = limits.max_tokens {
+        entries.push(Consumption { resource: BudgetResource::Tokens, used: usage.total_tokens as f64, limit: max as f64 });
+    }
+    if let Some(max) = limits.max_cost_usd {
+        entries.push(Consumption { resource: BudgetResource::Cost, used: usage.cost_usd, limit: max });
+    }
+    if let Some(max) = limits.max_duration {
+        entries.push(Consumption {
+            resource: BudgetResource::WallTime,
+            used: state.started_at.elapsed().as_secs_f64(),
+            limit: max.as_secs_f64(),
+        });
+    }
+    if let Some(max) = limits.max_tool_calls {
+        entries.push(Consumption { resource: BudgetResource::ToolCalls, used: usage.tool_calls as f64, limit: max as f64 });
+    }
+    if let Some(max) = limits.max_steps {
+        entries.push(Consumption { resource: BudgetResource::Steps, used: usage.steps as f64, limit: max as f64 });
+    }
+
+    entries
+}
+
+fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+// ============================================================================
+// Budgeted Model
+// ============================================================================
+
+/// Language model decorator that records usage against a [`BudgetTracker`]
+pub struct BudgetedModel {
+    inner: Arc<dyn LanguageModel>,
+    budget: Arc<BudgetTracker>,
+}
+
+impl BudgetedModel {
+    pub fn new(inner: Arc<dyn LanguageModel>, budget: Arc<BudgetTracker>) -> Self {
+        Self { inner, budget }
+    }
+
+    pub fn budget(&self) -> &Arc<BudgetTracker> {
+        &self.budget
+    }
+
+    fn annotate(&self, prompt: &str) -> String {
+        match self.budget.prompt_notice() {
+            Some(notice) => format!("{}\n\n{}", prompt, notice),
+            None => prompt.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModel for BudgetedModel {
+    async fn complete(&self, prompt: &str) -> Result<ModelResponse, ModelError> {
+        let prompt = self.annotate(prompt);
+        let response = self.inner.complete(&prompt).await?;
+        self.budget.record_model_call(&prompt, &response);
+        Ok(response)
+    }
+
+    async fn complete_with_tools(&self, prompt: &str, tools: &[ToolDefinition]) -> Result<ModelResponse, ModelError> {
+        let prompt = self.annotate(prompt);
+        let response = self.inner.complete_with_tools(&prompt, tools).await?;
+        self.budget.record_model_call(&prompt, &response);
+        Ok(response)
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MockModel, TokenUsage};
+
+    #[test]
+    fn test_limits_from_config() {
+        let mut config = AgentConfig::default();
+        config.execution.max_steps = 5;
+        config.execution.timeout_seconds = 0;
+        config.execution.budget.max_tokens = Some(1000);
+
+        let limits = BudgetLimits::from_config(&config);
+        assert_eq!(limits.max_steps, Some(5));
+        assert_eq!(limits.max_duration, None);
+        assert_eq!(limits.max_tokens, Some(1000));
+    }
+
+    #[test]
+    fn test_check_reports_exhausted_resource() {
+        let tracker = BudgetTracker::new(BudgetLimits {
+            max_steps: Some(2),
+            ..BudgetLimits::default()
+        });
+
+        tracker.record_step();
+        assert!(tracker.check().is_ok());
+        tracker.record_step();
+
+        let error = tracker.check().unwrap_err();
+        assert!(matches!(error, AgentError::BudgetExceeded { ref resource, .. } if resource == "steps"));
+        assert_eq!(tracker.usage().exhausted, Some(BudgetResource::Steps));
+    }
+
+    #[test]
+    fn test_cost_and_prompt_notice() {
+        let tracker = BudgetTracker::new(BudgetLimits {
+            max_cost_usd: Some(0.01),
+            prompt_cost_per_1k_tokens: 1.0,
+            completion_cost_per_1k_tokens: 2.0,
+            ..BudgetLimits::default()
+        });
+        assert!(tracker.prompt_notice().is_none());
+
+        let mut response = ModelResponse::text("ok".to_string());
+        response.usage = Some(TokenUsage { prompt_tokens: 5, completion_tokens: 2, total_tokens: 7 });
+        tracker.record_model_call("prompt", &response);
+
+        let usage = tracker.usage();
+        assert_eq!(usage.total_tokens, 7);
+        assert!((usage.cost_usd - 0.009).abs() < 1e-9);
+        assert!(tracker.prompt_notice().unwrap().contains("cost"));
+        assert!(tracker.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_budgeted_model_records_calls() {
+        let budget = Arc::new(BudgetTracker::unlimited());
+        let model = BudgetedModel::new(Arc::new(MockModel::new("mock".to_string())), budget.clone());
+
+        model.complete("hello").await.unwrap();
+        model.complete("world").await.unwrap();
+
+        let usage = budget.usage();
+        assert_eq!(usage.model_calls, 2);
+        assert!(usage.total_tokens > 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_on_wall_time() {
+        let tracker = BudgetTracker::new(BudgetLimits {
+            max_duration: Some(Duration::from_millis(20)),
+            ..BudgetLimits::default()
+        });
+
+        let result: Result<(), AgentError> = tracker
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+
+        assert!(matches!(result, Err(AgentError::BudgetExceeded { .. })));
+        assert_eq!(tracker.usage().exhausted, Some(BudgetResource::WallTime));
+    }
+}
@@ -12,7 +12,12 @@
 
 use crate::errors::{AgentError, CommandOperationError, ToolError};
 use crate::security::{CommandValidator, ResourceLimits};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc;
 
 /// Maximum command output size (1 MB) - kept for backward compatibility
 #[allow(dead_code)]
@@ -22,6 +27,9 @@ const MAX_OUTPUT_SIZE: usize = 1024 * 1024;
 #[allow(dead_code)]
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
+/// Lines buffered between the pipe readers and the consumer of a streamed command
+const STREAM_CHANNEL_CAPACITY: usize = 64;
+
 /// 执行 Shell 命令（带安全验证）
 ///
 /// 此函数会自动进行安全检查和资源限制：
@@ -42,7 +50,8 @@ const DEFAULT_TIMEOUT_SECS: u64 = 30;
 ///   - `SecurityError::DangerousPattern` - 命令包含危险模式
 ///   - `CommandOperationError::ExecutionFailed` - 命令执行失败
 ///   - `CommandOperationError::Timeout` - 命令执行超时
-///   - `CommandOperationError::OutputTooLarge` - 输出超过大小限制
+///
+/// 超过大小限制的输出会被截断，而不是报错。
 ///
 /// # 安全性
 ///
@@ -61,7 +70,7 @@ const DEFAULT_TIMEOUT_SECS: u64 = 30;
 /// # 资源限制
 ///
 /// - 执行超时: 30 秒
-/// - 输出大小: 1 MB
+/// - 输出大小: 1 MB（超出部分截断）
 ///
 /// # 示例
 ///
@@ -102,76 +111,216 @@ pub async fn run_command(command: &str) -> Result<String, AgentError> {
 ///
 /// # Returns
 ///
-/// The command output (stdout), or an error if the command fails.
+/// The command output (stdout), cut at `limits.max_output_size`, or an
+/// error if the command fails.
 pub async fn run_command_with_limits(
     command: &str,
     limits: &ResourceLimits,
 ) -> Result<String, AgentError> {
-    // Validate command for security
+    run_command_streaming(command, limits, |_| {}).await
+}
+
+/// Run a command, passing each output line to `on_line` as it is produced
+///
+/// The command is validated like [`run_command`]. Lines of stdout and
+/// stderr are tagged with their stream and the time they were read. Once
+/// the output reaches `limits.max_output_size` the rest is dropped and a
+/// truncation notice is emitted instead; the command keeps running until it
+/// exits or times out.
+///
+/// # Returns
+///
+/// The command output (stdout), or `CommandOperationError::ExecutionFailed`
+/// with the collected stderr if the command exits with a non-zero code.
+pub async fn run_command_streaming<F>(
+    command: &str,
+    limits: &ResourceLimits,
+    on_line: F,
+) -> Result<String, AgentError>
+where
+    F: FnMut(&OutputLine) + Send,
+{
     let validator = CommandValidator::new();
     validator.validate(command).map_err(|e| {
         AgentError::ToolError(ToolError::CommandOperation(
             CommandOperationError::Security(e)
         ))
     })?;
-    // Execute with timeout
-    let timeout_duration = limits.max_execution_time;
-    let max_output = limits.max_output_size;
 
-    let result = tokio::time::timeout(
-        timeout_duration,
-        tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-    ).await;
+    let output = stream_command(command, None, limits, on_line)
+        .await
+        .map_err(|e| AgentError::ToolError(ToolError::CommandOperation(e)))?;
 
-    let output = match result {
-        Ok(Ok(output)) => output,
-        Ok(Err(e)) => {
-            return Err(AgentError::ToolError(ToolError::CommandOperation(
-                CommandOperationError::IoError {
-                    command: command.to_string(),
-                    message: e.to_string(),
-                }
-            )));
-        }
-        Err(_) => {
-            return Err(AgentError::ToolError(ToolError::CommandOperation(
-                CommandOperationError::Timeout {
-                    seconds: timeout_duration.as_secs(),
+    match output.exit_code {
+        Some(0) => Ok(output.stdout),
+        code => Err(AgentError::ToolError(ToolError::CommandOperation(
+            CommandOperationError::ExecutionFailed {
+                code: code.unwrap_or(-1),
+                stderr: output.stderr,
+            }
+        ))),
+    }
+}
+
+/// Stream a line of command output came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of command output, without its line terminator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputLine {
+    pub stream: OutputStream,
+    pub line: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Everything a streamed command wrote, within the output limit
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the command was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Whether output past `max_output_size` was dropped
+    pub truncated: bool,
+}
+
+/// Run `command` without validation, streaming its output line by line
+///
+/// Callers are responsible for checking the command first. This is the
+/// shared runner behind [`run_command_streaming`] and the `run_command`
+/// tool; it enforces the timeout and output limit of `limits`.
+pub async fn stream_command<F>(
+    command: &str,
+    working_dir: Option<&Path>,
+    limits: &ResourceLimits,
+    mut on_line: F,
+) -> Result<StreamedOutput, CommandOperationError>
+where
+    F: FnMut(&OutputLine) + Send,
+{
+    let mut builder = tokio::process::Command::new("sh");
+    builder
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = working_dir {
+        builder.current_dir(dir);
+    }
+    let mut child = builder.spawn().map_err(|e| CommandOperationError::IoError {
+        command: command.to_string(),
+        message: e.to_string(),
+    })?;
+
+    let max_output = limits.max_output_size;
+    let (sender, mut lines) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, OutputStream::Stdout, max_output, sender.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, OutputStream::Stderr, max_output, sender));
+    }
+
+    let mut output = StreamedOutput::default();
+    let mut size = 0usize;
+    let run = async {
+        while let Some(line) = lines.recv().await {
+            if output.truncated {
+                continue;
+            }
+            size += line.line.len() + 1;
+            let line = if size > max_output {
+                output.truncated = true;
+                OutputLine {
+                    stream: line.stream,
+                    line: format!("[output truncated at {} bytes]", max_output),
+                    timestamp: line.timestamp,
                 }
-            )));
+            } else {
+                line
+            };
+            on_line(&line);
+            let target = match line.stream {
+                OutputStream::Stdout => &mut output.stdout,
+                OutputStream::Stderr => &mut output.stderr,
+            };
+            target.push_str(&line.line);
+            target.push('\n');
         }
+        child.wait().await
     };
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-
-        // Check output size
-        if stdout.len() > max_output {
-            return Err(AgentError::ToolError(ToolError::CommandOperation(
-                CommandOperationError::OutputTooLarge {
-                    size: stdout.len(),
-                    max_size: max_output,
-                }
-            )));
+    match tokio::time::timeout(limits.max_execution_time, run).await {
+        Ok(Ok(status)) => {
+            output.exit_code = status.code();
+            Ok(output)
         }
+        Ok(Err(e)) => Err(CommandOperationError::IoError {
+            command: command.to_string(),
+            message: e.to_string(),
+        }),
+        Err(_) => Err(CommandOperationError::Timeout {
+            seconds: limits.max_execution_time.as_secs(),
+        }),
+    }
+}
 
-        Ok(stdout)
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let code = output.status.code().unwrap_or(-1);
-
-        Err(AgentError::ToolError(ToolError::CommandOperation(
-            CommandOperationError::ExecutionFailed {
-                code,
-                stderr,
+/// Send each line read from `pipe` until it closes
+///
+/// Lines longer than `max_line` bytes are split, so a command that never
+/// writes a newline cannot grow the buffer past the output limit. The
+/// channel is bounded; a slow consumer makes the command wait on its pipe.
+async fn forward_lines(
+    pipe: impl AsyncRead + Unpin,
+    stream: OutputStream,
+    max_line: usize,
+    sender: mpsc::Sender<OutputLine>,
+) {
+    let max_line = max_line.max(1);
+    let mut reader = BufReader::new(pipe);
+    let mut buffer = Vec::new();
+    loop {
+        let chunk = match reader.fill_buf().await {
+            Ok([]) | Err(_) => break,
+            Ok(chunk) => chunk,
+        };
+        let window = &chunk[..chunk.len().min(max_line - buffer.len())];
+        let (used, complete) = match window.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => (newline + 1, true),
+            None => (window.len(), buffer.len() + window.len() >= max_line),
+        };
+        buffer.extend_from_slice(&window[..used]);
+        reader.consume(used);
+        if complete {
+            if !send_line(&sender, stream, &buffer).await {
+                return;
             }
-        )))
+            buffer.clear();
+        }
+    }
+    if !buffer.is_empty() {
+        send_line(&sender, stream, &buffer).await;
     }
 }
 
+/// Whether the consumer is still listening
+async fn send_line(sender: &mpsc::Sender<OutputLine>, stream: OutputStream, bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(bytes);
+    let line = OutputLine {
+        stream,
+        line: text.trim_end_matches(['\n', '\r']).to_string(),
+        timestamp: Utc::now(),
+    };
+    sender.send(line).await.is_ok()
+}
+
 /// Run a command with custom environment variables
 ///
 /// # Arguments
@@ -347,5 +496,42 @@ mod tests {
         let result = run_command("exit 1").await;
         assert!(result.is_err());
     }
-}
 
+    #[tokio::test]
+    async fn test_run_command_streaming_tags_lines() {
+        let mut lines = Vec::new();
+        let output = run_command_streaming(
+            "echo one; echo two >&2; echo three",
+            &ResourceLimits::default(),
+            |line| lines.push((line.stream, line.line.clone())),
+        ).await.unwrap();
+
+        assert_eq!(output, "one\nthree\n");
+        assert_eq!(lines.len(), 3);
+        assert!(lines.contains(&(OutputStream::Stderr, "two".to_string())));
+        let stdout: Vec<_> = lines.iter().filter(|(stream, _)| *stream == OutputStream::Stdout).collect();
+        assert_eq!(stdout, vec![&(OutputStream::Stdout, "one".to_string()), &(OutputStream::Stdout, "three".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_command_truncates_output() {
+        let limits = ResourceLimits { max_output_size: 10, ..ResourceLimits::default() };
+        let mut seen = 0;
+        let output = stream_command("printf 'aaaa\\nbbbb\\ncccc\\ndddd\\n'; exit 3", None, &limits, |_| seen += 1)
+            .await
+            .unwrap();
+
+        assert!(output.truncated);
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.stdout, "aaaa\nbbbb\n[output truncated at 10 bytes]\n");
+        assert_eq!(seen, 3);
+
+        // Output without newlines is split at the limit instead of buffered whole
+        let output = stream_command("head -c 100000 /dev/zero | tr '\\0' x", None, &limits, |_| {}).await.unwrap();
+        assert_eq!((output.stdout.as_str(), output.exit_code), ("[output truncated at 10 bytes]\n", Some(0)));
+
+        // run_command no longer fails on large output
+        let output = run_command_with_limits("echo 0123456789abcdef", &limits).await.unwrap();
+        assert!(output.starts_with("[output truncated"));
+    }
+}
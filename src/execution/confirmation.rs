@@ -0,0 +1,411 @@
+//! 人工确认通道
+//!
+//! 为高风险操作提供可插拔的确认机制：
+//! - [`TerminalConfirmationHandler`]：在终端展示 `ConfirmationRequest::format_prompt` 并读取用户选择
+//! - [`PolicyConfirmationHandler`]：按风险级别自动响应（无人值守场景）
+//! - [`ChannelConfirmationHandler`]：通过异步通道把请求转交给嵌入方自己的前端
+
+use crate::errors::AgentError;
+use crate::execution::guardrails::{
+    ConfirmationOption, ConfirmationRequest, ConfirmationResponse, OperationRiskLevel,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::OnceLock;
+use tokio::sync::{mpsc, oneshot};
+
+// ============================================================================
+// Confirmation Handler
+// ============================================================================
+
+/// 确认处理器
+///
+/// 执行器在需要确认时调用 `confirm`，超时由调用方按
+/// `GuardrailConfig::confirmation_timeout_seconds` 强制执行。
+#[async_trait]
+pub trait ConfirmationHandler: Send + Sync {
+    /// 请求确认并返回用户（或策略）的响应
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<ConfirmationResponse, AgentError>;
+}
+
+/// 构建响应的便捷函数
+pub fn respond(
+    request: &ConfirmationRequest,
+    choice: ConfirmationOption,
+    user_notes: Option<String>,
+    modified_params: Option<HashMap<String, serde_json::Value>>,
+) -> ConfirmationResponse {
+    ConfirmationResponse {
+        id: uuid::Uuid::new_v4().to_string(),
+        request_id: request.id.clone(),
+        choice,
+        responded_at: Utc::now(),
+        user_notes,
+        modified_params,
+    }
+}
+
+// ============================================================================
+// Terminal
+// ============================================================================
+
+/// 终端确认处理器
+///
+/// 输出 `format_prompt()`，从标准输入读取 P/D/S/A/M。选择 Modify 后逐行读取
+/// `key=value` 形式的参数（值按 JSON 解析，失败则作为字符串），空行结束。
+/// 标准输入由一个共用的读取线程统一读取，见 `stdin_lines`。
+#[derive(Debug, Default, Clone)]
+pub struct TerminalConfirmationHandler;
+
+impl TerminalConfirmationHandler {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 解析用户输入的选项
+    pub fn parse_choice(input: &str) -> Option<ConfirmationOption> {
+        match input.trim().to_lowercase().as_str() {
+            "p" | "proceed" | "y" | "yes" => Some(ConfirmationOption::Proceed),
+            "d" | "dry" | "dry-run" | "dryrun" => Some(ConfirmationOption::DryRunFirst),
+            "s" | "skip" => Some(ConfirmationOption::Skip),
+            "a" | "abort" | "n" | "no" => Some(ConfirmationOption::Abort),
+            "m" | "modify" => Some(ConfirmationOption::Modify),
+            _ => None,
+        }
+    }
+
+    /// 解析 `key=value` 形式的参数行
+    pub fn parse_param(line: &str) -> Option<(String, serde_json::Value)> {
+        let (key, value) = line.split_once('=')?;
+        let key = key.trim();
+        if key.is_empty() {
+            return None;
+        }
+        let value = value.trim();
+        let value = serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        Some((key.to_string(), value))
+    }
+
+    /// 展示请求并从 `lines` 逐行读取选择，直到得到有效响应
+    async fn prompt<W: Write + Send>(
+        request: &ConfirmationRequest,
+        lines: &mut mpsc::UnboundedReceiver<String>,
+        out: &mut W,
+    ) -> Result<ConfirmationResponse, AgentError> {
+        let io_error = |e: std::io::Error| AgentError::ExecutionError(format!("Confirmation I/O error: {}", e));
+
+        write!(out, "{}", request.format_prompt()).map_err(io_error)?;
+        out.flush().map_err(io_error)?;
+
+        loop {
+            let Some(line) = lines.recv().await else {
+                // 输入已关闭，按中止处理
+                return Ok(respond(request, ConfirmationOption::Abort, Some("stdin closed".to_string()), None));
+            };
+
+            match Self::parse_choice(&line) {
+                Some(ConfirmationOption::Modify) if request.options.contains(&ConfirmationOption::Modify) => {
+                    writeln!(out, "输入修改参数 (key=value)，空行结束:").map_err(io_error)?;
+                    let mut params = HashMap::new();
+                    while let Some(param_line) = lines.recv().await {
+                        if param_line.trim().is_empty() {
+                            break;
+                        }
+                        match Self::parse_param(&param_line) {
+                            Some((key, value)) => {
+                                params.insert(key, value);
+                            }
+                            None => writeln!(out, "无效参数，格式为 key=value").map_err(io_error)?,
+                        }
+                    }
+                    return Ok(respond(request, ConfirmationOption::Modify, None, Some(params)));
+                }
+                Some(choice) if request.options.contains(&choice) => {
+                    return Ok(respond(request, choice, None, None));
+                }
+                _ => {
+                    write!(out, "无效选择，请重新输入: ").map_err(io_error)?;
+                    out.flush().map_err(io_error)?;
+                }
+            }
+        }
+    }
+}
+
+/// 所有终端确认共用的标准输入读取线程
+///
+/// 线程在第一次确认时启动，此后独占读取标准输入，按行发送到通道。提示只在
+/// 通道上异步等待，确认超时时被丢弃的只是这次等待，不会留下一个仍在读取、
+/// 吞掉用户下一行输入的线程。
+fn stdin_lines() -> &'static tokio::sync::Mutex<mpsc::UnboundedReceiver<String>> {
+    static LINES: OnceLock<tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let stdin = std::io::stdin();
+            loop {
+                let mut line = String::new();
+                match stdin.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Stopped reading confirmations from stdin: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        tokio::sync::Mutex::new(receiver)
+    })
+}
+
+#[async_trait]
+impl ConfirmationHandler for TerminalConfirmationHandler {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<ConfirmationResponse, AgentError> {
+        let mut lines = stdin_lines().lock().await;
+        // 提示出现前输入的行（例如对已超时提示的迟到回答）不能用来回答本次请求
+        while let Ok(line) = lines.try_recv() {
+            tracing::warn!("Discarding input typed before confirmation {}: {:?}", request.id, line.trim());
+        }
+        Self::prompt(request, &mut lines, &mut std::io::stdout()).await
+    }
+}
+
+// ============================================================================
+// Policy
+// ============================================================================
+
+/// 自动确认策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationPolicy {
+    /// 自动批准的最高风险级别
+    pub auto_approve_up_to: OperationRiskLevel,
+    /// 超过阈值时的响应
+    pub on_exceeded: ConfirmationOption,
+    /// 批准前是否先要求 dry-run
+    pub dry_run_first: bool,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self {
+            auto_approve_up_to: OperationRiskLevel::Medium,
+            on_exceeded: ConfirmationOption::Skip,
+            dry_run_first: false,
+        }
+    }
+}
+
+/// 基于策略的自动确认处理器
+#[derive(Debug, Clone, Default)]
+pub struct PolicyConfirmationHandler {
+    policy: ConfirmationPolicy,
+}
+
+impl PolicyConfirmationHandler {
+    pub fn new(policy: ConfirmationPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// 批准所有操作
+    pub fn approve_all() -> Self {
+        Self::new(ConfirmationPolicy {
+            auto_approve_up_to: OperationRiskLevel::Critical,
+            on_exceeded: ConfirmationOption::Proceed,
+            dry_run_first: false,
+        })
+    }
+
+    /// 拒绝所有需要确认的操作
+    pub fn deny_all() -> Self {
+        Self::new(ConfirmationPolicy {
+            auto_approve_up_to: OperationRiskLevel::Safe,
+            on_exceeded: ConfirmationOption::Abort,
+            dry_run_first: false,
+        })
+    }
+
+    /// 根据风险级别决定响应
+    pub fn decide(&self, request: &ConfirmationRequest) -> ConfirmationOption {
+        let risk_level = request.operation_guard.risk_level;
+
+        if risk_level > self.policy.auto_approve_up_to {
+            return self.policy.on_exceeded.clone();
+        }
+
+        // 只有在请求仍提供 dry-run 选项时才要求先模拟
+        if self.policy.dry_run_first && request.options.contains(&ConfirmationOption::DryRunFirst) {
+            ConfirmationOption::DryRunFirst
+        } else {
+            ConfirmationOption::Proceed
+        }
+    }
+}
+
+#[async_trait]
+impl ConfirmationHandler for PolicyConfirmationHandler {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<ConfirmationResponse, AgentError> {
+        let choice = self.decide(request);
+        let notes = format!(
+            "policy: {} risk, auto-approve up to {}",
+            request.operation_guard.risk_level.description(),
+            self.policy.auto_approve_up_to.description()
+        );
+        Ok(respond(request, choice, Some(notes), None))
+    }
+}
+
+// ============================================================================
+// Async Channel
+// ============================================================================
+
+/// 等待响应的确认请求
+#[derive(Debug)]
+pub struct PendingConfirmation {
+    /// 确认请求
+    pub request: ConfirmationRequest,
+    responder: oneshot::Sender<ConfirmationResponse>,
+}
+
+impl PendingConfirmation {
+    /// 回复确认请求
+    pub fn respond(self, response: ConfirmationResponse) -> Result<(), AgentError> {
+        self.responder
+            .send(response)
+            .map_err(|_| AgentError::InvalidState("Confirmation requester is gone".to_string()))
+    }
+
+    /// 以指定选项回复
+    pub fn respond_with(
+        self,
+        choice: ConfirmationOption,
+        modified_params: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), AgentError> {
+        let response = respond(&self.request, choice, None, modified_params);
+        self.respond(response)
+    }
+}
+
+/// 基于异步通道的确认处理器
+///
+/// 请求被发送到 [`PendingConfirmation`] 接收端，由接收方调用 `respond` 回复。
+/// 接收端由嵌入 `SequentialExecutor` 的一方接到自己的前端；服务层目前不运行
+/// `SequentialExecutor`，因此没有接入这个通道。
+#[derive(Debug, Clone)]
+pub struct ChannelConfirmationHandler {
+    sender: mpsc::Sender<PendingConfirmation>,
+}
+
+impl ChannelConfirmationHandler {
+    /// 创建处理器及其请求接收端
+    pub fn new(buffer: usize) -> (Self, mpsc::Receiver<PendingConfirmation>) {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        (Self { sender }, receiver)
+    }
+}
+
+#[async_trait]
+impl ConfirmationHandler for ChannelConfirmationHandler {
+    async fn confirm(&self, request: &ConfirmationRequest) -> Result<ConfirmationResponse, AgentError> {
+        let (responder, response) = oneshot::channel();
+        self.sender
+            .send(PendingConfirmation {
+                request: request.clone(),
+                responder,
+            })
+            .await
+            .map_err(|_| AgentError::InvalidState("Confirmation channel closed".to_string()))?;
+
+        response
+            .await
+            .map_err(|_| AgentError::InvalidState("Confirmation dropped without a response".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::guardrails::{GuardrailEngine, OperationType};
+
+    fn request_for(operation_type: OperationType, description: &str) -> ConfirmationRequest {
+        let engine = GuardrailEngine::default();
+        let guard = engine.check_operation(operation_type, description, vec![]).unwrap();
+        engine.build_confirmation_request(guard)
+    }
+
+    #[test]
+    fn test_parse_terminal_input() {
+        assert_eq!(TerminalConfirmationHandler::parse_choice(" P\n"), Some(ConfirmationOption::Proceed));
+        assert_eq!(TerminalConfirmationHandler::parse_choice("abort"), Some(ConfirmationOption::Abort));
+        assert_eq!(TerminalConfirmationHandler::parse_choice("x"), None);
+
+        let (key, value) = TerminalConfirmationHandler::parse_param("expected_outputs=[\"a.rs\"]").unwrap();
+        assert_eq!(key, "expected_outputs");
+        assert!(value.is_array());
+        let (_, value) = TerminalConfirmationHandler::parse_param("description = plain text").unwrap();
+        assert_eq!(value, serde_json::json!("plain text"));
+    }
+
+    #[tokio::test]
+    async fn test_terminal_prompt_reads_lines_after_timeout() {
+        let request = request_for(OperationType::FileDelete, "delete file");
+        let (sender, mut lines) = mpsc::unbounded_channel();
+        let mut out = Vec::new();
+
+        // 超时丢弃等待后，下一行输入留给下一次提示
+        let waited = tokio::time::timeout(
+            std::time::Duration::from_millis(20),
+            TerminalConfirmationHandler::prompt(&request, &mut lines, &mut out),
+        )
+        .await;
+        assert!(waited.is_err());
+
+        sender.send("x\n".to_string()).unwrap();
+        sender.send("s\n".to_string()).unwrap();
+        let response = TerminalConfirmationHandler::prompt(&request, &mut lines, &mut out).await.unwrap();
+        assert_eq!(response.choice, ConfirmationOption::Skip);
+        assert!(String::from_utf8(out).unwrap().contains("无效选择"));
+
+        drop(sender);
+        let response = TerminalConfirmationHandler::prompt(&request, &mut lines, &mut Vec::new()).await.unwrap();
+        assert_eq!(response.choice, ConfirmationOption::Abort);
+    }
+
+    #[tokio::test]
+    async fn test_policy_handler() {
+        let handler = PolicyConfirmationHandler::default();
+
+        let low = request_for(OperationType::FileRead, "read a file");
+        assert_eq!(handler.confirm(&low).await.unwrap().choice, ConfirmationOption::Proceed);
+
+        let critical = request_for(OperationType::DatabaseDrop, "drop database");
+        let response = handler.confirm(&critical).await.unwrap();
+        assert_eq!(response.choice, ConfirmationOption::Skip);
+        assert_eq!(response.request_id, critical.id);
+    }
+
+    #[tokio::test]
+    async fn test_channel_handler() {
+        let (handler, mut receiver) = ChannelConfirmationHandler::new(4);
+
+        tokio::spawn(async move {
+            let pending = receiver.recv().await.unwrap();
+            let mut params = HashMap::new();
+            params.insert("description".to_string(), serde_json::json!("safer"));
+            pending.respond_with(ConfirmationOption::Modify, Some(params)).unwrap();
+        });
+
+        let request = request_for(OperationType::FileDelete, "delete file");
+        let response = handler.confirm(&request).await.unwrap();
+        assert_eq!(response.choice, ConfirmationOption::Modify);
+        assert_eq!(response.modified_params.unwrap()["description"], "safer");
+    }
+}
@@ -11,6 +11,8 @@
 
 use crate::errors::{AgentError, FileOperationError, ToolError};
 use crate::security::{PathValidator, ResourceLimits};
+use crate::tools::{AppliedEdit, FileEdit};
+use serde::{Deserialize, Serialize};
 use std::io::ErrorKind;
 
 /// Maximum file size for reading (10 MB) - kept for backward compatibility
@@ -43,6 +45,8 @@ const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 /// - 阻止访问敏感目录（如 `/etc`, `/root`）
 /// - 限制文件大小防止内存耗尽
 ///
+/// 只需查看大文件的一部分时使用 [`read_file_range`]，它按行读取并在超出限制时截断。
+///
 /// # 示例
 ///
 /// ```no_run
@@ -129,6 +133,375 @@ pub async fn read_file_with_limits(
     Ok(content)
 }
 
+/// 读取文件的行范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadRange {
+    /// 从第 `offset` 行（从 1 开始）起读取最多 `limit` 行，`None` 表示读到文件末尾
+    Lines { offset: usize, limit: Option<usize> },
+    /// 前 N 行
+    Head(usize),
+    /// 最后 N 行
+    Tail(usize),
+}
+
+impl ReadRange {
+    /// 整个文件
+    pub fn all() -> Self {
+        ReadRange::Lines { offset: 1, limit: None }
+    }
+}
+
+/// 按行读取的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSlice {
+    pub path: String,
+    /// 读取到的行（不含换行符）
+    pub lines: Vec<String>,
+    /// 第一行的行号（从 1 开始）
+    pub start_line: usize,
+    /// 文件总行数
+    pub total_lines: usize,
+    /// 是否因大小限制被截断
+    pub truncated: bool,
+    /// 截断、编码转换等提示
+    pub notices: Vec<String>,
+}
+
+impl FileSlice {
+    /// 最后一行的行号，未读取到任何行时为 `start_line - 1`
+    pub fn end_line(&self) -> usize {
+        self.start_line + self.lines.len() - 1
+    }
+
+    /// 不带行号的内容
+    pub fn content(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// 带行号的内容，格式为 `行号\t内容`
+    pub fn numbered(&self) -> String {
+        let width = self.end_line().max(1).to_string().len();
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{:>width$}\t{}", self.start_line + i, line, width = width))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 按行范围读取文件，适合把大文件的一部分交给模型查看
+///
+/// 与 [`read_file`] 不同：
+/// - 只保留请求的行，总行数照常统计，内存占用与文件大小无关
+/// - 输出超过 `limits.max_file_size` 时截断并在 `notices` 中说明，而不是返回 `FileTooLarge`
+/// - UTF-8 BOM 会被去掉，UTF-16 文件会被转码，无效的 UTF-8 字节被替换并给出提示
+/// - 二进制文件返回 `FileOperationError::BinaryFile`，其中包含文件类型和前几个字节的十六进制预览
+///
+/// 需要完整内容（例如修改后写回）时请使用 [`read_file`]，截断的内容不能写回文件。
+pub async fn read_file_range(
+    path: &str,
+    range: ReadRange,
+    limits: &ResourceLimits,
+) -> Result<FileSlice, AgentError> {
+    PathValidator::validate(path).map_err(|_| {
+        AgentError::ToolError(ToolError::FileOperation(
+            FileOperationError::InvalidPath {
+                path: path.to_string(),
+            }
+        ))
+    })?;
+
+    let path = path.to_string();
+    let max_bytes = limits.max_file_size;
+    tokio::task::spawn_blocking(move || slice_file(&path, range, max_bytes))
+        .await
+        .map_err(|e| AgentError::ExecutionError(e.to_string()))?
+}
+
+/// 用于识别二进制文件和编码的文件头字节数
+const SNIFF_LEN: usize = 8 * 1024;
+
+fn io_error(path: &str, e: std::io::Error) -> AgentError {
+    let error = match e.kind() {
+        ErrorKind::NotFound => FileOperationError::NotFound {
+            path: path.to_string(),
+        },
+        ErrorKind::PermissionDenied => FileOperationError::PermissionDenied {
+            path: path.to_string(),
+        },
+        _ => FileOperationError::IoError {
+            path: path.to_string(),
+            message: e.to_string(),
+        },
+    };
+    AgentError::ToolError(ToolError::FileOperation(error))
+}
+
+fn slice_file(path: &str, range: ReadRange, max_bytes: u64) -> Result<FileSlice, AgentError> {
+    use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path).map_err(|e| io_error(path, e))?;
+    let size = file.metadata().map_err(|e| io_error(path, e))?.len();
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    file.by_ref()
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| io_error(path, e))?;
+
+    let mut collector = LineCollector::new(path, range, max_bytes);
+
+    // UTF-16 文件按码元流式解码，与 UTF-8 一样只保留请求的行
+    let utf16 = match head.as_slice() {
+        [0xFF, 0xFE, ..] => Some(u16::from_le_bytes as fn([u8; 2]) -> u16),
+        [0xFE, 0xFF, ..] => Some(u16::from_be_bytes as fn([u8; 2]) -> u16),
+        _ => None,
+    };
+    if let Some(decode) = utf16 {
+        file.seek(SeekFrom::Start(2)).map_err(|e| io_error(path, e))?;
+        let mut reader = BufReader::new(file);
+        let mut failure = None;
+        let units = std::iter::from_fn(|| {
+            let mut pair = [0u8; 2];
+            match reader.read_exact(&mut pair) {
+                Ok(()) => Some(decode(pair)),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+                Err(e) => {
+                    failure = Some(e);
+                    None
+                }
+            }
+        });
+
+        let mut line = String::new();
+        let mut pending = false;
+        for ch in std::char::decode_utf16(units) {
+            let ch = ch.unwrap_or(char::REPLACEMENT_CHARACTER);
+            if ch == '\n' {
+                let text = std::mem::take(&mut line);
+                collector.push(text.strip_suffix('\r').map(str::to_string).unwrap_or(text));
+                pending = false;
+                continue;
+            }
+            pending = true;
+            if collector.wants_content() {
+                line.push(ch);
+            }
+        }
+        if let Some(e) = failure {
+            return Err(io_error(path, e));
+        }
+        if pending {
+            collector.push(line);
+        }
+        collector.notices.push("Decoded from UTF-16".to_string());
+        return Ok(collector.finish());
+    }
+
+    if let Some(kind) = binary_kind(&head) {
+        let preview = head.iter().take(32).map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+        return Err(AgentError::ToolError(ToolError::FileOperation(FileOperationError::BinaryFile {
+            path: path.to_string(),
+            size,
+            kind: kind.to_string(),
+            preview,
+        })));
+    }
+
+    file.seek(SeekFrom::Start(0)).map_err(|e| io_error(path, e))?;
+    let mut reader = BufReader::new(file);
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        reader.seek_relative(3).map_err(|e| io_error(path, e))?;
+    }
+
+    let mut lossy = false;
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer).map_err(|e| io_error(path, e))? == 0 {
+            break;
+        }
+        let line = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if !collector.wants_content() {
+            collector.skip();
+            continue;
+        }
+        let text = match std::str::from_utf8(line) {
+            Ok(text) => text.to_string(),
+            Err(_) => {
+                lossy = true;
+                String::from_utf8_lossy(line).into_owned()
+            }
+        };
+        collector.push(text);
+    }
+    if lossy {
+        collector.notices.push("File is not valid UTF-8; invalid bytes were replaced".to_string());
+    }
+
+    Ok(collector.finish())
+}
+
+/// 二进制文件的类型，文本文件返回 `None`
+fn binary_kind(head: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x7fELF", "ELF executable"),
+        (b"\x89PNG", "PNG image"),
+        (b"\xff\xd8\xff", "JPEG image"),
+        (b"GIF8", "GIF image"),
+        (b"%PDF", "PDF document"),
+        (b"PK\x03\x04", "ZIP archive"),
+        (b"\x1f\x8b", "gzip archive"),
+        (b"\0asm", "WebAssembly module"),
+    ];
+
+    if let Some((_, kind)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(kind);
+    }
+    head.contains(&0).then_some("unknown binary data")
+}
+
+/// 在字节预算内保留 [`ReadRange`] 选中的行
+struct LineCollector {
+    path: String,
+    range: ReadRange,
+    max_bytes: u64,
+    lines: std::collections::VecDeque<String>,
+    bytes: u64,
+    /// 第一条保留行的行号
+    first: usize,
+    total: usize,
+    truncated: bool,
+    notices: Vec<String>,
+}
+
+impl LineCollector {
+    fn new(path: &str, range: ReadRange, max_bytes: u64) -> Self {
+        let first = match range {
+            ReadRange::Lines { offset, .. } => offset.max(1),
+            _ => 1,
+        };
+        Self {
+            path: path.to_string(),
+            range,
+            max_bytes,
+            lines: std::collections::VecDeque::new(),
+            bytes: 0,
+            first,
+            total: 0,
+            truncated: false,
+            notices: Vec::new(),
+        }
+    }
+
+    /// 下一行是否可能被保留；其他行只计数
+    fn wants_content(&self) -> bool {
+        let line = self.total + 1;
+        match self.range {
+            ReadRange::Lines { offset, limit } => {
+                !self.truncated && line >= offset && limit.is_none_or(|limit| line < offset.max(1) + limit)
+            }
+            ReadRange::Head(count) => !self.truncated && line <= count,
+            ReadRange::Tail(_) => true,
+        }
+    }
+
+    fn skip(&mut self) {
+        self.total += 1;
+    }
+
+    fn push(&mut self, line: String) {
+        if !self.wants_content() {
+            self.skip();
+            return;
+        }
+        self.total += 1;
+
+        let cost = line.len() as u64 + 1;
+        if let ReadRange::Tail(count) = self.range {
+            self.lines.push_back(line);
+            self.bytes += cost;
+            if self.lines.len() > count {
+                self.pop_front();
+            }
+            while self.bytes > self.max_bytes && self.lines.len() > 1 {
+                self.pop_front();
+                self.truncated = true;
+            }
+            if self.bytes > self.max_bytes {
+                // 只剩一行仍超出预算时截断该行
+                if let Some(last) = self.lines.back_mut() {
+                    truncate_line(last, self.max_bytes);
+                    self.bytes = last.len() as u64 + 1;
+                }
+                self.truncated = true;
+            }
+            return;
+        }
+
+        if self.bytes + cost > self.max_bytes {
+            // 单行就超出预算时至少返回截断后的这一行
+            if self.lines.is_empty() {
+                let mut line = line;
+                truncate_line(&mut line, self.max_bytes);
+                self.bytes = line.len() as u64 + 1;
+                self.lines.push_back(line);
+            }
+            self.truncated = true;
+            return;
+        }
+        self.bytes += cost;
+        self.lines.push_back(line);
+    }
+
+    fn pop_front(&mut self) {
+        if let Some(dropped) = self.lines.pop_front() {
+            self.bytes -= dropped.len() as u64 + 1;
+        }
+    }
+
+    fn finish(mut self) -> FileSlice {
+        if let ReadRange::Tail(_) = self.range {
+            self.first = self.total - self.lines.len() + 1;
+        }
+        if self.truncated {
+            self.notices.push(format!(
+                "Output truncated at {} bytes; showing lines {}-{} of {}. Use offset/limit to read the rest.",
+                self.max_bytes,
+                self.first,
+                self.first + self.lines.len() - 1,
+                self.total
+            ));
+        }
+        if self.lines.is_empty() && self.total > 0 && self.first > self.total {
+            self.notices.push(format!(
+                "Line {} is past the end of {} ({} lines)",
+                self.first, self.path, self.total
+            ));
+        }
+
+        FileSlice {
+            path: self.path,
+            lines: self.lines.into(),
+            start_line: self.first,
+            total_lines: self.total,
+            truncated: self.truncated,
+            notices: self.notices,
+        }
+    }
+}
+
+/// 把一行截断到 `max_bytes` 以内（含换行符），并保持在字符边界上
+fn truncate_line(line: &mut String, max_bytes: u64) {
+    let mut end = (max_bytes.saturating_sub(1) as usize).min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line.truncate(end);
+}
+
 /// 将内容写入指定路径的文件
 ///
 /// 此函数会自动进行安全检查：
@@ -198,6 +571,30 @@ pub async fn write_file(path: &str, content: &str) -> Result<(), AgentError> {
     Ok(())
 }
 
+/// 对文件进行局部编辑（search/replace 块或 unified diff）
+///
+/// 编辑要么全部成功，要么不写入任何内容。文件不存在时按空文件处理，
+/// 因此空的 search 块或 `@@ -0,0` 的 diff 可以创建新文件。
+///
+/// # 返回
+///
+/// * `Ok(AppliedEdit)` - 新内容、增删行数以及模糊匹配/偏移说明
+/// * `Err(AgentError)` - 路径非法、读写失败，或有块/hunk 无法应用
+///   （错误信息逐条列出失败的块或 hunk）
+pub async fn edit_file(path: &str, edit: &FileEdit) -> Result<AppliedEdit, AgentError> {
+    let original = if file_exists(path).await {
+        read_file(path).await?
+    } else {
+        String::new()
+    };
+
+    let applied = edit.apply(&original)
+        .map_err(|e| AgentError::ToolError(ToolError::ExecutionError(format!("{}: {}", path, e))))?;
+
+    write_file(path, &applied.content).await?;
+    Ok(applied)
+}
+
 /// 列出目录中的所有文件和子目录
 ///
 /// 返回格式化的文件列表，每行一个条目，格式为 "类型: 名称"。
@@ -368,5 +765,94 @@ mod tests {
         // Cleanup
         let _ = tokio::fs::remove_file(test_file).await;
     }
+
+    #[tokio::test]
+    async fn test_read_file_range_modes_and_truncation() {
+        let test_file = "test_temp_range_file.txt";
+        let content: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+        write_file(test_file, &content).await.unwrap();
+        let limits = ResourceLimits::default();
+
+        let slice = read_file_range(test_file, ReadRange::Lines { offset: 10, limit: Some(3) }, &limits).await.unwrap();
+        assert_eq!(slice.lines, vec!["line 10", "line 11", "line 12"]);
+        assert_eq!((slice.start_line, slice.end_line(), slice.total_lines), (10, 12, 100));
+        assert!(slice.numbered().starts_with("10\tline 10"));
+
+        let slice = read_file_range(test_file, ReadRange::Tail(2), &limits).await.unwrap();
+        assert_eq!((slice.start_line, slice.content().as_str()), (99, "line 99\nline 100"));
+
+        let small = ResourceLimits { max_file_size: 40, ..ResourceLimits::default() };
+        let slice = read_file_range(test_file, ReadRange::all(), &small).await.unwrap();
+        assert!(slice.truncated);
+        assert_eq!(slice.lines.len(), 5);
+        assert!(slice.notices[0].contains("lines 1-5 of 100"));
+
+        let _ = tokio::fs::remove_file(test_file).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_file_range_detects_binary_and_utf16() {
+        let binary = "test_temp_binary_file.bin";
+        tokio::fs::write(binary, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").await.unwrap();
+        let error = read_file_range(binary, ReadRange::all(), &ResourceLimits::default()).await.unwrap_err();
+        assert!(error.to_string().contains("PNG image"));
+        assert!(error.to_string().contains("89 50 4e 47"));
+        let _ = tokio::fs::remove_file(binary).await;
+
+        let utf16 = "test_temp_utf16_file.txt";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("héllo\nwörld\n".encode_utf16().flat_map(u16::to_le_bytes));
+        tokio::fs::write(utf16, bytes).await.unwrap();
+        let slice = read_file_range(utf16, ReadRange::all(), &ResourceLimits::default()).await.unwrap();
+        assert_eq!(slice.lines, vec!["héllo", "wörld"]);
+        assert_eq!(slice.notices, vec!["Decoded from UTF-16"]);
+
+        let slice = read_file_range(utf16, ReadRange::Lines { offset: 2, limit: Some(1) }, &ResourceLimits::default()).await.unwrap();
+        assert_eq!((slice.lines, slice.start_line, slice.total_lines), (vec!["wörld".to_string()], 2, 2));
+        let _ = tokio::fs::remove_file(utf16).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_file_range_edge_cases() {
+        let test_file = "test_temp_range_edge_file.txt";
+        write_file(test_file, "").await.unwrap();
+        let slice = read_file_range(test_file, ReadRange::all(), &ResourceLimits::default()).await.unwrap();
+        assert!(slice.lines.is_empty());
+        assert_eq!((slice.total_lines, slice.end_line()), (0, 0));
+        assert!(slice.notices.is_empty());
+
+        let long = "é".repeat(40);
+        write_file(test_file, &format!("{}\nshort\n", long)).await.unwrap();
+        let small = ResourceLimits { max_file_size: 10, ..ResourceLimits::default() };
+        let slice = read_file_range(test_file, ReadRange::all(), &small).await.unwrap();
+        assert!(slice.truncated);
+        assert_eq!(slice.lines, vec!["éééé"]);
+        assert!(slice.notices[0].contains("lines 1-1 of 2"));
+
+        let slice = read_file_range(test_file, ReadRange::Tail(2), &small).await.unwrap();
+        assert_eq!((slice.lines, slice.start_line), (vec!["short".to_string()], 2));
+        write_file(test_file, &format!("short\n{}\n", long)).await.unwrap();
+        let slice = read_file_range(test_file, ReadRange::Tail(1), &small).await.unwrap();
+        assert_eq!((slice.lines, slice.start_line), (vec!["éééé".to_string()], 2));
+
+        let _ = tokio::fs::remove_file(test_file).await;
+    }
+
+    #[tokio::test]
+    async fn test_edit_file_is_all_or_nothing() {
+        let test_file = "test_temp_edit_file.txt";
+        write_file(test_file, "one\ntwo\nthree\n").await.unwrap();
+
+        let edit = FileEdit::parse_diff("@@ -2 +2 @@\n-two\n+2\n@@ -9 +9 @@\n-nine\n+9\n").unwrap();
+        assert!(edit_file(test_file, &edit).await.is_err());
+        assert_eq!(read_file(test_file).await.unwrap(), "one\ntwo\nthree\n");
+
+        let edit = FileEdit::parse_diff("@@ -2 +2 @@\n-two\n+2\n").unwrap();
+        let applied = edit_file(test_file, &edit).await.unwrap();
+        assert_eq!(applied.changes.total(), 2);
+        assert_eq!(read_file(test_file).await.unwrap(), "one\n2\nthree\n");
+
+        let _ = tokio::fs::remove_file(test_file).await;
+    }
 }
 
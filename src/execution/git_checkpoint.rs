@@ -0,0 +1,570 @@
+//! 基于 git 的检查点后端
+//!
+//! 每个成功的步骤在隐藏引用 `refs/agent-runner/checkpoints/<task_id>` 上记录为一次提交，
+//! 提交信息包含步骤名称、步骤ID和任务ID。提交通过临时索引文件生成，不会改动用户的
+//! 索引和分支；任务结束后可以回滚到任意步骤，或将全部改动压缩为一次提交放到用户分支上。
+
+use crate::errors::AgentError;
+use crate::execution::guardrails::{RollbackAction, RollbackPlan, RollbackStep};
+use crate::execution::sequential::StepExecutionOutput;
+use crate::execution::snapshot::{SnapshotBackend, SnapshotScope};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 检查点提交使用的作者信息
+const CHECKPOINT_AUTHOR_NAME: &str = "agent-runner";
+const CHECKPOINT_AUTHOR_EMAIL: &str = "agent-runner@localhost";
+
+/// 提交信息中的步骤与任务标记
+const STEP_ID_TRAILER: &str = "Step-Id: ";
+const TASK_ID_TRAILER: &str = "Task-Id: ";
+
+/// 单个步骤的检查点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitCheckpoint {
+    /// 提交 SHA
+    pub commit: String,
+    /// 步骤ID
+    pub step_id: String,
+    /// 步骤名称
+    pub step_name: String,
+    /// 任务ID
+    pub task_id: String,
+}
+
+/// git 检查点后端
+///
+/// 任务的第一个快照会检查工作区：存在未提交的改动时拒绝运行，除非调用了 `force(true)`
+/// （此时这些改动会作为基线提交记录在隐藏引用上）。
+pub struct GitCheckpointBackend {
+    repo: PathBuf,
+    ref_prefix: String,
+    force: bool,
+}
+
+impl GitCheckpointBackend {
+    /// 为 `repo` 创建检查点后端
+    pub fn new(repo: impl Into<PathBuf>) -> Self {
+        Self {
+            repo: repo.into(),
+            ref_prefix: "refs/agent-runner".to_string(),
+            force: false,
+        }
+    }
+
+    /// 工作区有未提交的改动时仍然运行
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// 设置隐藏引用的前缀（默认 `refs/agent-runner`）
+    pub fn with_ref_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.ref_prefix = prefix.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// 任务检查点所在的引用
+    pub fn checkpoint_ref(&self, task_id: &str) -> String {
+        format!("{}/checkpoints/{}", self.ref_prefix, task_id)
+    }
+
+    fn base_ref(&self, task_id: &str) -> String {
+        format!("{}/bases/{}", self.ref_prefix, task_id)
+    }
+
+    /// 列出任务的所有步骤检查点（按执行顺序）
+    pub async fn checkpoints(&self, task_id: &str) -> Result<Vec<GitCheckpoint>, AgentError> {
+        let tip = self.resolve(&self.checkpoint_ref(task_id)).await?;
+        let base = self.resolve(&self.base_ref(task_id)).await?;
+        let (tip, base) = match (tip, base) {
+            (Some(tip), Some(base)) => (tip, base),
+            _ => return Ok(Vec::new()),
+        };
+
+        let range = format!("{}..{}", base, tip);
+        let log = self
+            .git(&["log", "--reverse", "--format=%H%x1f%B%x1e", &range], None)
+            .await?;
+
+        let mut checkpoints = Vec::new();
+        for record in log.split('\x1e') {
+            let Some((commit, message)) = record.trim().split_once('\x1f') else {
+                continue;
+            };
+            let trailer = |prefix: &str| {
+                message
+                    .lines()
+                    .find_map(|line| line.strip_prefix(prefix))
+                    .map(|value| value.trim().to_string())
+            };
+            if let (Some(step_id), Some(task)) = (trailer(STEP_ID_TRAILER), trailer(TASK_ID_TRAILER)) {
+                checkpoints.push(GitCheckpoint {
+                    commit: commit.trim().to_string(),
+                    step_id,
+                    step_name: message.lines().next().unwrap_or_default().trim().to_string(),
+                    task_id: task,
+                });
+            }
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// 将工作区回滚到某个步骤完成时的状态，之后的检查点被丢弃
+    pub async fn rollback_to_step(&self, task_id: &str, step_id: &str) -> Result<GitCheckpoint, AgentError> {
+        let checkpoint = self
+            .checkpoints(task_id)
+            .await?
+            .into_iter()
+            .find(|checkpoint| checkpoint.step_id == step_id)
+            .ok_or_else(|| AgentError::InvalidState(format!("No checkpoint for step {} in task {}", step_id, task_id)))?;
+
+        self.restore_commit(&checkpoint.commit).await?;
+        self.git(&["update-ref", &self.checkpoint_ref(task_id), &checkpoint.commit], None)
+            .await?;
+
+        Ok(checkpoint)
+    }
+
+    /// 将工作区回滚到任务开始前的状态
+    pub async fn rollback_to_start(&self, task_id: &str) -> Result<(), AgentError> {
+        let base = self.require_ref(&self.base_ref(task_id)).await?;
+        self.restore_commit(&base).await?;
+        self.git(&["update-ref", &self.checkpoint_ref(task_id), &base], None).await?;
+        Ok(())
+    }
+
+    /// 将任务的全部改动压缩为一次提交放到当前分支上，返回新提交的 SHA
+    ///
+    /// 要求用户分支自任务开始后没有移动；成功后隐藏引用被删除。
+    pub async fn squash_onto_branch(&self, task_id: &str, message: &str) -> Result<String, AgentError> {
+        let tip = self.require_ref(&self.checkpoint_ref(task_id)).await?;
+        let base = self.require_ref(&self.base_ref(task_id)).await?;
+        let head = self.git(&["rev-parse", "HEAD"], None).await?;
+        let head = head.trim();
+
+        let base_head = self.first_parent_outside_task(&base, task_id).await?;
+        if head != base_head {
+            return Err(AgentError::InvalidState(format!(
+                "HEAD moved from {} to {} since task {} started; refusing to squash",
+                base_head, head, task_id
+            )));
+        }
+
+        let tree = self.git(&["rev-parse", &format!("{}^{{tree}}", tip)], None).await?;
+        let commit = self
+            .git(&["commit-tree", tree.trim(), "-p", head, "-m", message], None)
+            .await?;
+        let commit = commit.trim().to_string();
+
+        let reflog = format!("agent-runner: squash task {}", task_id);
+        self.git(&["update-ref", "-m", &reflog, "HEAD", &commit, head], None).await?;
+        // 工作区已与新提交一致，只需同步索引
+        self.git(&["reset", "-q"], None).await?;
+        self.discard(task_id).await?;
+
+        Ok(commit)
+    }
+
+    /// 删除任务的隐藏引用
+    pub async fn discard(&self, task_id: &str) -> Result<(), AgentError> {
+        for reference in [self.checkpoint_ref(task_id), self.base_ref(task_id)] {
+            if self.resolve(&reference).await?.is_some() {
+                self.git(&["update-ref", "-d", &reference], None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 任务第一次快照时检查工作区并记录基线
+    async fn ensure_started(&self, task_id: &str) -> Result<String, AgentError> {
+        if let Some(tip) = self.resolve(&self.checkpoint_ref(task_id)).await? {
+            return Ok(tip);
+        }
+
+        let head = self
+            .git(&["rev-parse", "--verify", "HEAD"], None)
+            .await
+            .map_err(|e| AgentError::InvalidState(format!("Git checkpoints need a repository with at least one commit: {}", e)))?;
+        let head = head.trim().to_string();
+
+        let status = self.git(&["status", "--porcelain"], None).await?;
+        let base = if status.trim().is_empty() {
+            head
+        } else if self.force {
+            tracing::warn!("Working tree has uncommitted changes; recording them as the checkpoint baseline");
+            let message = format!("agent-runner baseline\n\n{}{}", TASK_ID_TRAILER, task_id);
+            self.commit_worktree(&head, &message).await?
+        } else {
+            return Err(AgentError::InvalidState(format!(
+                "Working tree has uncommitted changes; commit or stash them first, or force the git checkpoint backend:\n{}",
+                status.trim_end()
+            )));
+        };
+
+        self.git(&["update-ref", &self.base_ref(task_id), &base], None).await?;
+        self.git(&["update-ref", &self.checkpoint_ref(task_id), &base], None).await?;
+        Ok(base)
+    }
+
+    /// 基线提交可能是强制模式下的未提交改动，找到任务开始时真正的 HEAD
+    async fn first_parent_outside_task(&self, base: &str, task_id: &str) -> Result<String, AgentError> {
+        let message = self.git(&["log", "-1", "--format=%B", base], None).await?;
+        let is_baseline = message
+            .lines()
+            .any(|line| line.strip_prefix(TASK_ID_TRAILER).map(str::trim) == Some(task_id));
+        if is_baseline {
+            let parent = self.git(&["rev-parse", &format!("{}^", base)], None).await?;
+            Ok(parent.trim().to_string())
+        } else {
+            Ok(base.to_string())
+        }
+    }
+
+    /// 将当前工作区（遵循 .gitignore）提交为 `parent` 的子提交，不改动用户索引
+    async fn commit_worktree(&self, parent: &str, message: &str) -> Result<String, AgentError> {
+        let index = TempIndex::new();
+        self.git(&["read-tree", parent], Some(&index)).await?;
+        self.git(&["add", "-A", "--", "."], Some(&index)).await?;
+        let tree = self.git(&["write-tree"], Some(&index)).await?;
+
+        let output = self
+            .command(&["commit-tree", tree.trim(), "-p", parent, "-m", message], None)
+            .env("GIT_AUTHOR_NAME", CHECKPOINT_AUTHOR_NAME)
+            .env("GIT_AUTHOR_EMAIL", CHECKPOINT_AUTHOR_EMAIL)
+            .env("GIT_COMMITTER_NAME", CHECKPOINT_AUTHOR_NAME)
+            .env("GIT_COMMITTER_EMAIL", CHECKPOINT_AUTHOR_EMAIL)
+            .output()
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to run git: {}", e)))?;
+        Ok(Self::stdout(&["commit-tree"], output)?.trim().to_string())
+    }
+
+    /// 工作区中相对于 `commit` 有变化的路径（仓库相对路径）
+    async fn changed_paths(&self, commit: &str) -> Result<Vec<String>, AgentError> {
+        let index = TempIndex::new();
+        self.git(&["read-tree", commit], Some(&index)).await?;
+        self.git(&["add", "-A", "--", "."], Some(&index)).await?;
+        let diff = self
+            .git(&["diff", "--cached", "--name-only", "--no-renames", "-z", commit], Some(&index))
+            .await?;
+        Ok(diff.split('\0').filter(|path| !path.is_empty()).map(str::to_string).collect())
+    }
+
+    /// 将工作区恢复到 `commit` 的状态
+    async fn restore_commit(&self, commit: &str) -> Result<(), AgentError> {
+        let plan = self.rollback_plan(commit, None).await?;
+        self.execute_rollback(&plan).await
+    }
+
+    /// 将路径转换为仓库相对路径；仓库外的路径返回 `None`
+    fn repo_relative(&self, path: &str) -> Option<String> {
+        let path = Path::new(path);
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().ok()?.join(path)
+        };
+        let repo = self.repo.canonicalize().ok()?;
+        let absolute = match absolute.parent().and_then(|parent| parent.canonicalize().ok()) {
+            Some(parent) => parent.join(absolute.file_name()?),
+            None => absolute,
+        };
+        absolute
+            .strip_prefix(&repo)
+            .ok()
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    async fn exists_in(&self, commit: &str, relative: &str) -> Result<bool, AgentError> {
+        let spec = format!("{}:{}", commit, relative);
+        let output = self
+            .command(&["cat-file", "-e", &spec], None)
+            .output()
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to run git: {}", e)))?;
+        Ok(output.status.success())
+    }
+
+    async fn resolve(&self, reference: &str) -> Result<Option<String>, AgentError> {
+        let output = self
+            .command(&["rev-parse", "--verify", "-q", reference], None)
+            .output()
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to run git: {}", e)))?;
+        if output.status.success() {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn require_ref(&self, reference: &str) -> Result<String, AgentError> {
+        self.resolve(reference)
+            .await?
+            .ok_or_else(|| AgentError::InvalidState(format!("Git reference not found: {}", reference)))
+    }
+
+    fn command(&self, args: &[&str], index: Option<&TempIndex>) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new("git");
+        cmd.args(args).current_dir(&self.repo);
+        if let Some(index) = index {
+            cmd.env("GIT_INDEX_FILE", &index.0);
+        }
+        cmd
+    }
+
+    async fn git(&self, args: &[&str], index: Option<&TempIndex>) -> Result<String, AgentError> {
+        let output = self
+            .command(args, index)
+            .output()
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to run git: {}", e)))?;
+        Self::stdout(args, output)
+    }
+
+    fn stdout(args: &[&str], output: std::process::Output) -> Result<String, AgentError> {
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(AgentError::ExecutionError(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
+
+#[async_trait]
+impl SnapshotBackend for GitCheckpointBackend {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn captures_workspace(&self) -> bool {
+        true
+    }
+
+    /// 快照ID为步骤开始前的检查点提交
+    async fn create_snapshot(&self, scope: &SnapshotScope, _paths: &[String]) -> Result<String, AgentError> {
+        self.ensure_started(&scope.task_id).await
+    }
+
+    async fn checkpoint(&self, scope: &SnapshotScope, _output: &StepExecutionOutput) -> Result<(), AgentError> {
+        let reference = self.checkpoint_ref(&scope.task_id);
+        let tip = self.ensure_started(&scope.task_id).await?;
+        let message = format!(
+            "{}\n\n{}{}\n{}{}",
+            scope.step_name, STEP_ID_TRAILER, scope.step_id, TASK_ID_TRAILER, scope.task_id
+        );
+        let commit = self.commit_worktree(&tip, &message).await?;
+        self.git(&["update-ref", &reference, &commit, &tip], None).await?;
+        Ok(())
+    }
+
+    async fn rollback_plan(
+        &self,
+        snapshot_id: &str,
+        touched_paths: Option<&[String]>,
+    ) -> Result<RollbackPlan, AgentError> {
+        let paths: Vec<(String, String)> = match touched_paths {
+            Some(paths) => paths
+                .iter()
+                .filter_map(|path| match self.repo_relative(path) {
+                    Some(relative) => Some((path.clone(), relative)),
+                    None => {
+                        tracing::warn!("'{}' is outside the repository and cannot be checkpointed", path);
+                        None
+                    }
+                })
+                .collect(),
+            None => self
+                .changed_paths(snapshot_id)
+                .await?
+                .into_iter()
+                .map(|relative| (self.repo.join(&relative).to_string_lossy().to_string(), relative))
+                .collect(),
+        };
+
+        let mut plan = RollbackPlan::new();
+        for (sequence, (path, relative)) in paths.into_iter().enumerate() {
+            let (description, action) = if self.exists_in(snapshot_id, &relative).await? {
+                (
+                    format!("恢复 {}", relative),
+                    RollbackAction::RestoreFile { path, snapshot_id: snapshot_id.to_string() },
+                )
+            } else {
+                (format!("删除 {}", relative), RollbackAction::DeleteFile { path })
+            };
+
+            plan.add_step(RollbackStep {
+                id: uuid::Uuid::new_v4().to_string(),
+                description,
+                action,
+                sequence,
+            });
+        }
+
+        Ok(plan)
+    }
+
+    async fn execute_rollback(&self, plan: &RollbackPlan) -> Result<(), AgentError> {
+        for step in plan.steps_reversed() {
+            match &step.action {
+                RollbackAction::RestoreFile { path, snapshot_id }
+                | RollbackAction::RestoreConfig { path, snapshot_id } => {
+                    let relative = self.repo_relative(path).ok_or_else(|| {
+                        AgentError::InvalidState(format!("'{}' is outside the repository", path))
+                    })?;
+                    // 通过临时索引检出，保留文件模式且不影响用户索引
+                    let index = TempIndex::new();
+                    self.git(&["read-tree", snapshot_id], Some(&index)).await?;
+                    self.git(&["checkout-index", "-f", "--", &relative], Some(&index)).await?;
+                }
+                RollbackAction::DeleteFile { path } => match tokio::fs::remove_file(path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(AgentError::ExecutionError(format!("Failed to delete '{}': {}", path, e)));
+                    }
+                },
+                other => {
+                    tracing::warn!("Unsupported rollback action for git checkpoints: {:?}", other);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 保留隐藏引用，以便任务结束后回滚或压缩提交；不再需要时调用 `discard`
+    async fn finish(&self, _task_id: &str) -> Result<(), AgentError> {
+        Ok(())
+    }
+}
+
+/// 临时索引文件，离开作用域时删除
+struct TempIndex(PathBuf);
+
+impl TempIndex {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("agent-runner-index-{}", uuid::Uuid::new_v4())))
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use crate::execution::snapshot::touched_files;
+    use std::collections::HashMap;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn init_repo() -> TempDir {
+        let repo = TempDir::new("git_checkpoint");
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.name", "Test"]);
+        git(&repo, &["config", "user.email", "test@example.com"]);
+        std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "-q", "-m", "initial"]);
+        repo
+    }
+
+    fn scope(step: &str) -> SnapshotScope {
+        SnapshotScope {
+            task_id: "task-1".to_string(),
+            step_id: step.to_string(),
+            step_name: format!("Step {}", step),
+        }
+    }
+
+    fn output(modified: &[String]) -> StepExecutionOutput {
+        StepExecutionOutput {
+            step_id: "step".to_string(),
+            status: crate::execution::sequential::ExecutionStatus::Success,
+            outputs: HashMap::new(),
+            logs: vec![],
+            generated_files: vec![],
+            modified_files: modified.to_vec(),
+            line_changes: HashMap::new(),
+            executed_commands: vec![],
+            snapshot_id: None,
+            rollback_plan: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_rollback_and_squash() {
+        let repo = init_repo();
+        let backend = GitCheckpointBackend::new(&repo);
+        let readme = repo.join("README.md").to_string_lossy().to_string();
+        let added = repo.join("added.txt").to_string_lossy().to_string();
+
+        // 步骤 a：修改 README
+        backend.create_snapshot(&scope("a"), &[]).await.unwrap();
+        std::fs::write(&readme, "changed\n").unwrap();
+        backend.checkpoint(&scope("a"), &output(std::slice::from_ref(&readme))).await.unwrap();
+
+        // 步骤 b：新增文件，生成的回滚计划会删除它
+        let before_b = backend.create_snapshot(&scope("b"), &[]).await.unwrap();
+        std::fs::write(&added, "new\n").unwrap();
+        let step_b = output(std::slice::from_ref(&added));
+        let plan = backend.rollback_plan(&before_b, Some(&touched_files(&step_b))).await.unwrap();
+        assert!(matches!(plan.steps[0].action, RollbackAction::DeleteFile { .. }));
+        backend.checkpoint(&scope("b"), &step_b).await.unwrap();
+
+        let checkpoints = backend.checkpoints("task-1").await.unwrap();
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[0].step_id, "a");
+        assert_eq!(checkpoints[1].step_name, "Step b");
+        // 用户分支与索引保持不变
+        assert_eq!(git(&repo, &["log", "--format=%s"]), "initial");
+
+        let restored = backend.rollback_to_step("task-1", "a").await.unwrap();
+        assert_eq!(restored.step_id, "a");
+        assert!(!Path::new(&added).exists());
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "changed\n");
+
+        backend.squash_onto_branch("task-1", "Apply agent changes").await.unwrap();
+        assert_eq!(git(&repo, &["log", "--format=%s", "-1"]), "Apply agent changes");
+        assert!(git(&repo, &["status", "--porcelain"]).is_empty());
+        assert!(backend.checkpoints("task-1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_dirty_tree_unless_forced() {
+        let repo = init_repo();
+        std::fs::write(repo.join("README.md"), "uncommitted\n").unwrap();
+
+        let error = GitCheckpointBackend::new(&repo)
+            .create_snapshot(&scope("a"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AgentError::InvalidState(_)));
+
+        let forced = GitCheckpointBackend::new(&repo).force(true);
+        forced.create_snapshot(&scope("a"), &[]).await.unwrap();
+        forced.rollback_to_start("task-1").await.unwrap();
+        assert_eq!(std::fs::read_to_string(repo.join("README.md")).unwrap(), "uncommitted\n");
+        forced.discard("task-1").await.unwrap();
+    }
+}
@@ -464,9 +464,35 @@ pub struct ConfirmationRequest {
     pub timeout_at: DateTime<Utc>,
     /// 确认选项
     pub options: Vec<ConfirmationOption>,
+    /// 已完成的 dry-run 结果（选择 DryRunFirst 后再次确认时提供）
+    #[serde(default)]
+    pub dry_run: Option<DryRunResult>,
 }
 
 impl ConfirmationRequest {
+    /// 创建确认请求
+    pub fn new(operation_guard: OperationGuard, timeout_seconds: u64, allow_dry_run: bool) -> Self {
+        let requested_at = Utc::now();
+        let mut options = vec![ConfirmationOption::Proceed];
+        if allow_dry_run {
+            options.push(ConfirmationOption::DryRunFirst);
+        }
+        options.extend([
+            ConfirmationOption::Skip,
+            ConfirmationOption::Abort,
+            ConfirmationOption::Modify,
+        ]);
+
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            operation_guard,
+            requested_at,
+            timeout_at: requested_at + chrono::Duration::seconds(timeout_seconds as i64),
+            options,
+            dry_run: None,
+        }
+    }
+
     /// 格式化为用户友好的提示信息
     pub fn format_prompt(&self) -> String {
         let guard = &self.operation_guard;
@@ -542,13 +568,40 @@ impl ConfirmationRequest {
             prompt.push_str("回滚计划: ❌ 无法回滚\n\n");
         }
 
+        // Dry-run 结果
+        if let Some(dry_run) = &self.dry_run {
+            prompt.push_str(&format!(
+                "模拟结果: {}\n",
+                if dry_run.success { "✅ 可以执行" } else { "❌ 存在问题" }
+            ));
+            for action in &dry_run.planned_actions {
+                let status = if action.would_succeed { "✅" } else { "❌" };
+                prompt.push_str(&format!("  {} {}: {}\n", status, action.action_type, action.description));
+                if let Some(reason) = &action.failure_reason {
+                    prompt.push_str(&format!("     原因: {}\n", reason));
+                }
+            }
+            for warning in &dry_run.warnings {
+                prompt.push_str(&format!("  ⚠️  {}\n", warning));
+            }
+            prompt.push('\n');
+        }
+
         // 选项
         prompt.push_str("请选择操作:\n");
-        prompt.push_str("  [P] Proceed - 继续执行\n");
-        prompt.push_str("  [D] Dry Run - 先模拟执行\n");
-        prompt.push_str("  [S] Skip - 跳过此步骤\n");
-        prompt.push_str("  [A] Abort - 中止整个任务\n\n");
-        prompt.push_str("选择 (P/D/S/A): ");
+        let mut keys = Vec::new();
+        for option in &self.options {
+            let (key, label) = match option {
+                ConfirmationOption::Proceed => ("P", "Proceed - 继续执行"),
+                ConfirmationOption::DryRunFirst => ("D", "Dry Run - 先模拟执行"),
+                ConfirmationOption::Skip => ("S", "Skip - 跳过此步骤"),
+                ConfirmationOption::Abort => ("A", "Abort - 中止整个任务"),
+                ConfirmationOption::Modify => ("M", "Modify - 修改操作参数"),
+            };
+            prompt.push_str(&format!("  [{}] {}\n", key, label));
+            keys.push(key);
+        }
+        prompt.push_str(&format!("\n选择 ({}): ", keys.join("/")));
 
         prompt
     }
@@ -811,6 +864,20 @@ impl GuardrailEngine {
         })
     }
 
+    /// 获取保护配置
+    pub fn config(&self) -> &GuardrailConfig {
+        &self.config
+    }
+
+    /// 为操作守卫创建确认请求，超时时间取自 `confirmation_timeout_seconds`
+    pub fn build_confirmation_request(&self, operation_guard: OperationGuard) -> ConfirmationRequest {
+        ConfirmationRequest::new(
+            operation_guard,
+            self.config.confirmation_timeout_seconds,
+            self.config.enable_dry_run,
+        )
+    }
+
     /// 检查路径是否受保护
     fn is_protected_path(&self, path: &str) -> bool {
         for protected in &self.config.protected_paths {
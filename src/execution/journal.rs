@@ -0,0 +1,484 @@
+//! Change Journal
+//!
+//! Records every file a task creates, modifies or deletes, together with the
+//! prior contents and SHA-256 hashes, so the task can be undone later.
+//!
+//! - [`ChangeJournal`] collects the changes of one task while it runs
+//! - [`JournalStore`] persists journals on disk and reverts them
+//!
+//! Undo refuses to touch anything when a file no longer matches the hash the
+//! task left behind, i.e. when someone else changed it in the meantime.
+
+use crate::config::AgentConfig;
+use crate::errors::AgentError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const JOURNAL_FILE: &str = "journal.json";
+const BLOB_DIR: &str = "blobs";
+
+// ============================================================================
+// Journal Entries
+// ============================================================================
+
+/// How a task changed a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// One file touched by a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Absolute path of the file
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Hash of the contents before the task touched the file (`None` if it did not exist)
+    pub prior_hash: Option<String>,
+    /// Hash of the contents the task left behind (`None` if the task deleted it)
+    pub new_hash: Option<String>,
+    /// Blob holding the prior contents, relative to the journal directory
+    pub prior_blob: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// The persisted change journal of one task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub task_id: String,
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<JournalEntry>,
+    #[serde(default)]
+    pub reverted_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of reverting a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevertReport {
+    pub task_id: String,
+    /// Files restored to their prior contents
+    pub restored_files: Vec<String>,
+    /// Files the task created, now removed
+    pub deleted_files: Vec<String>,
+    pub reverted_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// Change Journal
+// ============================================================================
+
+/// Collects the file changes of a single task
+///
+/// Call [`record_before`](Self::record_before) before a file is written and
+/// [`record_after`](Self::record_after) once the write is done. Only the first
+/// `record_before` per path keeps the prior contents, so repeated writes still
+/// undo to the state before the task.
+pub struct ChangeJournal {
+    dir: PathBuf,
+    record: Mutex<JournalRecord>,
+}
+
+impl ChangeJournal {
+    /// Start a journal for `task_id` under `store`
+    pub fn new(store: &JournalStore, task_id: impl Into<String>) -> Self {
+        let task_id = task_id.into();
+        Self {
+            dir: store.task_dir(&task_id),
+            record: Mutex::new(JournalRecord {
+                task_id,
+                created_at: Utc::now(),
+                entries: Vec::new(),
+                reverted_at: None,
+            }),
+        }
+    }
+
+    pub fn task_id(&self) -> String {
+        self.lock().task_id.clone()
+    }
+
+    /// Entries recorded so far
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.lock().entries.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Capture the state of `path` before the task changes it
+    pub async fn record_before(&self, path: &str) -> Result<(), AgentError> {
+        let path = absolute_path(path);
+        if self.lock().entries.iter().any(|entry| entry.path == path) {
+            return Ok(());
+        }
+
+        let prior = read_optional(&path).await?;
+        let prior_blob = match &prior {
+            Some(content) => {
+                let blob_dir = self.dir.join(BLOB_DIR);
+                create_task_dir(&self.dir).await?;
+                tokio::fs::create_dir_all(&blob_dir).await.map_err(|e| io_error(&blob_dir, e))?;
+                let name = format!("{}/{}", BLOB_DIR, uuid::Uuid::new_v4());
+                let blob = self.dir.join(&name);
+                tokio::fs::write(&blob, content).await.map_err(|e| io_error(&blob, e))?;
+                Some(name)
+            }
+            None => None,
+        };
+
+        let prior_hash = prior.as_deref().map(content_hash);
+        self.lock().entries.push(JournalEntry {
+            path,
+            kind: if prior_hash.is_some() { ChangeKind::Modified } else { ChangeKind::Created },
+            new_hash: prior_hash.clone(),
+            prior_hash,
+            prior_blob,
+            recorded_at: Utc::now(),
+        });
+        Ok(())
+    }
+
+    /// Record the state of `path` after the task changed it
+    pub async fn record_after(&self, path: &str) -> Result<(), AgentError> {
+        let path = absolute_path(path);
+        let new_hash = hash_file(Path::new(&path)).await?;
+
+        let mut record = self.lock();
+        let Some(index) = record.entries.iter().position(|entry| entry.path == path) else {
+            tracing::warn!("'{}' changed without a prior record; it cannot be undone", path);
+            return Ok(());
+        };
+
+        let kind = match (&record.entries[index].prior_hash, &new_hash) {
+            (None, None) => {
+                // Created and removed again within the task: nothing to undo
+                record.entries.remove(index);
+                return Ok(());
+            }
+            (None, Some(_)) => ChangeKind::Created,
+            (Some(_), Some(_)) => ChangeKind::Modified,
+            (Some(_), None) => ChangeKind::Deleted,
+        };
+        let entry = &mut record.entries[index];
+        entry.kind = kind;
+        entry.new_hash = new_hash;
+        entry.recorded_at = Utc::now();
+        Ok(())
+    }
+
+    /// Write the journal to disk; empty journals are not persisted
+    pub async fn save(&self) -> Result<(), AgentError> {
+        let record = self.lock().clone();
+        if record.entries.is_empty() {
+            return Ok(());
+        }
+        write_record(&self.dir, &record).await
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JournalRecord> {
+        self.record.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// ============================================================================
+// Journal Store
+// ============================================================================
+
+/// On-disk store of task journals, one directory per task
+#[derive(Debug, Clone)]
+pub struct JournalStore {
+    root: PathBuf,
+}
+
+impl JournalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Store configured by `execution.journal`; `None` when journaling is disabled
+    pub fn from_config(config: &AgentConfig) -> Option<Self> {
+        let journal = &config.execution.journal;
+        journal.enabled.then(|| Self::new(&journal.dir))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn task_dir(&self, task_id: &str) -> PathBuf {
+        self.root.join(task_id)
+    }
+
+    /// Load the journal of `task_id`
+    pub async fn load(&self, task_id: &str) -> Result<JournalRecord, AgentError> {
+        if task_id.is_empty() || task_id.contains(['/', '\\']) || task_id.contains("..") {
+            return Err(AgentError::InvalidState(format!("Invalid task id: {}", task_id)));
+        }
+
+        let path = self.task_dir(task_id).join(JOURNAL_FILE);
+        let content = match tokio::fs::read(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AgentError::InvalidState(format!("No change journal for task {}", task_id)));
+            }
+            Err(e) => return Err(io_error(&path, e)),
+        };
+        serde_json::from_slice(&content)
+            .map_err(|e| AgentError::InvalidState(format!("Corrupt change journal for task {}: {}", task_id, e)))
+    }
+
+    /// All journals, newest first
+    pub async fn list(&self) -> Result<Vec<JournalRecord>, AgentError> {
+        let mut records = Vec::new();
+        let mut dirs = match tokio::fs::read_dir(&self.root).await {
+            Ok(dirs) => dirs,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(records),
+            Err(e) => return Err(io_error(&self.root, e)),
+        };
+
+        while let Some(dir) = dirs.next_entry().await.map_err(|e| io_error(&self.root, e))? {
+            let task_id = dir.file_name().to_string_lossy().to_string();
+            match self.load(&task_id).await {
+                Ok(record) => records.push(record),
+                Err(e) => tracing::debug!("Skipping {}: {}", task_id, e),
+            }
+        }
+
+        records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+        Ok(records)
+    }
+
+    /// The most recent journal that has not been reverted yet
+    pub async fn latest(&self) -> Result<Option<JournalRecord>, AgentError> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|record| record.reverted_at.is_none()))
+    }
+
+    /// Undo the changes of `task_id`, or of the latest task when `None`
+    ///
+    /// Every file is checked against the hash the task left behind before
+    /// anything is restored; any mismatch aborts the revert with
+    /// `AgentError::Conflict`.
+    pub async fn revert(&self, task_id: Option<&str>) -> Result<RevertReport, AgentError> {
+        let mut record = match task_id {
+            Some(task_id) => self.load(task_id).await?,
+            None => self
+                .latest()
+                .await?
+                .ok_or_else(|| AgentError::InvalidState("No task to undo".to_string()))?,
+        };
+
+        if let Some(reverted_at) = record.reverted_at {
+            return Err(AgentError::InvalidState(format!(
+                "Task {} was already reverted at {}",
+                record.task_id, reverted_at
+            )));
+        }
+
+        let mut conflicts = Vec::new();
+        for entry in &record.entries {
+            if hash_file(Path::new(&entry.path)).await? != entry.new_hash {
+                conflicts.push(entry.path.clone());
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(AgentError::Conflict(format!(
+                "Files changed since task {} ran, refusing to undo: {}",
+                record.task_id,
+                conflicts.join(", ")
+            )));
+        }
+
+        let dir = self.task_dir(&record.task_id);
+        let mut report = RevertReport {
+            task_id: record.task_id.clone(),
+            restored_files: Vec::new(),
+            deleted_files: Vec::new(),
+            reverted_at: Utc::now(),
+        };
+
+        for entry in record.entries.iter().rev() {
+            let path = Path::new(&entry.path);
+            match &entry.prior_blob {
+                Some(blob) => {
+                    let content = tokio::fs::read(dir.join(blob)).await.map_err(|e| io_error(&dir.join(blob), e))?;
+                    if let Some(parent) = path.parent() {
+                        tokio::fs::create_dir_all(parent).await.map_err(|e| io_error(parent, e))?;
+                    }
+                    tokio::fs::write(path, content).await.map_err(|e| io_error(path, e))?;
+                    report.restored_files.push(entry.path.clone());
+                }
+                None => {
+                    match tokio::fs::remove_file(path).await {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(io_error(path, e)),
+                    }
+                    report.deleted_files.push(entry.path.clone());
+                }
+            }
+        }
+
+        record.reverted_at = Some(report.reverted_at);
+        write_record(&dir, &record).await?;
+        Ok(report)
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// SHA-256 of `content` as lowercase hex
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+async fn hash_file(path: &Path) -> Result<Option<String>, AgentError> {
+    Ok(read_optional(&path.to_string_lossy()).await?.as_deref().map(content_hash))
+}
+
+async fn read_optional(path: &str) -> Result<Option<Vec<u8>>, AgentError> {
+    match tokio::fs::read(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(Path::new(path), e)),
+    }
+}
+
+async fn write_record(dir: &Path, record: &JournalRecord) -> Result<(), AgentError> {
+    create_task_dir(dir).await?;
+    let content = serde_json::to_vec_pretty(record)
+        .map_err(|e| AgentError::ExecutionError(format!("Failed to serialize change journal: {}", e)))?;
+    let path = dir.join(JOURNAL_FILE);
+    tokio::fs::write(&path, content).await.map_err(|e| io_error(&path, e))
+}
+
+/// Create the directory of one task, keeping the store root out of version control
+async fn create_task_dir(dir: &Path) -> Result<(), AgentError> {
+    tokio::fs::create_dir_all(dir).await.map_err(|e| io_error(dir, e))?;
+    if let Some(root) = dir.parent() {
+        let ignore = root.join(".gitignore");
+        if !ignore.exists() {
+            tokio::fs::write(&ignore, "*\n").await.map_err(|e| io_error(&ignore, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn absolute_path(path: &str) -> String {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.to_string_lossy().to_string();
+    }
+    std::env::current_dir()
+        .map(|cwd| cwd.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> AgentError {
+    AgentError::ExecutionError(format!("Change journal I/O error for '{}': {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    async fn write_through(journal: &ChangeJournal, path: &Path, content: &str) {
+        let path = path.to_string_lossy();
+        journal.record_before(&path).await.unwrap();
+        std::fs::write(path.as_ref(), content).unwrap();
+        journal.record_after(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_revert_restores_and_deletes() {
+        let dir = TempDir::new("journal_test");
+        let store = JournalStore::new(dir.join("journal"));
+        let existing = dir.join("existing.txt");
+        let created = dir.join("created.txt");
+        std::fs::write(&existing, "before").unwrap();
+
+        let journal = ChangeJournal::new(&store, "task-1");
+        write_through(&journal, &existing, "first").await;
+        write_through(&journal, &existing, "second").await;
+        write_through(&journal, &created, "new").await;
+        journal.save().await.unwrap();
+
+        let kinds: Vec<ChangeKind> = journal.entries().iter().map(|entry| entry.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::Modified, ChangeKind::Created]);
+
+        let report = store.revert(None).await.unwrap();
+        assert_eq!(report.task_id, "task-1");
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "before");
+        assert!(!created.exists());
+
+        // A reverted task cannot be undone twice
+        assert!(store.revert(Some("task-1")).await.is_err());
+        assert!(store.latest().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_registry_journals_writes() {
+        use crate::tools::{ToolArgs, ToolCall, ToolRegistry, WriteFileTool};
+        use std::sync::Arc;
+
+        let dir = TempDir::new("journal_test");
+        let store = JournalStore::new(dir.join("journal"));
+        let registry = ToolRegistry::new();
+        registry.register(WriteFileTool).await;
+
+        let journal = Arc::new(ChangeJournal::new(&store, "task-3"));
+        registry.set_journal(Some(Arc::clone(&journal)));
+
+        // write_file only accepts relative paths
+        let relative = format!("target/journal_tool_{}.txt", uuid::Uuid::new_v4());
+        let mut args = std::collections::HashMap::new();
+        args.insert("path".to_string(), serde_json::json!(relative));
+        args.insert("content".to_string(), serde_json::json!("written"));
+        registry
+            .execute(&ToolCall { name: "write_file".to_string(), args: ToolArgs::from_map(args) })
+            .await
+            .unwrap();
+        registry.set_journal(None);
+        journal.save().await.unwrap();
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, ChangeKind::Created);
+        assert_eq!(entries[0].new_hash.as_deref(), Some(content_hash(b"written").as_str()));
+
+        store.revert(Some("task-3")).await.unwrap();
+        assert!(!Path::new(&relative).exists());
+    }
+
+    #[tokio::test]
+    async fn test_revert_refuses_when_file_changed_since() {
+        let dir = TempDir::new("journal_test");
+        let store = JournalStore::new(dir.join("journal"));
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "before").unwrap();
+
+        let journal = ChangeJournal::new(&store, "task-2");
+        write_through(&journal, &file, "agent").await;
+        journal.save().await.unwrap();
+
+        std::fs::write(&file, "someone else").unwrap();
+        let error = store.revert(Some("task-2")).await.unwrap_err();
+        assert!(matches!(error, AgentError::Conflict(_)));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "someone else");
+    }
+}
@@ -6,16 +6,25 @@ pub mod file_ops;
 pub mod command_ops;
 pub mod sequential;
 pub mod guardrails;
+pub mod confirmation;
+pub mod budget;
+pub mod snapshot;
+pub mod git_checkpoint;
+pub mod journal;
 
 // Re-export commonly used items
-pub use file_ops::{read_file, write_file, list_files};
-pub use command_ops::run_command;
+pub use file_ops::{read_file, read_file_range, write_file, edit_file, list_files, ReadRange, FileSlice};
+pub use command_ops::{run_command, run_command_streaming, stream_command, OutputLine, OutputStream, StreamedOutput};
 
 // Re-export sequential execution types
 pub use sequential::{
     SequentialExecutor,
     SequentialExecutionPlan,
     ExecutionConfig,
+    CompileRepairConfig,
+    RepairRound,
+    ExecutionStrategy,
+    TestDrivenConfig,
     ExecutionPhase,
     PhaseResult,
     PhaseStatus,
@@ -47,3 +56,41 @@ pub use guardrails::{
     DryRunResult,
 };
 
+// Re-export confirmation handlers
+pub use confirmation::{
+    ConfirmationHandler,
+    ConfirmationPolicy,
+    TerminalConfirmationHandler,
+    PolicyConfirmationHandler,
+    ChannelConfirmationHandler,
+    PendingConfirmation,
+};
+
+// Re-export budget types
+pub use budget::{
+    BudgetTracker,
+    BudgetLimits,
+    BudgetUsage,
+    BudgetResource,
+    BudgetedModel,
+};
+
+// Re-export snapshot types
+pub use snapshot::{
+    SnapshotBackend,
+    SnapshotScope,
+    FileSnapshotStore,
+    FileSnapshot,
+    FileSnapshotEntry,
+};
+pub use git_checkpoint::{GitCheckpointBackend, GitCheckpoint};
+
+// Re-export change journal types
+pub use journal::{
+    ChangeJournal,
+    JournalStore,
+    JournalRecord,
+    JournalEntry,
+    ChangeKind,
+    RevertReport,
+};
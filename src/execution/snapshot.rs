@@ -0,0 +1,530 @@
+//! 步骤快照与回滚
+//!
+//! 步骤修改文件前记录目标文件的原始内容和元数据（或记录文件原本不存在），
+//! 步骤完成后根据 `StepExecutionOutput` 的 `modified_files`/`generated_files`
+//! 自动生成 [`RollbackPlan`]，回滚时逐字节恢复工作区。
+
+use crate::errors::AgentError;
+use crate::execution::guardrails::{RollbackAction, RollbackPlan, RollbackStep};
+use crate::execution::sequential::StepExecutionOutput;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// ============================================================================
+// Snapshot Backend
+// ============================================================================
+
+/// 快照所属的任务与步骤
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotScope {
+    /// 任务ID
+    pub task_id: String,
+    /// 步骤ID
+    pub step_id: String,
+    /// 步骤名称
+    pub step_name: String,
+}
+
+/// 快照后端
+///
+/// 执行器在步骤执行前调用 `create_snapshot`，步骤成功后调用 `checkpoint`
+/// 并通过 `rollback_plan` 生成回滚计划，失败时用 `execute_rollback` 恢复，
+/// 运行结束后调用 `finish` 清理。
+#[async_trait]
+pub trait SnapshotBackend: Send + Sync {
+    /// 后端名称
+    fn name(&self) -> &str;
+
+    /// 是否记录整个工作区；为 `true` 时执行器会为每个步骤创建快照，而不仅是写文件的步骤
+    fn captures_workspace(&self) -> bool {
+        false
+    }
+
+    /// 步骤执行前记录 `paths` 的当前状态，返回快照ID
+    async fn create_snapshot(&self, scope: &SnapshotScope, paths: &[String]) -> Result<String, AgentError>;
+
+    /// 步骤成功后记录检查点
+    async fn checkpoint(&self, _scope: &SnapshotScope, _output: &StepExecutionOutput) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    /// 根据步骤实际改动的文件生成回滚计划；`touched_paths` 为 `None` 时恢复快照中的全部文件
+    async fn rollback_plan(
+        &self,
+        snapshot_id: &str,
+        touched_paths: Option<&[String]>,
+    ) -> Result<RollbackPlan, AgentError>;
+
+    /// 执行回滚计划
+    async fn execute_rollback(&self, plan: &RollbackPlan) -> Result<(), AgentError>;
+
+    /// 运行结束，清理该任务的快照
+    async fn finish(&self, task_id: &str) -> Result<(), AgentError>;
+}
+
+/// 步骤实际改动的文件（修改与新建，去重）
+pub fn touched_files(output: &StepExecutionOutput) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for path in output.modified_files.iter().chain(output.generated_files.iter()) {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+// ============================================================================
+// File Snapshot Store
+// ============================================================================
+
+/// 单个文件的快照记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshotEntry {
+    /// 文件路径
+    pub path: String,
+    /// 快照时文件是否存在
+    pub existed: bool,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 是否只读
+    pub readonly: bool,
+    /// Unix 权限位
+    pub mode: Option<u32>,
+    /// 最后修改时间
+    pub modified: Option<DateTime<Utc>>,
+    /// 快照时不存在的父目录（由内向外），回滚删除文件时一并清理
+    pub missing_dirs: Vec<String>,
+    /// 内容副本位置
+    blob: Option<PathBuf>,
+}
+
+/// 文件快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// 快照ID
+    pub id: String,
+    /// 所属任务与步骤
+    pub scope: SnapshotScope,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 文件记录
+    pub entries: Vec<FileSnapshotEntry>,
+}
+
+/// 基于文件副本的快照存储
+///
+/// 内容副本保存在 `<root>/<task_id>/<snapshot_id>/`，`finish` 时删除整个任务目录。
+pub struct FileSnapshotStore {
+    root: PathBuf,
+    snapshots: Mutex<HashMap<String, FileSnapshot>>,
+}
+
+impl FileSnapshotStore {
+    /// 创建快照存储
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            snapshots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 使用系统临时目录的快照存储
+    pub fn temporary() -> Self {
+        Self::new(std::env::temp_dir().join("agent-runner-snapshots"))
+    }
+
+    /// 快照根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 获取快照
+    pub fn get(&self, snapshot_id: &str) -> Option<FileSnapshot> {
+        self.lock().get(snapshot_id).cloned()
+    }
+
+    /// 当前保存的快照数量
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn capture_entry(&self, dir: &Path, index: usize, path: &str) -> Result<Option<FileSnapshotEntry>, AgentError> {
+        let io_error = |e: std::io::Error| AgentError::ExecutionError(format!("Failed to snapshot '{}': {}", path, e));
+
+        let metadata = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Some(FileSnapshotEntry {
+                    path: path.to_string(),
+                    existed: false,
+                    size: 0,
+                    readonly: false,
+                    mode: None,
+                    modified: None,
+                    missing_dirs: missing_dirs(Path::new(path)),
+                    blob: None,
+                }));
+            }
+            Err(e) => return Err(io_error(e)),
+        };
+
+        if !metadata.is_file() {
+            tracing::warn!("Skipping snapshot of non-file path: {}", path);
+            return Ok(None);
+        }
+
+        let blob = dir.join(format!("{}.blob", index));
+        tokio::fs::copy(path, &blob).await.map_err(io_error)?;
+
+        Ok(Some(FileSnapshotEntry {
+            path: path.to_string(),
+            existed: true,
+            size: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            mode: unix_mode(&metadata),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            missing_dirs: Vec::new(),
+            blob: Some(blob),
+        }))
+    }
+
+    async fn restore_entry(entry: &FileSnapshotEntry) -> Result<(), AgentError> {
+        let path = Path::new(&entry.path);
+        let io_error = |e: std::io::Error| AgentError::ExecutionError(format!("Failed to restore '{}': {}", entry.path, e));
+
+        let blob = match (&entry.blob, entry.existed) {
+            (Some(blob), true) => blob,
+            _ => return Self::delete_entry(entry).await,
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+
+        // 目标可能已被改为只读，先放开写权限
+        if let Ok(metadata) = tokio::fs::metadata(path).await {
+            let mut permissions = metadata.permissions();
+            if permissions.readonly() {
+                #[allow(clippy::permissions_set_readonly_false)]
+                permissions.set_readonly(false);
+                tokio::fs::set_permissions(path, permissions).await.map_err(io_error)?;
+            }
+        }
+
+        let content = tokio::fs::read(blob).await.map_err(io_error)?;
+        tokio::fs::write(path, &content).await.map_err(io_error)?;
+
+        if let Some(modified) = entry.modified {
+            let file = std::fs::File::options().write(true).open(path).map_err(io_error)?;
+            file.set_modified(SystemTime::from(modified)).map_err(io_error)?;
+        }
+
+        let mut permissions = tokio::fs::metadata(path).await.map_err(io_error)?.permissions();
+        apply_mode(&mut permissions, entry.mode, entry.readonly);
+        tokio::fs::set_permissions(path, permissions).await.map_err(io_error)?;
+
+        Ok(())
+    }
+
+    async fn delete_entry(entry: &FileSnapshotEntry) -> Result<(), AgentError> {
+        match tokio::fs::remove_file(&entry.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(AgentError::ExecutionError(format!("Failed to delete '{}': {}", entry.path, e)));
+            }
+        }
+
+        // 清理步骤创建的空目录
+        for dir in &entry.missing_dirs {
+            if tokio::fs::remove_dir(dir).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_entry(&self, snapshot_id: &str, path: &str) -> Result<FileSnapshotEntry, AgentError> {
+        let snapshots = self.lock();
+        let snapshot = snapshots
+            .get(snapshot_id)
+            .ok_or_else(|| AgentError::InvalidState(format!("Snapshot not found: {}", snapshot_id)))?;
+        snapshot
+            .entries
+            .iter()
+            .find(|entry| entry.path == path)
+            .cloned()
+            .ok_or_else(|| AgentError::InvalidState(format!("'{}' is not part of snapshot {}", path, snapshot_id)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, FileSnapshot>> {
+        self.snapshots.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl SnapshotBackend for FileSnapshotStore {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn create_snapshot(&self, scope: &SnapshotScope, paths: &[String]) -> Result<String, AgentError> {
+        let snapshot_id = uuid::Uuid::new_v4().to_string();
+        let dir = self.root.join(&scope.task_id).join(&snapshot_id);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to create snapshot directory: {}", e)))?;
+
+        let mut entries = Vec::new();
+        for (index, path) in paths.iter().enumerate() {
+            if entries.iter().any(|entry: &FileSnapshotEntry| &entry.path == path) {
+                continue;
+            }
+            if let Some(entry) = self.capture_entry(&dir, index, path).await? {
+                entries.push(entry);
+            }
+        }
+
+        self.lock().insert(
+            snapshot_id.clone(),
+            FileSnapshot {
+                id: snapshot_id.clone(),
+                scope: scope.clone(),
+                created_at: Utc::now(),
+                entries,
+            },
+        );
+
+        Ok(snapshot_id)
+    }
+
+    async fn rollback_plan(
+        &self,
+        snapshot_id: &str,
+        touched_paths: Option<&[String]>,
+    ) -> Result<RollbackPlan, AgentError> {
+        let snapshot = self
+            .get(snapshot_id)
+            .ok_or_else(|| AgentError::InvalidState(format!("Snapshot not found: {}", snapshot_id)))?;
+
+        let entries: Vec<&FileSnapshotEntry> = match touched_paths {
+            Some(paths) => paths
+                .iter()
+                .filter_map(|path| {
+                    let entry = snapshot.entries.iter().find(|entry| &entry.path == path);
+                    if entry.is_none() {
+                        tracing::warn!("'{}' was changed but not captured in snapshot {}", path, snapshot_id);
+                    }
+                    entry
+                })
+                .collect(),
+            None => snapshot.entries.iter().collect(),
+        };
+
+        let mut plan = RollbackPlan::new();
+        for (sequence, entry) in entries.into_iter().enumerate() {
+            let (description, action) = if entry.existed {
+                (
+                    format!("恢复 {}", entry.path),
+                    RollbackAction::RestoreFile {
+                        path: entry.path.clone(),
+                        snapshot_id: snapshot_id.to_string(),
+                    },
+                )
+            } else {
+                (
+                    format!("删除 {}", entry.path),
+                    RollbackAction::DeleteFile { path: entry.path.clone() },
+                )
+            };
+
+            plan.add_step(RollbackStep {
+                id: uuid::Uuid::new_v4().to_string(),
+                description,
+                action,
+                sequence,
+            });
+        }
+
+        Ok(plan)
+    }
+
+    async fn execute_rollback(&self, plan: &RollbackPlan) -> Result<(), AgentError> {
+        for step in plan.steps_reversed() {
+            match &step.action {
+                RollbackAction::RestoreFile { path, snapshot_id }
+                | RollbackAction::RestoreConfig { path, snapshot_id } => {
+                    let entry = self.find_entry(snapshot_id, path)?;
+                    Self::restore_entry(&entry).await?;
+                }
+                RollbackAction::DeleteFile { path } => {
+                    // 优先使用快照中记录的目录信息，以便清理新建的空目录
+                    let entry = self
+                        .lock()
+                        .values()
+                        .flat_map(|snapshot| snapshot.entries.iter())
+                        .find(|entry| &entry.path == path && !entry.existed)
+                        .cloned()
+                        .unwrap_or_else(|| FileSnapshotEntry {
+                            path: path.clone(),
+                            existed: false,
+                            size: 0,
+                            readonly: false,
+                            mode: None,
+                            modified: None,
+                            missing_dirs: Vec::new(),
+                            blob: None,
+                        });
+                    Self::delete_entry(&entry).await?;
+                }
+                other => {
+                    tracing::warn!("Unsupported rollback action for file snapshots: {:?}", other);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn finish(&self, task_id: &str) -> Result<(), AgentError> {
+        self.lock().retain(|_, snapshot| snapshot.scope.task_id != task_id);
+
+        match tokio::fs::remove_dir_all(self.root.join(task_id)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AgentError::ExecutionError(format!(
+                "Failed to clean up snapshots for task {}: {}",
+                task_id, e
+            ))),
+        }
+    }
+}
+
+/// 路径中快照时尚不存在的父目录（由内向外）
+fn missing_dirs(path: &Path) -> Vec<String> {
+    path.ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .take_while(|dir| !dir.exists())
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn apply_mode(permissions: &mut std::fs::Permissions, mode: Option<u32>, readonly: bool) {
+    use std::os::unix::fs::PermissionsExt;
+    match mode {
+        Some(mode) => permissions.set_mode(mode),
+        None => permissions.set_readonly(readonly),
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_mode(permissions: &mut std::fs::Permissions, _mode: Option<u32>, readonly: bool) {
+    permissions.set_readonly(readonly);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn scope(task_id: &str) -> SnapshotScope {
+        SnapshotScope {
+            task_id: task_id.to_string(),
+            step_id: "step-1".to_string(),
+            step_name: "Write files".to_string(),
+        }
+    }
+
+    fn output(modified: &[&str], generated: &[&str]) -> StepExecutionOutput {
+        StepExecutionOutput {
+            step_id: "step-1".to_string(),
+            status: crate::execution::sequential::ExecutionStatus::Success,
+            outputs: HashMap::new(),
+            logs: vec![],
+            generated_files: generated.iter().map(|s| s.to_string()).collect(),
+            modified_files: modified.iter().map(|s| s.to_string()).collect(),
+            line_changes: HashMap::new(),
+            executed_commands: vec![],
+            snapshot_id: None,
+            rollback_plan: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restores_modified_and_deletes_created() {
+        let workspace = TempDir::new("snapshot_ws");
+        let existing = workspace.join("existing.bin").to_string_lossy().to_string();
+        let created = workspace.join("new/dir/created.txt").to_string_lossy().to_string();
+        let original: Vec<u8> = (0..=255u8).collect();
+        std::fs::write(&existing, &original).unwrap();
+
+        let store = FileSnapshotStore::new(workspace.join(".snapshots"));
+        let task_scope = scope("task-a");
+        let snapshot_id = store
+            .create_snapshot(&task_scope, &[existing.clone(), created.clone()])
+            .await
+            .unwrap();
+
+        // 步骤修改文件并新建文件
+        std::fs::write(&existing, b"changed").unwrap();
+        std::fs::create_dir_all(workspace.join("new/dir")).unwrap();
+        std::fs::write(&created, b"hello").unwrap();
+
+        let touched = touched_files(&output(&[existing.as_str(), created.as_str()], &[created.as_str()]));
+        let plan = store.rollback_plan(&snapshot_id, Some(&touched)).await.unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert!(matches!(plan.steps[0].action, RollbackAction::RestoreFile { .. }));
+        assert!(matches!(plan.steps[1].action, RollbackAction::DeleteFile { .. }));
+
+        store.execute_rollback(&plan).await.unwrap();
+        assert_eq!(std::fs::read(&existing).unwrap(), original);
+        assert!(!Path::new(&created).exists());
+        assert!(!workspace.join("new").exists());
+
+        store.finish("task-a").await.unwrap();
+        assert!(store.is_empty());
+        assert!(!workspace.join(".snapshots/task-a").exists());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_plan_skips_uncaptured_paths() {
+        let workspace = TempDir::new("snapshot_ws");
+        let store = FileSnapshotStore::new(workspace.join(".snapshots"));
+        let captured = workspace.join("a.txt").to_string_lossy().to_string();
+
+        let snapshot_id = store.create_snapshot(&scope("task-b"), std::slice::from_ref(&captured)).await.unwrap();
+        let plan = store
+            .rollback_plan(&snapshot_id, Some(&["other.txt".to_string()]))
+            .await
+            .unwrap();
+        assert!(plan.steps.is_empty());
+
+        let full = store.rollback_plan(&snapshot_id, None).await.unwrap();
+        assert_eq!(full.steps.len(), 1);
+
+        store.finish("task-b").await.unwrap();
+    }
+}
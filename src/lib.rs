@@ -19,6 +19,7 @@ pub mod models;         // AI model interfaces
 pub mod prompts;        // Prompt engineering system
 pub mod security;       // Security features (NEW)
 pub mod tools;          // Tool system
+pub mod mcp;            // Model Context Protocol client
 pub mod types;          // Core type definitions
 
 // Execution modules
@@ -28,6 +29,9 @@ pub mod planning;       // Task planning and analysis (formerly understanding)
 // Helper modules
 pub mod parser;   // Task helper functions
 
+#[cfg(test)]
+mod test_support;  // Fixtures shared by unit tests
+
 // Service modules (optional, enabled with "service" feature)
 #[cfg(feature = "service")]
 pub mod service;
@@ -47,7 +51,7 @@ pub use errors::AgentError;
 #[cfg(feature = "service")]
 pub use service::types::{
     // Task types  
-    TaskRequest, TaskResponse, TaskStatus, TaskResult, TaskPriority,
+    TaskRequest, TaskResponse, TaskRevertResponse, TaskStatus, TaskResult, TaskPriority,
     TaskContext, TaskConstraints,
     ExecutionStep, StepType, StepStatus, TaskMetrics,
     TaskArtifact, ArtifactType, ServiceError,
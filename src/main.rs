@@ -1,16 +1,20 @@
 //! Main entry point for the AI-Native Code Agent
 
-use agent_runner::cli::Cli;
+use agent_runner::cli::{Cli, Commands};
 use clap::Parser;
 use tracing::{info, error};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-
     let cli = Cli::parse();
 
+    // Initialize logging; `mcp-serve` owns stdout, so it logs to stderr
+    if matches!(cli.command, Commands::McpServe { .. }) {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+
     info!("Starting AI-Native Code Agent");
 
     match cli.run().await {
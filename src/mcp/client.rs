@@ -0,0 +1,533 @@
+//! MCP client and the tools it imports
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use crate::config::{McpServerConfig, ToolConfig};
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationTarget};
+use crate::tools::{check_guardrails, Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolRegistry, ToolResult};
+use super::protocol::{
+    CallToolResult, InitializeResult, JsonRpcMessage, ListToolsResult, McpToolInfo, PROTOCOL_VERSION,
+    SUPPORTED_PROTOCOL_VERSIONS,
+};
+use super::transport::{HttpTransport, StdioTransport, Transport};
+
+/// Longest tool name models accept
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Client for one configured MCP server
+///
+/// The connection is opened and initialized lazily. When the server exits,
+/// the next request starts it again and repeats the handshake; a server that
+/// keeps failing is given up on after `max_restarts` restarts without a
+/// successful request.
+pub struct McpClient {
+    config: McpServerConfig,
+    connection: Mutex<Option<Arc<dyn Transport>>>,
+    server_info: std::sync::Mutex<Option<InitializeResult>>,
+    next_id: AtomicU64,
+    restarts: AtomicU32,
+}
+
+impl McpClient {
+    pub fn new(config: McpServerConfig) -> Self {
+        Self {
+            config,
+            connection: Mutex::new(None),
+            server_info: std::sync::Mutex::new(None),
+            next_id: AtomicU64::new(1),
+            restarts: AtomicU32::new(0),
+        }
+    }
+
+    /// Create a client and perform the initialize handshake
+    pub async fn connect(config: McpServerConfig) -> Result<Arc<Self>, ToolError> {
+        let client = Arc::new(Self::new(config));
+        client.transport().await?;
+        Ok(client)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// What the server reported during the last handshake
+    pub fn server_info(&self) -> Option<InitializeResult> {
+        self.server_info.lock().unwrap().clone()
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs.max(1))
+    }
+
+    /// The open connection, (re)starting the server if needed
+    async fn transport(&self) -> Result<Arc<dyn Transport>, ToolError> {
+        let mut connection = self.connection.lock().await;
+        match connection.as_ref() {
+            Some(transport) if !transport.is_closed() => return Ok(transport.clone()),
+            Some(_) => {
+                let restarts = self.restarts.fetch_add(1, Ordering::SeqCst) + 1;
+                if restarts > self.config.max_restarts {
+                    return Err(ToolError::ExecutionError(format!(
+                        "MCP server '{}' stopped and was restarted {} times without success; giving up",
+                        self.config.name, self.config.max_restarts
+                    )));
+                }
+                tracing::warn!("MCP server '{}' is not running; restarting ({})", self.config.name, restarts);
+            }
+            None => {}
+        }
+
+        let transport = self.start().await;
+        *connection = transport.as_ref().ok().cloned();
+        transport
+    }
+
+    /// Open the transport and perform the initialize handshake
+    async fn start(&self) -> Result<Arc<dyn Transport>, ToolError> {
+        let transport: Arc<dyn Transport> = match (&self.config.command, &self.config.url) {
+            (Some(command), _) => Arc::new(StdioTransport::spawn(
+                &self.config.name,
+                command,
+                &self.config.args,
+                &self.config.env,
+            )?),
+            (None, Some(url)) => Arc::new(HttpTransport::new(&self.config.name, url, &self.config.headers)),
+            (None, None) => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "MCP server '{}' needs a command or a url",
+                    self.config.name
+                )))
+            }
+        };
+
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "agent-runner", "version": env!("CARGO_PKG_VERSION")},
+        });
+        let initialized = async {
+            let result = self.send(transport.as_ref(), "initialize", Some(params)).await?;
+            let info: InitializeResult = serde_json::from_value(result).map_err(|e| {
+                ToolError::ExecutionError(format!("Invalid initialize result from MCP server '{}': {}", self.config.name, e))
+            })?;
+            if !SUPPORTED_PROTOCOL_VERSIONS.contains(&info.protocol_version.as_str()) {
+                return Err(ToolError::ExecutionError(format!(
+                    "MCP server '{}' speaks unsupported protocol version {}",
+                    self.config.name, info.protocol_version
+                )));
+            }
+            transport.notify(JsonRpcMessage::notification("notifications/initialized", None)).await?;
+            Ok(info)
+        }
+        .await;
+
+        match initialized {
+            Ok(info) => {
+                tracing::info!(
+                    "Connected to MCP server '{}' ({} {})",
+                    self.config.name,
+                    info.server_info.name,
+                    info.server_info.version
+                );
+                *self.server_info.lock().unwrap() = Some(info);
+                Ok(transport)
+            }
+            Err(e) => {
+                transport.close().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn send(&self, transport: &dyn Transport, method: &str, params: Option<Value>) -> Result<Value, ToolError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let response = transport
+            .request(JsonRpcMessage::request(json!(id), method, params), self.timeout())
+            .await?;
+        if let Some(error) = response.error {
+            return Err(ToolError::ExecutionError(format!(
+                "MCP server '{}' failed {}: {} ({})",
+                self.config.name, method, error.message, error.code
+            )));
+        }
+        Ok(response.result.unwrap_or(Value::Null))
+    }
+
+    /// Send a request, starting the server first if it is not running
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value, ToolError> {
+        let transport = self.transport().await?;
+        let result = self.send(transport.as_ref(), method, params).await?;
+        self.restarts.store(0, Ordering::SeqCst);
+        Ok(result)
+    }
+
+    /// All tools of the server, following pagination
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>, ToolError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.as_ref().map(|cursor| json!({"cursor": cursor}));
+            let result = self.request("tools/list", params).await?;
+            let page: ListToolsResult = serde_json::from_value(result).map_err(|e| {
+                ToolError::ExecutionError(format!("Invalid tools/list result from MCP server '{}': {}", self.config.name, e))
+            })?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+                _ => break,
+            }
+        }
+        Ok(tools)
+    }
+
+    /// Call a tool by its name on the server
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, ToolError> {
+        let result = self
+            .request("tools/call", Some(json!({"name": name, "arguments": arguments})))
+            .await?;
+        serde_json::from_value(result).map_err(|e| {
+            ToolError::ExecutionError(format!("Invalid tools/call result from MCP server '{}': {}", self.config.name, e))
+        })
+    }
+
+    /// Close the connection and stop the server
+    pub async fn shutdown(&self) {
+        if let Some(transport) = self.connection.lock().await.take() {
+            transport.close().await;
+        }
+    }
+}
+
+// ============================================================================
+// Schema mapping
+// ============================================================================
+
+/// Parameters of a JSON Schema object (`properties` and `required`)
+pub fn parameters_from_schema(schema: &Value) -> Vec<Parameter> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| parameter_from_schema(name, property, required.contains(&name.as_str())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Map one property schema onto a [`Parameter`]
+///
+/// `anyOf`/`oneOf` use their first non-null alternative; a property without
+/// a usable type is treated as a string.
+pub fn parameter_from_schema(name: &str, schema: &Value, required: bool) -> Parameter {
+    let alternative = ["anyOf", "oneOf"]
+        .iter()
+        .filter_map(|key| schema.get(*key).and_then(Value::as_array))
+        .flatten()
+        .find(|alternative| alternative.get("type").and_then(Value::as_str) != Some("null"));
+    let (parameter_type, effective) = match (schema_type(schema), alternative) {
+        (Some(parameter_type), _) => (Some(parameter_type), schema),
+        (None, Some(alternative)) => (schema_type(alternative), alternative),
+        (None, None) => (None, schema),
+    };
+
+    let description = schema
+        .get("description")
+        .or_else(|| schema.get("title"))
+        .or_else(|| effective.get("description"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let mut parameter = Parameter::new(name, description, parameter_type.unwrap_or(ParameterType::String), required);
+
+    parameter.default_value = schema.get("default").filter(|value| !value.is_null()).cloned();
+    parameter.enum_values = effective.get("enum").and_then(Value::as_array).cloned();
+    parameter.minimum = effective.get("minimum").and_then(Value::as_f64);
+    parameter.maximum = effective.get("maximum").and_then(Value::as_f64);
+    parameter.pattern = effective.get("pattern").and_then(Value::as_str).map(str::to_string);
+    parameter.items = effective
+        .get("items")
+        .filter(|items| items.is_object())
+        .map(|items| Box::new(parameter_from_schema("items", items, true)));
+    parameter.properties = parameters_from_schema(effective);
+    parameter
+}
+
+fn schema_type(schema: &Value) -> Option<ParameterType> {
+    let name = match schema.get("type") {
+        Some(Value::String(name)) => Some(name.as_str()),
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).find(|name| *name != "null"),
+        _ => None,
+    };
+    match name {
+        Some("string") => Some(ParameterType::String),
+        Some("integer") => Some(ParameterType::Integer),
+        Some("number") => Some(ParameterType::Number),
+        Some("boolean") => Some(ParameterType::Boolean),
+        Some("array") => Some(ParameterType::Array),
+        Some("object") => Some(ParameterType::Object),
+        _ if schema.get("properties").is_some() => Some(ParameterType::Object),
+        _ if schema.get("items").is_some() => Some(ParameterType::Array),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Imported tools
+// ============================================================================
+
+/// A tool served by an MCP server
+///
+/// Registered as `<server>_<tool>` so tools of different servers, and our
+/// own tools, do not collide.
+pub struct McpTool {
+    client: Arc<McpClient>,
+    info: McpToolInfo,
+    name: String,
+    description: String,
+    parameters: Vec<Parameter>,
+    guardrails: GuardrailEngine,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: McpToolInfo) -> Self {
+        let name: String = format!("{}_{}", client.name(), info.name)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .take(MAX_TOOL_NAME_LEN)
+            .collect();
+        let description = info
+            .description
+            .clone()
+            .or_else(|| info.annotations.as_ref().and_then(|a| a.title.clone()))
+            .unwrap_or_else(|| format!("{} (from MCP server {})", info.name, client.name()));
+
+        Self {
+            parameters: parameters_from_schema(&info.input_schema),
+            client,
+            info,
+            name,
+            description,
+            guardrails: GuardrailEngine::default(),
+        }
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// The tool as listed by the server
+    pub fn info(&self) -> &McpToolInfo {
+        &self.info
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        self.parameters.clone()
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        let read_only = self.info.annotations.as_ref().and_then(|a| a.read_only_hint).unwrap_or(false);
+        let metadata = if read_only { ToolMetadata::read_only() } else { ToolMetadata::mutating() };
+        metadata.with_timeout(self.client.timeout())
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        check_guardrails(
+            &self.guardrails,
+            self.metadata().operation_type(),
+            &format!("Call MCP tool {} on {}", self.info.name, self.client.name()),
+            vec![OperationTarget {
+                resource_type: "mcp_tool".to_string(),
+                path: format!("{}/{}", self.client.name(), self.info.name),
+                is_protected: false,
+                snapshot: None,
+            }],
+        )?;
+
+        let arguments = Value::Object(args.as_map().iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        let result = self.client.call_tool(&self.info.name, arguments).await?;
+
+        let text = result.text();
+        let mut tool_result = if result.is_error {
+            ToolResult::error(if text.is_empty() { format!("{} failed", self.name) } else { text.clone() })
+        } else {
+            ToolResult::text(text.clone())
+        };
+        tool_result.content = text;
+        tool_result.data = result.structured_content;
+        Ok(tool_result)
+    }
+}
+
+/// Import every tool of a connected server, returning the registered names
+///
+/// An imported tool never replaces a tool that is already registered.
+pub async fn register_mcp_tools(registry: &ToolRegistry, client: Arc<McpClient>) -> Result<Vec<String>, ToolError> {
+    let mut registered = Vec::new();
+    for info in client.list_tools().await? {
+        let tool = McpTool::new(client.clone(), info);
+        let name = tool.name().to_string();
+        if registry.has_tool(&name).await {
+            tracing::warn!("MCP tool '{}' conflicts with a registered tool and was skipped", name);
+            continue;
+        }
+        if !registry.register(tool).await {
+            tracing::warn!("MCP tool '{}' is not allowed by enabled_tools/disabled_tools and was skipped", name);
+            continue;
+        }
+        registered.push(name);
+    }
+    Ok(registered)
+}
+
+/// Connect to the MCP servers configured in `ToolConfig` and import their tools
+///
+/// A server that cannot be reached is logged and skipped.
+pub async fn register_configured_mcp_servers(registry: &ToolRegistry, config: &ToolConfig) -> Vec<String> {
+    let mut registered = Vec::new();
+    for server in &config.mcp_servers {
+        let imported = match McpClient::connect(server.clone()).await {
+            Ok(client) => register_mcp_tools(registry, client).await,
+            Err(e) => Err(e),
+        };
+        match imported {
+            Ok(names) => {
+                tracing::info!("Imported {} tools from MCP server '{}'", names.len(), server.name);
+                registered.extend(names);
+            }
+            Err(e) => tracing::warn!("Skipping MCP server '{}': {}", server.name, e),
+        }
+    }
+    registered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::collections::HashMap;
+
+    /// A stdio MCP server in a few lines of shell: one page of tools per
+    /// `tools/list` call, `echo` reports the server's pid and arguments, and
+    /// calling `crash` makes the server exit
+    const STUB_SERVER: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"1.0"}}}\n' "$id" ;;
+    *'"cursor":"2"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"crash","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echo the arguments","annotations":{"readOnlyHint":true},"inputSchema":{"type":"object","properties":{"text":{"type":"string"},"times":{"type":"integer","minimum":1,"default":1}},"required":["text"]}}],"nextCursor":"2"}}\n' "$id" ;;
+    *'"name":"crash"'*)
+      exit 1 ;;
+    *'"method":"tools/call"'*)
+      args=$(printf '%s' "$line" | sed -n 's/.*"arguments":\({[^}]*}\).*/\1/p')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"pid %s"}],"structuredContent":%s}}\n' "$id" "$$" "$args" ;;
+  esac
+done
+"#;
+
+    /// Directory holding the stub server as `server.sh`
+    fn stub_server() -> TempDir {
+        let dir = TempDir::new("mcp_stub");
+        let script = dir.join("server.sh");
+        std::fs::write(&script, STUB_SERVER).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        dir
+    }
+
+    fn call(name: &str, args: Value) -> crate::tools::ToolCall {
+        let args: HashMap<String, Value> = serde_json::from_value(args).unwrap();
+        crate::tools::ToolCall { name: name.to_string(), args: ToolArgs::from_map(args) }
+    }
+
+    #[tokio::test]
+    async fn test_import_and_call_stdio_tools_across_restart() {
+        let dir = stub_server();
+        let script = dir.join("server.sh");
+        let config = McpServerConfig {
+            name: "stub".to_string(),
+            command: Some(script.to_string_lossy().to_string()),
+            timeout_secs: 10,
+            ..Default::default()
+        };
+        let client = McpClient::connect(config).await.unwrap();
+        assert_eq!(client.server_info().unwrap().server_info.name, "stub");
+
+        let registry = ToolRegistry::new();
+        let registered = register_mcp_tools(&registry, client.clone()).await.unwrap();
+        assert_eq!(registered, vec!["stub_echo", "stub_crash"]);
+        assert!(registry.metadata("stub_echo").await.unwrap().read_only);
+
+        // Arguments are validated against the imported schema
+        assert!(registry.execute(&call("stub_echo", json!({"times": 2}))).await.is_err());
+
+        let first = registry.execute(&call("stub_echo", json!({"text": "hi"}))).await.unwrap();
+        assert!(first.success);
+        assert!(first.content.starts_with("pid "));
+        assert_eq!(first.data, Some(json!({"text": "hi", "times": 1})));
+
+        // The server dies mid-call; the next call starts a new one
+        assert!(registry.execute(&call("stub_crash", json!({}))).await.is_err());
+        let second = registry.execute(&call("stub_echo", json!({"text": "again"}))).await.unwrap();
+        assert!(second.success);
+        assert_ne!(first.content, second.content);
+
+        client.shutdown().await;
+    }
+
+    #[test]
+    fn test_parameters_from_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "File path", "pattern": "^[^/]"},
+                "mode": {"enum": ["fast", "full"], "type": "string"},
+                "limit": {"anyOf": [{"type": "integer", "maximum": 100}, {"type": "null"}], "default": null},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "options": {"properties": {"depth": {"type": "number"}}, "required": ["depth"]},
+            },
+            "required": ["path"],
+        });
+        let parameters = parameters_from_schema(&schema);
+        let get = |name: &str| parameters.iter().find(|p| p.name == name).unwrap();
+
+        assert!(get("path").required);
+        assert_eq!(get("path").pattern.as_deref(), Some("^[^/]"));
+        assert_eq!(get("mode").enum_values, Some(vec![json!("fast"), json!("full")]));
+        assert_eq!(get("limit").parameter_type, ParameterType::Integer);
+        assert_eq!(get("limit").maximum, Some(100.0));
+        assert!(!get("limit").required);
+        assert_eq!(get("tags").items.as_ref().unwrap().parameter_type, ParameterType::String);
+        assert_eq!(get("options").parameter_type, ParameterType::Object);
+        assert!(get("options").properties[0].required);
+    }
+}
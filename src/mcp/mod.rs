@@ -0,0 +1,41 @@
+//! Model Context Protocol (MCP)
+//!
+//! [`McpClient`] connects to an MCP server, either launched as a child process
+//! (stdio) or reached over streamable HTTP, performs the `initialize`
+//! handshake and calls its tools. Each server tool is registered in the
+//! [`ToolRegistry`](crate::tools::ToolRegistry) as an [`McpTool`] named
+//! `<server>_<tool>`, with its input schema mapped onto our
+//! [`Parameter`](crate::tools::Parameter) types so arguments are validated
+//! before they reach the server.
+//!
+//! Servers are configured in `ToolConfig.mcp_servers`:
+//!
+//! ```toml
+//! [[tools.mcp_servers]]
+//! name = "docs"
+//! command = "docs-mcp-server"
+//! args = ["--root", "./docs"]
+//!
+//! [[tools.mcp_servers]]
+//! name = "db"
+//! url = "http://localhost:8931/mcp"
+//! ```
+//!
+//! In the other direction, [`McpServer`] (the `mcp-serve` command) publishes
+//! our own registry and a `run_task` tool to MCP clients over stdio.
+
+pub mod protocol;
+pub mod transport;
+pub mod client;
+pub mod server;
+
+pub use protocol::{
+    JsonRpcMessage, JsonRpcError, InitializeResult, Implementation, McpToolInfo, ToolAnnotations,
+    ListToolsResult, CallToolResult, McpContent, PROTOCOL_VERSION,
+};
+pub use transport::{Transport, StdioTransport, HttpTransport};
+pub use client::{
+    McpClient, McpTool, parameters_from_schema, parameter_from_schema, register_mcp_tools,
+    register_configured_mcp_servers,
+};
+pub use server::{McpServer, RUN_TASK_TOOL};
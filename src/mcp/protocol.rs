@@ -0,0 +1,220 @@
+//! JSON-RPC messages and the MCP types exchanged over them
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Protocol version sent in `initialize`
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// Protocol versions this crate can talk
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC 2.0 request, notification or response
+///
+/// Requests carry `id` and `method`, notifications only `method`, and
+/// responses `id` with either `result` or `error`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcMessage {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcMessage {
+    fn new() -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: None,
+            params: None,
+            result: None,
+            error: None,
+        }
+    }
+
+    pub fn request(id: Value, method: &str, params: Option<Value>) -> Self {
+        Self { id: Some(id), method: Some(method.to_string()), params, ..Self::new() }
+    }
+
+    pub fn notification(method: &str, params: Option<Value>) -> Self {
+        Self { method: Some(method.to_string()), params, ..Self::new() }
+    }
+
+    pub fn response(id: Value, result: Value) -> Self {
+        Self { id: Some(id), result: Some(result), ..Self::new() }
+    }
+
+    pub fn error_response(id: Value, error: JsonRpcError) -> Self {
+        Self { id: Some(id), error: Some(error), ..Self::new() }
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.id.is_some() && self.method.is_some()
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none() && self.method.is_some()
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.id.is_some() && self.method.is_none()
+    }
+}
+
+/// Error object of a JSON-RPC response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+/// Name and version exchanged during `initialize`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Implementation {
+    pub name: String,
+    pub version: String,
+}
+
+/// Result of `initialize`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    pub protocol_version: String,
+    #[serde(default)]
+    pub capabilities: Value,
+    pub server_info: Implementation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+/// A tool as listed by `tools/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema of the arguments object
+    pub input_schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Behaviour hints a server gives about a tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+/// Result of `tools/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    pub tools: Vec<McpToolInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Result of `tools/call`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<McpContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Text of all content parts, one per line
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(McpContent::as_text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A content part of a tool result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: Value,
+    },
+    ResourceLink {
+        uri: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+impl McpContent {
+    pub fn text(text: impl Into<String>) -> Self {
+        McpContent::Text { text: text.into() }
+    }
+
+    /// Textual form of the part; binary data is replaced by a placeholder
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            McpContent::Text { text } => Some(text.clone()),
+            McpContent::Image { mime_type, .. } => Some(format!("[image {}]", mime_type)),
+            McpContent::Audio { mime_type, .. } => Some(format!("[audio {}]", mime_type)),
+            McpContent::Resource { resource } => resource
+                .get("text")
+                .or_else(|| resource.get("uri"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            McpContent::ResourceLink { uri } => Some(uri.clone()),
+            McpContent::Unknown => None,
+        }
+    }
+}
@@ -0,0 +1,480 @@
+//! MCP server exposing the tool registry and the task runner
+//!
+//! [`McpServer`] answers newline-delimited JSON-RPC over stdio (or any pair
+//! of async streams). Every registered tool is published with its JSON
+//! Schema and called through the [`ToolRegistry`], after the same
+//! [`GuardrailEngine`] and [`CommandValidator`] checks the agent applies. With
+//! an agent attached, a `run_task` tool runs `TaskAgent::process_task` and
+//! reports progress to clients that send a progress token.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use crate::agent::TaskAgent;
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationTarget};
+use crate::security::CommandValidator;
+use crate::tools::{check_guardrails, object_schema, Parameter, ToolArgs, ToolCall, ToolRegistry, ToolResult};
+use super::protocol::{
+    CallToolResult, Implementation, InitializeResult, JsonRpcError, JsonRpcMessage, ListToolsResult, McpContent,
+    McpToolInfo, ToolAnnotations, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+    PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS,
+};
+
+/// Name of the tool that runs a whole task
+pub const RUN_TASK_TOOL: &str = "run_task";
+
+/// How often `run_task` reports progress while the task runs
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Serialized writer shared by the request handlers
+#[derive(Clone)]
+struct Outgoing {
+    writer: Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>,
+}
+
+impl Outgoing {
+    async fn send(&self, message: &JsonRpcMessage) {
+        let mut line = match serde_json::to_vec(message) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to encode MCP message: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut writer = self.writer.lock().await;
+        if let Err(e) = async {
+            writer.write_all(&line).await?;
+            writer.flush().await
+        }
+        .await
+        {
+            tracing::warn!("Failed to write MCP message: {}", e);
+        }
+    }
+
+    /// Send `notifications/progress` if the client asked for it
+    async fn progress(&self, token: Option<&Value>, progress: u64, message: String) {
+        if let Some(token) = token {
+            let params = json!({"progressToken": token, "progress": progress, "message": message});
+            self.send(&JsonRpcMessage::notification("notifications/progress", Some(params))).await;
+        }
+    }
+}
+
+/// MCP server for a tool registry and, optionally, a task agent
+pub struct McpServer {
+    registry: Arc<ToolRegistry>,
+    guardrails: GuardrailEngine,
+    validator: CommandValidator,
+    agent: Option<Mutex<TaskAgent>>,
+}
+
+impl McpServer {
+    pub fn new(registry: Arc<ToolRegistry>) -> Self {
+        Self {
+            registry,
+            guardrails: GuardrailEngine::default(),
+            validator: CommandValidator::default(),
+            agent: None,
+        }
+    }
+
+    /// Serve tasks with `agent` through the `run_task` tool, one at a time
+    pub fn with_agent(mut self, agent: TaskAgent) -> Self {
+        self.agent = Some(Mutex::new(agent));
+        self
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    pub fn with_command_validator(mut self, validator: CommandValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Serve on the process's stdin and stdout until stdin closes
+    pub async fn serve_stdio(self) -> Result<(), ToolError> {
+        Arc::new(self).serve(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Serve on `reader` and `writer` until the reader reaches end of file
+    ///
+    /// Requests are handled concurrently, so pings are answered while a task
+    /// runs; in-flight requests are finished before returning.
+    pub async fn serve<R, W>(self: Arc<Self>, reader: R, writer: W) -> Result<(), ToolError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let outgoing = Outgoing { writer: Arc::new(Mutex::new(Box::new(writer))) };
+        let mut lines = BufReader::new(reader).lines();
+        let mut handlers = JoinSet::new();
+
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read MCP input: {}", e)))?
+        {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let message: JsonRpcMessage = match serde_json::from_str(line) {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = JsonRpcError::new(PARSE_ERROR, format!("Invalid JSON-RPC message: {}", e));
+                    outgoing.send(&JsonRpcMessage::error_response(Value::Null, error)).await;
+                    continue;
+                }
+            };
+
+            if message.is_request() {
+                let server = self.clone();
+                let outgoing = outgoing.clone();
+                handlers.spawn(async move {
+                    let id = message.id.clone().unwrap_or(Value::Null);
+                    let method = message.method.as_deref().unwrap_or_default();
+                    let response = match server.handle_request(method, message.params, &outgoing).await {
+                        Ok(result) => JsonRpcMessage::response(id, result),
+                        Err(error) => JsonRpcMessage::error_response(id, error),
+                    };
+                    outgoing.send(&response).await;
+                });
+            } else if message.is_notification() {
+                tracing::debug!("MCP client sent {:?}", message.method);
+            } else if !message.is_response() {
+                let error = JsonRpcError::new(INVALID_REQUEST, "Message is neither a request nor a notification");
+                outgoing.send(&JsonRpcMessage::error_response(message.id.unwrap_or(Value::Null), error)).await;
+            }
+        }
+
+        while handlers.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    async fn handle_request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        outgoing: &Outgoing,
+    ) -> Result<Value, JsonRpcError> {
+        let params = params.unwrap_or_else(|| json!({}));
+        let result = match method {
+            "initialize" => serde_json::to_value(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => serde_json::to_value(ListToolsResult { tools: self.list_tools().await, next_cursor: None }),
+            "tools/call" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| JsonRpcError::new(INVALID_PARAMS, "tools/call needs a tool name"))?;
+                let arguments: HashMap<String, Value> = match params.get("arguments") {
+                    None | Some(Value::Null) => HashMap::new(),
+                    Some(arguments) => serde_json::from_value(arguments.clone())
+                        .map_err(|_| JsonRpcError::new(INVALID_PARAMS, "Tool arguments must be an object"))?,
+                };
+                let token = params.get("_meta").and_then(|meta| meta.get("progressToken"));
+
+                let result = if name == RUN_TASK_TOOL && self.agent.is_some() {
+                    self.run_task(&arguments, token, outgoing).await
+                } else if self.registry.has_tool(name).await {
+                    self.call_tool(name, arguments).await
+                } else {
+                    return Err(JsonRpcError::new(INVALID_PARAMS, format!("Unknown tool: {}", name)));
+                };
+                serde_json::to_value(result)
+            }
+            _ => return Err(JsonRpcError::new(METHOD_NOT_FOUND, format!("Method not supported: {}", method))),
+        };
+        result.map_err(|e| JsonRpcError::new(INTERNAL_ERROR, e.to_string()))
+    }
+
+    fn initialize(&self, params: &Value) -> InitializeResult {
+        // Answer with the client's version when we speak it, otherwise our latest
+        let requested = params.get("protocolVersion").and_then(Value::as_str).unwrap_or_default();
+        let protocol_version = SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|version| **version == requested)
+            .copied()
+            .unwrap_or(PROTOCOL_VERSION);
+
+        InitializeResult {
+            protocol_version: protocol_version.to_string(),
+            capabilities: json!({"tools": {"listChanged": false}}),
+            server_info: Implementation {
+                name: "agent-runner".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            instructions: self
+                .agent
+                .as_ref()
+                .map(|_| format!("Call {} to have the agent plan and carry out a whole task.", RUN_TASK_TOOL)),
+        }
+    }
+
+    async fn list_tools(&self) -> Vec<McpToolInfo> {
+        let mut tools = Vec::new();
+        for definition in self.registry.definitions().await {
+            if self.agent.is_some() && definition.name == RUN_TASK_TOOL {
+                continue;
+            }
+            let read_only = self.registry.metadata(&definition.name).await.is_some_and(|m| m.read_only);
+            tools.push(McpToolInfo {
+                name: definition.name,
+                description: Some(definition.description),
+                input_schema: definition.parameters,
+                annotations: Some(ToolAnnotations {
+                    read_only_hint: Some(read_only),
+                    ..ToolAnnotations::default()
+                }),
+            });
+        }
+        if self.agent.is_some() {
+            tools.push(McpToolInfo {
+                name: RUN_TASK_TOOL.to_string(),
+                description: Some(
+                    "Plan and carry out a task described in natural language, using the agent's tools".to_string(),
+                ),
+                input_schema: object_schema(&[Parameter::required("task", "The task description")]),
+                annotations: None,
+            });
+        }
+        tools
+    }
+
+    /// Check a call against the guardrails and the command validator
+    ///
+    /// Path-like arguments become guardrail targets, so protected paths are
+    /// refused, and the arguments are scanned for dangerous patterns. A
+    /// free-form `command` argument (one without enum values) is a shell
+    /// command and has to pass the [`CommandValidator`].
+    async fn check_call(&self, name: &str, args: &HashMap<String, Value>) -> Result<(), ToolError> {
+        let metadata = self.registry.metadata(name).await.unwrap_or_default();
+        let parameters = self.registry.parameters(name).await.unwrap_or_default();
+
+        let free_form_command = parameters
+            .iter()
+            .any(|parameter| parameter.name == "command" && parameter.enum_values.is_none());
+        if free_form_command {
+            if let Some(command) = args.get("command").and_then(Value::as_str) {
+                self.validator
+                    .validate(command)
+                    .map_err(|e| ToolError::PermissionDenied(format!("{}: {}", command, e)))?;
+            }
+        }
+
+        let targets = args
+            .iter()
+            .filter(|(key, _)| key.contains("path") || key.contains("file") || key.as_str() == "working_dir")
+            .flat_map(|(_, value)| match value {
+                Value::String(path) => vec![path.clone()],
+                Value::Array(paths) => paths.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+                _ => Vec::new(),
+            })
+            .map(|path| OperationTarget {
+                resource_type: "file".to_string(),
+                path,
+                is_protected: false,
+                snapshot: None,
+            })
+            .collect();
+        let description = format!("Call tool {} with {}", name, Value::Object(args.clone().into_iter().collect()));
+        check_guardrails(&self.guardrails, metadata.operation_type(), &description, targets)?;
+        Ok(())
+    }
+
+    async fn call_tool(&self, name: &str, args: HashMap<String, Value>) -> CallToolResult {
+        if let Err(e) = self.check_call(name, &args).await {
+            tracing::warn!("Refused MCP call to {}: {}", name, e);
+            return error_result(e.to_string());
+        }
+        let call = ToolCall { name: name.to_string(), args: ToolArgs::from_map(args) };
+        match self.registry.execute(&call).await {
+            Ok(result) => tool_result_to_mcp(result),
+            Err(e) => error_result(e.to_string()),
+        }
+    }
+
+    /// Run a task with the agent, reporting budget usage as progress
+    async fn run_task(&self, args: &HashMap<String, Value>, token: Option<&Value>, outgoing: &Outgoing) -> CallToolResult {
+        let Some(agent) = &self.agent else {
+            return error_result(format!("Unknown tool: {}", RUN_TASK_TOOL));
+        };
+        let task = match args.get("task").and_then(Value::as_str) {
+            Some(task) if !task.trim().is_empty() => task.to_string(),
+            _ => return error_result("Missing parameter: task".to_string()),
+        };
+
+        let mut agent = agent.lock().await;
+        let budget = agent.get_budget().clone();
+        let mut progress = 0;
+        outgoing.progress(token, progress, format!("Started task: {}", task)).await;
+
+        let run = agent.process_task(&task);
+        tokio::pin!(run);
+        let mut ticks = tokio::time::interval(PROGRESS_INTERVAL);
+        ticks.tick().await;
+        let result = loop {
+            tokio::select! {
+                result = &mut run => break result,
+                _ = ticks.tick() => {
+                    let usage = budget.usage();
+                    progress += 1;
+                    let message = format!(
+                        "{} model calls, {} tool calls, {} steps, {} tokens",
+                        usage.model_calls, usage.tool_calls, usage.steps, usage.total_tokens
+                    );
+                    outgoing.progress(token, progress, message).await;
+                }
+            }
+        };
+        outgoing.progress(token, progress + 1, "Task finished".to_string()).await;
+
+        match result {
+            Ok(result) => {
+                let mut text = result.summary.clone();
+                if let Some(details) = &result.details {
+                    text.push_str("\n\n");
+                    text.push_str(details);
+                }
+                CallToolResult {
+                    content: vec![McpContent::text(text)],
+                    structured_content: serde_json::to_value(&result).ok(),
+                    is_error: !result.success,
+                }
+            }
+            Err(e) => error_result(e.to_string()),
+        }
+    }
+}
+
+fn error_result(message: String) -> CallToolResult {
+    CallToolResult { content: vec![McpContent::text(message)], structured_content: None, is_error: true }
+}
+
+fn tool_result_to_mcp(result: ToolResult) -> CallToolResult {
+    if !result.success {
+        return error_result(result.error.unwrap_or(result.summary));
+    }
+    let text = if result.content.is_empty() { result.summary } else { result.content };
+    CallToolResult {
+        content: vec![McpContent::text(text)],
+        // Structured content has to be an object
+        structured_content: result.data.filter(Value::is_object),
+        is_error: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AgentConfig;
+    use crate::models::MockModel;
+    use crate::tools::{ReadFileTool, RunCommandTool};
+    use tokio::io::{AsyncBufReadExt, DuplexStream, Lines};
+
+    struct TestClient {
+        input: DuplexStream,
+        output: Lines<BufReader<DuplexStream>>,
+    }
+
+    impl TestClient {
+        fn start(server: McpServer) -> Self {
+            let (input, server_input) = tokio::io::duplex(64 * 1024);
+            let (server_output, output) = tokio::io::duplex(64 * 1024);
+            tokio::spawn(Arc::new(server).serve(server_input, server_output));
+            Self { input, output: BufReader::new(output).lines() }
+        }
+
+        async fn send(&mut self, message: Value) {
+            let line = format!("{}\n", message);
+            self.input.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        async fn next(&mut self) -> JsonRpcMessage {
+            let line = self.output.next_line().await.unwrap().unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+
+        async fn call(&mut self, id: u64, name: &str, arguments: Value) -> CallToolResult {
+            let params = json!({"name": name, "arguments": arguments});
+            self.send(json!({"jsonrpc": "2.0", "id": id, "method": "tools/call", "params": params})).await;
+            serde_json::from_value(self.next().await.result.unwrap()).unwrap()
+        }
+    }
+
+    async fn server() -> McpServer {
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(ReadFileTool).await;
+        registry.register(RunCommandTool).await;
+        let agent = TaskAgent::new(Box::new(MockModel::new("test".to_string())), AgentConfig::default());
+        McpServer::new(registry).with_agent(agent)
+    }
+
+    #[tokio::test]
+    async fn test_publishes_and_guards_tools() {
+        let mut client = TestClient::start(server().await);
+
+        client.send(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2024-11-05"}})).await;
+        let info: InitializeResult = serde_json::from_value(client.next().await.result.unwrap()).unwrap();
+        assert_eq!(info.protocol_version, "2024-11-05");
+
+        client.send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await;
+        client.send(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"})).await;
+        let list: ListToolsResult = serde_json::from_value(client.next().await.result.unwrap()).unwrap();
+        let names: Vec<&str> = list.tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, vec!["read_file", "run_command", RUN_TASK_TOOL]);
+        assert_eq!(list.tools[0].input_schema["required"], json!(["path"]));
+        assert_eq!(list.tools[0].annotations.as_ref().unwrap().read_only_hint, Some(true));
+
+        let echoed = client.call(3, "run_command", json!({"command": "echo hi"})).await;
+        assert!(!echoed.is_error);
+        assert_eq!(echoed.text().trim(), "hi");
+
+        // Refused by the command validator and by the protected path guardrail
+        assert!(client.call(4, "run_command", json!({"command": "rm -rf target"})).await.is_error);
+        assert!(client.call(5, "run_command", json!({"command": "shutdown now"})).await.text().contains("shutdown"));
+        let secret = client.call(6, "read_file", json!({"path": ".env"})).await;
+        assert!(secret.is_error);
+        assert!(secret.text().contains("Permission denied"));
+
+        client.send(json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": {"name": "missing"}})).await;
+        assert_eq!(client.next().await.error.unwrap().code, INVALID_PARAMS);
+        client.send(json!({"jsonrpc": "2.0", "id": 8, "method": "resources/list"})).await;
+        assert_eq!(client.next().await.error.unwrap().code, METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_run_task_reports_progress() {
+        let mut client = TestClient::start(server().await);
+
+        let params = json!({"name": RUN_TASK_TOOL, "arguments": {"task": "List files"}, "_meta": {"progressToken": "t1"}});
+        client.send(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": params})).await;
+
+        let mut progress = Vec::new();
+        let response = loop {
+            let message = client.next().await;
+            if message.is_response() {
+                break message;
+            }
+            assert_eq!(message.method.as_deref(), Some("notifications/progress"));
+            let params = message.params.unwrap();
+            assert_eq!(params["progressToken"], "t1");
+            progress.push(params["progress"].as_u64().unwrap());
+        };
+
+        assert!(progress.len() >= 2);
+        assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+        let result: CallToolResult = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(result.structured_content.unwrap().get("task_id").is_some());
+    }
+}
@@ -0,0 +1,398 @@
+//! MCP transports
+//!
+//! - [`StdioTransport`] launches the server as a child process and exchanges
+//!   newline-delimited JSON-RPC messages over its stdin and stdout. Anything
+//!   the server writes to stderr is logged.
+//! - [`HttpTransport`] POSTs each message to the server's endpoint (streamable
+//!   HTTP) and accepts either a JSON or a server-sent events response.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use crate::errors::ToolError;
+use super::protocol::{JsonRpcError, JsonRpcMessage, METHOD_NOT_FOUND};
+
+/// Header carrying the session of a streamable HTTP connection
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// A connection to one MCP server
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Send a request and wait for the response with the same id
+    async fn request(&self, request: JsonRpcMessage, timeout: Duration) -> Result<JsonRpcMessage, ToolError>;
+
+    /// Send a notification, which has no response
+    async fn notify(&self, notification: JsonRpcMessage) -> Result<(), ToolError>;
+
+    /// Whether the connection is gone and has to be opened again
+    fn is_closed(&self) -> bool;
+
+    /// Close the connection, stopping the server if this transport started it
+    async fn close(&self);
+}
+
+fn request_key(id: &Option<Value>) -> String {
+    id.as_ref().map(Value::to_string).unwrap_or_default()
+}
+
+// ============================================================================
+// Stdio
+// ============================================================================
+
+type Pending = Arc<StdMutex<HashMap<String, oneshot::Sender<JsonRpcMessage>>>>;
+
+/// Transport to a server running as a child process
+pub struct StdioTransport {
+    name: String,
+    child: Mutex<Child>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl StdioTransport {
+    /// Launch `command` and start reading its output
+    pub fn spawn(
+        name: &str,
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+    ) -> Result<Self, ToolError> {
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to start MCP server '{}' ({}): {}", name, command, e)))?;
+
+        let missing = || ToolError::ExecutionError(format!("MCP server '{}' has no stdio pipes", name));
+        let stdin = Arc::new(Mutex::new(child.stdin.take().ok_or_else(missing)?));
+        let stdout = child.stdout.take().ok_or_else(missing)?;
+        let stderr = child.stderr.take().ok_or_else(missing)?;
+
+        let pending: Pending = Arc::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let reader = tokio::spawn(read_messages(
+            name.to_string(),
+            BufReader::new(stdout),
+            stdin.clone(),
+            pending.clone(),
+            closed.clone(),
+        ));
+        let server = name.to_string();
+        let logger = tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("MCP server '{}': {}", server, line);
+            }
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            child: Mutex::new(child),
+            stdin,
+            pending,
+            closed,
+            tasks: vec![reader, logger],
+        })
+    }
+
+    fn closed_error(&self) -> ToolError {
+        ToolError::ExecutionError(format!("MCP server '{}' closed the connection", self.name))
+    }
+}
+
+/// Route responses to their waiting requests until the server's stdout closes
+async fn read_messages(
+    name: String,
+    stdout: BufReader<tokio::process::ChildStdout>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+) {
+    let mut lines = stdout.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let message: JsonRpcMessage = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!("MCP server '{}' wrote a line that is not JSON-RPC ({}): {}", name, e, line);
+                continue;
+            }
+        };
+
+        if message.is_response() {
+            let waiting = pending.lock().unwrap().remove(&request_key(&message.id));
+            match waiting {
+                Some(sender) => {
+                    let _ = sender.send(message);
+                }
+                None => tracing::debug!("MCP server '{}' answered an unknown request {:?}", name, message.id),
+            }
+        } else if message.is_request() {
+            // Only pings are served; sampling and roots are not offered in `initialize`
+            let id = message.id.clone().unwrap_or(Value::Null);
+            let reply = match message.method.as_deref() {
+                Some("ping") => JsonRpcMessage::response(id, json!({})),
+                method => JsonRpcMessage::error_response(
+                    id,
+                    JsonRpcError::new(METHOD_NOT_FOUND, format!("Method not supported: {}", method.unwrap_or_default())),
+                ),
+            };
+            if let Err(e) = write_message(&stdin, &reply).await {
+                tracing::warn!("Failed to answer MCP server '{}': {}", name, e);
+            }
+        } else {
+            tracing::debug!("MCP server '{}' sent {:?}", name, message.method);
+        }
+    }
+
+    // Mark the connection closed before dropping the waiting senders so
+    // callers that see the failure also see `is_closed`
+    closed.store(true, Ordering::SeqCst);
+    pending.lock().unwrap().clear();
+    tracing::info!("MCP server '{}' closed its output", name);
+}
+
+async fn write_message(stdin: &Mutex<ChildStdin>, message: &JsonRpcMessage) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(&line).await?;
+    stdin.flush().await
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn request(&self, request: JsonRpcMessage, timeout: Duration) -> Result<JsonRpcMessage, ToolError> {
+        if self.is_closed() {
+            return Err(self.closed_error());
+        }
+        let key = request_key(&request.id);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(key.clone(), sender);
+
+        if let Err(e) = write_message(&self.stdin, &request).await {
+            self.pending.lock().unwrap().remove(&key);
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(ToolError::ExecutionError(format!("Failed to write to MCP server '{}': {}", self.name, e)));
+        }
+
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(self.closed_error()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&key);
+                Err(ToolError::ExecutionError(format!(
+                    "MCP server '{}' did not answer {} within {}s",
+                    self.name,
+                    request.method.as_deref().unwrap_or("request"),
+                    timeout.as_secs()
+                )))
+            }
+        }
+    }
+
+    async fn notify(&self, notification: JsonRpcMessage) -> Result<(), ToolError> {
+        if self.is_closed() {
+            return Err(self.closed_error());
+        }
+        write_message(&self.stdin, &notification).await.map_err(|e| {
+            self.closed.store(true, Ordering::SeqCst);
+            ToolError::ExecutionError(format!("Failed to write to MCP server '{}': {}", self.name, e))
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let mut child = self.child.lock().await;
+        let _ = child.start_kill();
+        let _ = child.wait().await;
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+// ============================================================================
+// Streamable HTTP
+// ============================================================================
+
+/// Transport to a server reachable over streamable HTTP
+pub struct HttpTransport {
+    name: String,
+    url: String,
+    headers: HashMap<String, String>,
+    client: reqwest::Client,
+    session: StdMutex<Option<String>>,
+    closed: AtomicBool,
+}
+
+impl HttpTransport {
+    pub fn new(name: &str, url: &str, headers: &HashMap<String, String>) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            headers: headers.clone(),
+            client: reqwest::Client::new(),
+            session: StdMutex::new(None),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    async fn post(&self, message: &JsonRpcMessage, timeout: Duration) -> Result<reqwest::Response, ToolError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .timeout(timeout)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        let session = self.session.lock().unwrap().clone();
+        if let Some(session) = &session {
+            request = request.header(SESSION_HEADER, session);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Request to MCP server '{}' failed: {}", self.name, e)))?;
+
+        if let Some(id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session.lock().unwrap() = Some(id.to_string());
+        }
+        // The server forgot our session; it has to be initialized again
+        if response.status() == reqwest::StatusCode::NOT_FOUND && session.is_some() {
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(ToolError::ExecutionError(format!("MCP server '{}' ended the session", self.name)));
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ToolError::ExecutionError(format!(
+                "MCP server '{}' returned {}: {}",
+                self.name,
+                status,
+                body.chars().take(200).collect::<String>()
+            )));
+        }
+        Ok(response)
+    }
+}
+
+/// Find the response to `key` in a JSON body, which may be a batch
+fn response_from_json(body: &str, key: &str) -> Option<JsonRpcMessage> {
+    match serde_json::from_str::<Value>(body).ok()? {
+        Value::Array(messages) => messages
+            .into_iter()
+            .filter_map(|message| serde_json::from_value::<JsonRpcMessage>(message).ok())
+            .find(|message| message.is_response() && request_key(&message.id) == key),
+        message => serde_json::from_value(message).ok(),
+    }
+}
+
+/// Find the response to `key` among the events of a server-sent events body
+fn response_from_events(body: &str, key: &str) -> Option<JsonRpcMessage> {
+    body.replace("\r\n", "\n")
+        .split("\n\n")
+        .map(|event| {
+            event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|data| !data.is_empty())
+        .find_map(|data| response_from_json(&data, key).filter(|message| request_key(&message.id) == key))
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn request(&self, request: JsonRpcMessage, timeout: Duration) -> Result<JsonRpcMessage, ToolError> {
+        let response = self.post(&request, timeout).await?;
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read response of MCP server '{}': {}", self.name, e)))?;
+
+        let key = request_key(&request.id);
+        let message = if is_stream {
+            response_from_events(&body, &key)
+        } else {
+            response_from_json(&body, &key)
+        };
+        message.ok_or_else(|| {
+            ToolError::ExecutionError(format!(
+                "MCP server '{}' did not answer {}",
+                self.name,
+                request.method.as_deref().unwrap_or("request")
+            ))
+        })
+    }
+
+    async fn notify(&self, notification: JsonRpcMessage) -> Result<(), ToolError> {
+        self.post(&notification, Duration::from_secs(30)).await.map(|_| ())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let session = self.session.lock().unwrap().take();
+        if let Some(session) = session {
+            let _ = self.client.delete(&self.url).header(SESSION_HEADER, session).send().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_responses_from_http_bodies() {
+        let events = "event: message\r\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\r\n\r\n\
+                      event: message\r\ndata: {\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"ok\":true}}\r\n\r\n";
+        let response = response_from_events(events, "7").unwrap();
+        assert_eq!(response.result, Some(json!({"ok": true})));
+        assert!(response_from_events(events, "8").is_none());
+
+        let batch = r#"[{"jsonrpc":"2.0","id":1,"result":{}},{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"nope"}}]"#;
+        assert_eq!(response_from_json(batch, "2").unwrap().error.unwrap().code, METHOD_NOT_FOUND);
+    }
+}
@@ -7,6 +7,7 @@
 //! - Extract file paths from text
 //! - Extract shell commands from text
 //! - Extract directory paths from text
+//! - Extract quoted text, e.g. the content of a file to write
 //!
 //! # Examples
 //!
@@ -152,6 +153,24 @@ pub fn extract_directory_path(text: &str) -> Option<String> {
     None
 }
 
+/// Extract the first text enclosed in double or single quotes
+///
+/// # Examples
+///
+/// ```
+/// use agent_runner::parser::extract_quoted_text;
+///
+/// let text = "Write \"hello world\" to file notes.txt";
+/// assert_eq!(extract_quoted_text(text), Some("hello world".to_string()));
+/// ```
+pub fn extract_quoted_text(text: &str) -> Option<String> {
+    let start = text.find(['"', '\''])?;
+    let quote = text[start..].chars().next()?;
+    let rest = &text[start + 1..];
+    let end = rest.find(quote)?;
+    Some(rest[..end].to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,6 +224,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_quoted_text() {
+        assert_eq!(
+            extract_quoted_text("Write \"fn main() {}\" to file main.rs"),
+            Some("fn main() {}".to_string())
+        );
+
+        assert_eq!(
+            extract_quoted_text("Write 'it\"s' to file a.txt"),
+            Some("it\"s".to_string())
+        );
+
+        assert_eq!(
+            extract_quoted_text("Write \"unterminated to file a.txt"),
+            None
+        );
+    }
+
     #[test]
     fn test_has_file_extension() {
         assert!(has_file_extension("test.rs"));
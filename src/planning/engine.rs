@@ -3,6 +3,7 @@
 use crate::errors::AgentError;
 use crate::models::LanguageModel;
 use crate::prompts::{PromptBuilder, PromptTemplate};
+use super::repo_map::RepoMapBuilder;
 use crate::types::{TaskComplexity, TaskPlan};
 use std::sync::Arc;
 
@@ -61,6 +62,7 @@ pub struct PlanningEngine {
     model: Arc<dyn LanguageModel>,
     prompt_template: PromptTemplate,
     config: PlanningConfig,
+    repo_map: Option<RepoMapBuilder>,
 }
 
 impl PlanningEngine {
@@ -70,6 +72,7 @@ impl PlanningEngine {
             model,
             prompt_template: PromptTemplate::default(),
             config: PlanningConfig::default(),
+            repo_map: None,
         }
     }
 
@@ -79,6 +82,7 @@ impl PlanningEngine {
             model,
             prompt_template: template,
             config: PlanningConfig::default(),
+            repo_map: None,
         }
     }
 
@@ -88,6 +92,7 @@ impl PlanningEngine {
             model,
             prompt_template: PromptTemplate::default(),
             config,
+            repo_map: None,
         }
     }
 
@@ -101,9 +106,16 @@ impl PlanningEngine {
             model,
             prompt_template: template,
             config,
+            repo_map: None,
         }
     }
 
+    /// Include a map of the repository, ranked by relevance to the request, in planning prompts
+    pub fn with_repo_map(mut self, repo_map: RepoMapBuilder) -> Self {
+        self.repo_map = Some(repo_map);
+        self
+    }
+
     /// Load template from file
     pub fn load_template(&mut self, path: &str) -> Result<(), AgentError> {
         self.prompt_template = PromptTemplate::from_file(path)
@@ -164,7 +176,8 @@ impl PlanningEngine {
             tracing::info!("🧠 Starting task analysis for: {}", request);
         }
 
-        let prompt = self.build_understanding_prompt(request, task_type);
+        let repo_map = self.repo_map(request).await;
+        let prompt = self.build_understanding_prompt(request, task_type, repo_map.as_deref());
 
         if self.config.verbose {
             tracing::debug!("📝 Sending prompt to AI model");
@@ -191,6 +204,26 @@ impl PlanningEngine {
         Ok(plan)
     }
 
+    /// The rendered repository map, if one is configured and the workspace has source files
+    ///
+    /// Failing to build the map only costs context, so errors are logged and planning goes on.
+    async fn repo_map(&self, request: &str) -> Option<String> {
+        let builder = self.repo_map.clone()?;
+        let request = request.to_string();
+        match tokio::task::spawn_blocking(move || builder.build(&request)).await {
+            Ok(Ok(map)) if !map.is_empty() => Some(map.render()),
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => {
+                tracing::warn!("Skipping repository map: {}", e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Repository map task failed: {}", e);
+                None
+            }
+        }
+    }
+
     /// Call AI model with retry logic
     async fn call_model_with_retry(&self, prompt: &str) -> Result<String, AgentError> {
         let mut last_error = None;
@@ -221,8 +254,11 @@ impl PlanningEngine {
     }
 
     /// Build the prompt for task understanding using the template system
-    fn build_understanding_prompt(&self, request: &str, task_type: Option<&str>) -> String {
+    fn build_understanding_prompt(&self, request: &str, task_type: Option<&str>, repo_map: Option<&str>) -> String {
         let mut builder = PromptBuilder::new(self.prompt_template.clone());
+        if let Some(repo_map) = repo_map {
+            builder = builder.section("Repository Map", repo_map);
+        }
 
         // Set task type if provided
         if let Some(tt) = task_type {
@@ -27,9 +27,11 @@
 
 mod engine;
 mod approach_parser;
+pub mod repo_map;
 
 pub use engine::{PlanningEngine, PlanningConfig};
 pub use approach_parser::ApproachParser;
+pub use repo_map::{RepoMap, RepoMapBuilder, FileSymbols, Symbol, SymbolKind};
 
 // Backward compatibility aliases (deprecated)
 #[deprecated(since = "0.2.3", note = "Use `PlanningEngine` instead")]
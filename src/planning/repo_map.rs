@@ -0,0 +1,395 @@
+//! Repository map for planning context
+//!
+//! Without it the planner only sees the request. [`RepoMapBuilder`] walks the
+//! workspace (respecting ignore files), extracts the top-level symbols of
+//! each source file with per-language patterns, ranks files by how many
+//! request terms their path and symbols mention, and renders as many of them
+//! as fit a token budget. Symbols are cached per file and only extracted
+//! again when the file's modification time or size changes.
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+use crate::errors::AgentError;
+use crate::tools::walk::{read_text, relative_path, split_words, WorkspaceWalk};
+
+/// Symbols listed per file at most
+const MAX_SYMBOLS_PER_FILE: usize = 40;
+
+/// Characters of a signature kept in the map
+const MAX_SIGNATURE_CHARS: usize = 100;
+
+/// Kind of a top-level symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Class,
+    Interface,
+    Type,
+}
+
+/// A top-level declaration in a source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// 1-based line of the declaration
+    pub line: usize,
+    /// The declaration line, without its body
+    pub signature: String,
+}
+
+/// Symbols of one file and its relevance to the request
+#[derive(Debug, Clone)]
+pub struct FileSymbols {
+    pub path: String,
+    pub symbols: Vec<Symbol>,
+    pub score: f64,
+}
+
+/// Ranked files that fit the token budget
+#[derive(Debug, Clone, Default)]
+pub struct RepoMap {
+    pub files: Vec<FileSymbols>,
+    /// Source files left out to stay within the budget
+    pub omitted_files: usize,
+}
+
+impl RepoMap {
+    /// The map as indented text, one file per block
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for file in &self.files {
+            text.push_str(&render_file(file));
+        }
+        if self.omitted_files > 0 {
+            text.push_str(&format!("... {} more files not shown\n", self.omitted_files));
+        }
+        text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Symbols of a file as of the modification time and size they were read at
+#[derive(Debug, Clone)]
+struct CachedSymbols {
+    modified: Option<SystemTime>,
+    len: u64,
+    symbols: Vec<Symbol>,
+}
+
+impl CachedSymbols {
+    fn is_current(&self, metadata: &std::fs::Metadata) -> bool {
+        self.modified.is_some() && self.modified == metadata.modified().ok() && self.len == metadata.len()
+    }
+}
+
+/// Builds a [`RepoMap`] of a workspace
+///
+/// Clones share the symbol cache.
+#[derive(Debug, Clone)]
+pub struct RepoMapBuilder {
+    root: PathBuf,
+    token_budget: usize,
+    cache: Arc<Mutex<HashMap<PathBuf, CachedSymbols>>>,
+}
+
+impl RepoMapBuilder {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), token_budget: 1024, cache: Arc::default() }
+    }
+
+    /// Approximate number of tokens the rendered map may take
+    pub fn with_token_budget(mut self, token_budget: usize) -> Self {
+        self.token_budget = token_budget;
+        self
+    }
+
+    /// Map the workspace, ranking files by relevance to `request`
+    pub fn build(&self, request: &str) -> Result<RepoMap, AgentError> {
+        let walk = WorkspaceWalk::new(&self.root);
+        let files = walk.files().map_err(AgentError::ToolError)?;
+        let terms = terms(request);
+
+        // Files that disappeared fall out of the cache with this swap
+        let mut cache = self.cache.lock().unwrap();
+        let previous = std::mem::take(&mut *cache);
+        let mut ranked = Vec::new();
+        for path in files {
+            let Some(cached) = self.symbols(&path, &previous) else { continue };
+            ranked.push(self.rank(&path, cached.symbols.clone(), &terms));
+            cache.insert(path, cached);
+        }
+        drop(cache);
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+
+        let mut map = RepoMap::default();
+        let mut used = 0;
+        for file in ranked {
+            let tokens = estimate_tokens(&render_file(&file));
+            if used + tokens > self.token_budget {
+                map.omitted_files += 1;
+                continue;
+            }
+            used += tokens;
+            map.files.push(file);
+        }
+        Ok(map)
+    }
+
+    /// Symbols of a source file, from the cache while the file is unchanged
+    fn symbols(&self, path: &Path, previous: &HashMap<PathBuf, CachedSymbols>) -> Option<CachedSymbols> {
+        let language = Language::from_path(path)?;
+        let metadata = std::fs::metadata(path).ok()?;
+        if let Some(cached) = previous.get(path).filter(|cached| cached.is_current(&metadata)) {
+            return Some(cached.clone());
+        }
+        let content = read_text(path)?;
+        let mut symbols = extract_symbols(language, &content);
+        symbols.truncate(MAX_SYMBOLS_PER_FILE);
+        Some(CachedSymbols { modified: metadata.modified().ok(), len: metadata.len(), symbols })
+    }
+
+    fn rank(&self, path: &Path, symbols: Vec<Symbol>, terms: &HashSet<String>) -> FileSymbols {
+        let relative = relative_path(&self.root, path);
+        let path_terms = split_identifier(&relative);
+        let mut score = 0.0;
+        for term in terms {
+            if path_terms.contains(term) {
+                score += 3.0;
+            }
+            let mentions = symbols.iter().filter(|symbol| split_identifier(&symbol.name).contains(term)).count();
+            score += 2.0 * mentions.min(3) as f64;
+        }
+        FileSymbols { path: relative, symbols, score }
+    }
+}
+
+fn render_file(file: &FileSymbols) -> String {
+    let mut text = format!("{}\n", file.path);
+    for symbol in &file.symbols {
+        text.push_str(&format!("  {}\n", symbol.signature));
+    }
+    text
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// ============================================================================
+// Symbol extraction
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+}
+
+impl Language {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => Some(Self::JavaScript),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+}
+
+/// Patterns for top-level declarations; the `name` group is the symbol name
+static PATTERNS: LazyLock<Vec<(Language, SymbolKind, Regex)>> = LazyLock::new(|| {
+    let rust_vis = r"(?:pub(?:\([^)]*\))?\s+)?";
+    let specs: Vec<(Language, SymbolKind, String)> = vec![
+        (Language::Rust, SymbolKind::Function, format!(r"^{}(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+\S+\s+)?fn\s+(?P<name>\w+)", rust_vis)),
+        (Language::Rust, SymbolKind::Struct, format!(r"^{}struct\s+(?P<name>\w+)", rust_vis)),
+        (Language::Rust, SymbolKind::Enum, format!(r"^{}enum\s+(?P<name>\w+)", rust_vis)),
+        (Language::Rust, SymbolKind::Trait, format!(r"^{}(?:unsafe\s+)?trait\s+(?P<name>\w+)", rust_vis)),
+        (Language::Rust, SymbolKind::Impl, r"^(?:unsafe\s+)?impl(?:<[^{]*?>)?\s+(?:[\w:<>, ]+?\s+for\s+)?(?P<name>[\w:]+)".to_string()),
+        (Language::Rust, SymbolKind::Type, format!(r"^{}type\s+(?P<name>\w+)", rust_vis)),
+        (Language::Python, SymbolKind::Function, r"^(?:async\s+)?def\s+(?P<name>\w+)".to_string()),
+        (Language::Python, SymbolKind::Class, r"^class\s+(?P<name>\w+)".to_string()),
+        (Language::JavaScript, SymbolKind::Function, r"^(?:export\s+)?(?:default\s+)?(?:async\s+)?function\*?\s+(?P<name>\w+)".to_string()),
+        (Language::JavaScript, SymbolKind::Function, r"^(?:export\s+)?(?:const|let|var)\s+(?P<name>\w+)\s*=\s*(?:async\s+)?(?:function|\([^)]*\)\s*=>|\w+\s*=>)".to_string()),
+        (Language::JavaScript, SymbolKind::Class, r"^(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+(?P<name>\w+)".to_string()),
+        (Language::JavaScript, SymbolKind::Interface, r"^(?:export\s+)?interface\s+(?P<name>\w+)".to_string()),
+        (Language::JavaScript, SymbolKind::Type, r"^(?:export\s+)?type\s+(?P<name>\w+)\s*=".to_string()),
+        (Language::Go, SymbolKind::Function, r"^func\s+(?:\([^)]*\)\s*)?(?P<name>\w+)".to_string()),
+        (Language::Go, SymbolKind::Struct, r"^type\s+(?P<name>\w+)\s+struct\b".to_string()),
+        (Language::Go, SymbolKind::Interface, r"^type\s+(?P<name>\w+)\s+interface\b".to_string()),
+    ];
+    specs
+        .into_iter()
+        .map(|(language, kind, pattern)| (language, kind, Regex::new(&pattern).expect("valid symbol pattern")))
+        .collect()
+});
+
+/// Top-level declarations of `content`, in file order
+///
+/// Only unindented lines are considered, so methods and nested items are
+/// left out; Rust `impl` blocks stand in for their methods.
+fn extract_symbols(language: Language, content: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let declaration = line;
+        for (pattern_language, kind, pattern) in PATTERNS.iter() {
+            if *pattern_language != language {
+                continue;
+            }
+            if let Some(captures) = pattern.captures(declaration) {
+                symbols.push(Symbol {
+                    kind: *kind,
+                    name: captures["name"].to_string(),
+                    line: index + 1,
+                    signature: signature(declaration),
+                });
+                break;
+            }
+        }
+    }
+    symbols
+}
+
+/// The declaration without its body, shortened
+fn signature(line: &str) -> String {
+    let line = line.split('{').next().unwrap_or(line).trim_end();
+    let line = line.strip_suffix(':').unwrap_or(line).trim_end();
+    if line.chars().count() > MAX_SIGNATURE_CHARS {
+        let cut: String = line.chars().take(MAX_SIGNATURE_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+// ============================================================================
+// Relevance
+// ============================================================================
+
+/// Words shorter than this are ignored when matching the request
+const MIN_TERM_LEN: usize = 3;
+
+/// Common English words that say nothing about where to look
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "into", "when", "should", "add", "make", "use",
+    "new", "all", "not", "are", "can", "let", "get", "set", "fix", "support", "instead", "which", "their",
+];
+
+/// Lower-case terms of the request that are worth matching
+fn terms(request: &str) -> HashSet<String> {
+    split_identifier(request)
+        .into_iter()
+        .filter(|term| term.len() >= MIN_TERM_LEN && !STOP_WORDS.contains(&term.as_str()))
+        .collect()
+}
+
+/// The distinct lower-case words of an identifier, path or sentence
+fn split_identifier(text: &str) -> HashSet<String> {
+    split_words(text).into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn names(language: Language, content: &str) -> Vec<(SymbolKind, String)> {
+        extract_symbols(language, content).into_iter().map(|symbol| (symbol.kind, symbol.name)).collect()
+    }
+
+    #[test]
+    fn test_extracts_top_level_symbols() {
+        let rust = "pub struct Registry {\n    tools: Vec<Tool>,\n}\n\nimpl<T: Clone> Default for Registry {\n    fn default() -> Self { todo!() }\n}\n\npub(crate) async fn load(path: &Path) -> Result<()> {\n}\npub trait Tool: Send {}\n";
+        assert_eq!(names(Language::Rust, rust), vec![
+            (SymbolKind::Struct, "Registry".to_string()),
+            (SymbolKind::Impl, "Registry".to_string()),
+            (SymbolKind::Function, "load".to_string()),
+            (SymbolKind::Trait, "Tool".to_string()),
+        ]);
+        assert_eq!(extract_symbols(Language::Rust, rust)[2].signature, "pub(crate) async fn load(path: &Path) -> Result<()>");
+
+        let python = "class Parser:\n    def parse(self):\n        pass\n\nasync def main():\n    pass\n";
+        assert_eq!(names(Language::Python, python), vec![
+            (SymbolKind::Class, "Parser".to_string()),
+            (SymbolKind::Function, "main".to_string()),
+        ]);
+
+        let js = "export default function App() {}\nexport const useStore = (init) => {}\nexport interface Props {}\nclass Cache {}\n";
+        assert_eq!(names(Language::JavaScript, js), vec![
+            (SymbolKind::Function, "App".to_string()),
+            (SymbolKind::Function, "useStore".to_string()),
+            (SymbolKind::Interface, "Props".to_string()),
+            (SymbolKind::Class, "Cache".to_string()),
+        ]);
+
+        let go = "type Server struct {\n}\nfunc (s *Server) Start() error {\n}\nfunc NewServer() *Server {\n}\n";
+        assert_eq!(names(Language::Go, go), vec![
+            (SymbolKind::Struct, "Server".to_string()),
+            (SymbolKind::Function, "Start".to_string()),
+            (SymbolKind::Function, "NewServer".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_ranks_by_request_and_fits_budget() {
+        let root = TempDir::new("repo_map");
+        std::fs::create_dir_all(root.join("src/billing")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/billing/invoice.rs"), "pub struct Invoice;\npub fn render_invoice() {}\n").unwrap();
+        std::fs::write(root.join("src/users.py"), "class UserStore:\n    pass\n").unwrap();
+        std::fs::write(root.join("target/generated.rs"), "pub fn invoice_generated() {}\n").unwrap();
+        std::fs::write(root.join("README.md"), "# Invoices\n").unwrap();
+
+        let map = RepoMapBuilder::new(&root).build("Fix rounding in invoice totals").unwrap();
+        let paths: Vec<&str> = map.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/billing/invoice.rs", "src/main.rs", "src/users.py"]);
+        assert!(map.render().starts_with("src/billing/invoice.rs\n  pub struct Invoice;\n  pub fn render_invoice()\n"));
+
+        let small = RepoMapBuilder::new(&root).with_token_budget(20).build("invoice").unwrap();
+        assert_eq!(small.files.len(), 1);
+        assert_eq!(small.files[0].path, "src/billing/invoice.rs");
+        assert_eq!(small.omitted_files, 2);
+        assert!(small.render().ends_with("... 2 more files not shown\n"));
+    }
+
+    #[test]
+    fn test_symbols_are_cached_until_the_file_changes() {
+        let root = TempDir::new("repo_map_cache");
+        let file = root.join("lib.rs");
+        std::fs::write(&file, "pub fn alpha() {}\n").unwrap();
+        let builder = RepoMapBuilder::new(&*root);
+        assert_eq!(builder.build("alpha").unwrap().files[0].symbols[0].name, "alpha");
+
+        // Same size and modification time: the cached symbols are used
+        let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+        std::fs::write(&file, "pub fn gamma() {}\n").unwrap();
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(modified).unwrap();
+        assert_eq!(builder.clone().build("alpha").unwrap().files[0].symbols[0].name, "alpha");
+
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(builder.build("alpha").unwrap().files[0].symbols[0].name, "gamma");
+    }
+}
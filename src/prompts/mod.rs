@@ -98,6 +98,7 @@ pub struct PromptBuilder {
     template: PromptTemplate,
     task_type: Option<String>,
     context: HashMap<String, String>,
+    sections: Vec<(String, String)>,
 }
 
 impl PromptBuilder {
@@ -107,6 +108,7 @@ impl PromptBuilder {
             template,
             task_type: None,
             context: HashMap::new(),
+            sections: Vec::new(),
         }
     }
 
@@ -122,6 +124,12 @@ impl PromptBuilder {
         self
     }
 
+    /// Add a `# {title}` section ahead of the user request, e.g. a repository map
+    pub fn section(mut self, title: &str, body: &str) -> Self {
+        self.sections.push((title.to_string(), body.to_string()));
+        self
+    }
+
     /// Build the final prompt
     pub fn build(&self, user_request: &str) -> String {
         let mut prompt = String::new();
@@ -151,6 +159,11 @@ impl PromptBuilder {
             prompt.push('\n');
         }
 
+        // 2b. Extra sections, e.g. the repository map
+        for (title, body) in &self.sections {
+            prompt.push_str(&format!("# {}\n{}\n\n", title, body.trim_end()));
+        }
+
         // 3. Scenario-specific instructions
         if let Some(ref task_type) = self.task_type {
             if let Some(scenario) = self.template.scenarios.get(task_type) {
@@ -277,5 +290,17 @@ mod tests {
         assert!(prompt.contains("User Request"));
         assert!(prompt.contains("hello world"));
     }
-}
 
+    #[test]
+    fn test_section_precedes_user_request() {
+        let prompt = PromptBuilder::new(PromptTemplate::default())
+            .context("language", "Rust")
+            .section("Repository Map", "src/lib.rs\n  pub fn run()\n")
+            .build("Fix run");
+
+        let map = prompt.find("# Repository Map\nsrc/lib.rs\n  pub fn run()\n\n").unwrap();
+        assert!(map < prompt.find("# User Request").unwrap());
+        assert!(prompt.contains("# Additional Context\n**language**: Rust\n"));
+        assert!(!prompt.contains("**Repository Map**"));
+    }
+}
@@ -1,13 +1,17 @@
 //! Agent Runner Service HTTP Server
 
 use axum::{
-    extract::{Path, State},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
     http::StatusCode,
-    response::Json,
+    response::{Json, Response},
     routing::{delete, get, post, put},
     Router,
     middleware,
 };
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -19,7 +23,7 @@ use tower_http::{
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use agent_runner::{TaskAgentService, ServiceConfig, TaskRequest, BatchTaskRequest, TaskResponse, BatchTaskResponse, ServiceStatus, MetricsSnapshot, ServiceError};
+use agent_runner::{TaskAgentService, ServiceConfig, TaskRequest, BatchTaskRequest, TaskResponse, TaskRevertResponse, BatchTaskResponse, ServiceStatus, MetricsSnapshot, ServiceError, WebSocketMessage};
 use agent_runner::config::AgentConfig;
 
 #[derive(Clone)]
@@ -28,20 +32,6 @@ struct AppState {
     config: Arc<tokio::sync::RwLock<AgentConfig>>,
 }
 
-#[derive(Deserialize)]
-struct TaskQuery {
-    #[serde(default)]
-    verbose: bool,
-}
-
-#[derive(Deserialize)]
-struct PaginationQuery {
-    #[serde(default = "default_limit")]
-    limit: usize,
-    #[serde(default = "default_offset")]
-    offset: usize,
-}
-
 #[derive(Deserialize)]
 struct ModelConfigUpdate {
     provider: Option<String>,
@@ -64,9 +54,6 @@ struct ConfigValidationResponse {
     warnings: Vec<String>,
 }
 
-fn default_limit() -> usize { 50 }
-fn default_offset() -> usize { 0 }
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
@@ -122,6 +109,8 @@ fn create_router(service: Arc<TaskAgentService>, config: Arc<tokio::sync::RwLock
         .route("/api/v1/tasks/batch", post(execute_batch))
         .route("/api/v1/tasks/:task_id", get(get_task_status))
         .route("/api/v1/tasks/:task_id", delete(cancel_task))
+        .route("/api/v1/tasks/:task_id/revert", post(revert_task))
+        .route("/api/v1/tasks/:task_id/updates", get(task_updates))
 
         // Configuration management
         .route("/api/v1/config", get(get_config))
@@ -130,7 +119,7 @@ fn create_router(service: Arc<TaskAgentService>, config: Arc<tokio::sync::RwLock
         .route("/api/v1/config/validate", post(validate_config))
 
         // Legacy routes for backward compatibility
-        .route("/tasks", post(execute_task))
+        .route("/tasks", post(execute_task_legacy))
         .route("/config", get(get_service_status))
 
         .nest_service("/metrics", axum::routing::get(prometheus_metrics_handler))
@@ -161,31 +150,9 @@ async fn get_metrics(State(state): State<AppState>) -> Result<Json<MetricsSnapsh
     state.service.get_metrics().await.map(Json)
 }
 
-async fn list_tools() -> Result<Json<serde_json::Value>, ServiceError> {
-    Ok(Json(serde_json::json!({
-        "tools": [
-            {
-                "name": "read_file",
-                "description": "Read the contents of a file",
-                "parameters": ["path"]
-            },
-            {
-                "name": "write_file",
-                "description": "Write content to a file",
-                "parameters": ["path", "content"]
-            },
-            {
-                "name": "run_command",
-                "description": "Execute a shell command",
-                "parameters": ["command", "working_dir"]
-            },
-            {
-                "name": "list_files",
-                "description": "List files and directories",
-                "parameters": ["path"]
-            }
-        ]
-    })))
+async fn list_tools(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ServiceError> {
+    let tools = state.service.list_tools().await?;
+    Ok(Json(serde_json::json!({ "tools": tools })))
 }
 
 async fn execute_task(
@@ -254,6 +221,47 @@ async fn cancel_task(
     Ok(StatusCode::NO_CONTENT)
 }
 
+async fn revert_task(
+    State(state): State<AppState>,
+    Path(task_id): Path<String>,
+) -> Result<Json<TaskRevertResponse>, ServiceError> {
+    info!("Reverting task {}", task_id);
+    state.service.revert_task(&task_id).await.map(Json)
+}
+
+/// WebSocket of live task updates, one JSON `WebSocketMessage` per text frame
+async fn task_updates(
+    State(state): State<AppState>,
+    Path(task_id): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    // Subscribe before the upgrade so no update is lost in between
+    let updates = state.service.subscribe_to_task_updates(&task_id);
+    upgrade.on_upgrade(move |socket| forward_task_updates(socket, updates))
+}
+
+/// Send updates until the task ends or the client goes away
+async fn forward_task_updates(mut socket: WebSocket, updates: impl Stream<Item = WebSocketMessage> + Send + 'static) {
+    let mut updates = Box::pin(updates);
+    loop {
+        tokio::select! {
+            update = updates.next() => {
+                let Some(update) = update else { break };
+                let finished = matches!(update, WebSocketMessage::TaskCompleted { .. } | WebSocketMessage::TaskFailed { .. });
+                let Ok(text) = serde_json::to_string(&update) else { continue };
+                if socket.send(Message::Text(text)).await.is_err() || finished {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.close().await;
+}
+
 // Configuration management endpoints
 
 async fn get_config(State(state): State<AppState>) -> Result<Json<serde_json::Value>, ServiceError> {
@@ -464,18 +472,10 @@ async fn load_service_config() -> Result<ServiceConfig, anyhow::Error> {
         .and_then(|s| s.parse().ok())
         .unwrap_or(300);
 
-    let enable_metrics = std::env::var("AGENT_RUNNER_ENABLE_METRICS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(true);
-
     Ok(ServiceConfig {
         max_concurrent_tasks,
-        default_task_timeout,
-        max_task_timeout: 3600,
-        enable_metrics,
-        log_level: std::env::var("AGENT_RUNNER_LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
-        log_level: std::env::var("AGENT_RUNNER_LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+        default_task_timeout: Some(default_task_timeout),
+        ..ServiceConfig::default()
     })
 }
 
@@ -508,6 +508,7 @@ async fn load_agent_config() -> Result<AgentConfig, anyhow::Error> {
             _ => return Err(anyhow::anyhow!("Unsupported model provider: {}", provider)),
         };
 
+        let defaults = AgentConfig::default();
         Ok(AgentConfig {
             model: agent_runner::config::ModelConfig {
                 provider: provider_config,
@@ -540,20 +541,32 @@ async fn load_agent_config() -> Result<AgentConfig, anyhow::Error> {
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(300),
+                budget: agent_runner::config::BudgetConfig {
+                    max_tokens: std::env::var("AGENT_RUNNER_MAX_TOKENS_BUDGET")
+                        .ok()
+                        .and_then(|s| s.parse().ok()),
+                    max_cost_usd: std::env::var("AGENT_RUNNER_MAX_COST_USD")
+                        .ok()
+                        .and_then(|s| s.parse().ok()),
+                    ..Default::default()
+                },
+                // AGENT_RUNNER_JOURNAL_DIR moves the change journal used by task revert
+                journal: std::env::var("AGENT_RUNNER_JOURNAL_DIR")
+                    .map(|dir| agent_runner::config::JournalConfig { enabled: true, dir })
+                    .unwrap_or_default(),
+                repo_map: Default::default(),
+                index: Default::default(),
             },
             tools: agent_runner::config::ToolConfig {
                 auto_discovery: true,
                 custom_tools_path: None,
-                enabled_tools: vec![
-                    "read_file".to_string(),
-                    "write_file".to_string(),
-                    "run_command".to_string(),
-                    "list_files".to_string(),
-                ],
+                enabled_tools: vec![],
                 disabled_tools: vec![],
+                mcp_servers: vec![],
+                output: Default::default(),
             },
-            safety: Default::default(),
-            logging: Default::default(),
+            safety: defaults.safety,
+            logging: defaults.logging,
         })
     }
 }
\ No newline at end of file
@@ -7,7 +7,7 @@ use serde_json;
 use futures::stream::{Stream};
 
 use crate::service::types::{
-    TaskRequest, TaskResponse, BatchTaskRequest, BatchTaskResponse,
+    TaskRequest, TaskResponse, TaskRevertResponse, BatchTaskRequest, BatchTaskResponse,
     ServiceStatus, WebSocketMessage, TaskContext, BatchExecutionMode,
     ServiceError,
 };
@@ -29,6 +29,9 @@ pub trait TaskAgentApi: Send + Sync {
     /// Cancel a running task
     async fn cancel_task(&self, task_id: &str) -> ServiceResult<()>;
 
+    /// Revert the file changes of a finished task
+    async fn revert_task(&self, task_id: &str) -> ServiceResult<TaskRevertResponse>;
+
     /// Get service status
     async fn get_service_status(&self) -> ServiceResult<ServiceStatus>;
 
@@ -69,6 +72,10 @@ impl TaskAgentApi for InProcessApi {
         self.service.cancel_task(task_id).await
     }
 
+    async fn revert_task(&self, task_id: &str) -> ServiceResult<TaskRevertResponse> {
+        self.service.revert_task(task_id).await
+    }
+
     async fn get_service_status(&self) -> ServiceResult<ServiceStatus> {
         self.service.get_service_status().await
     }
@@ -77,9 +84,8 @@ impl TaskAgentApi for InProcessApi {
         self.service.get_metrics().await
     }
 
-    async fn subscribe_to_task_updates(&self, _task_id: &str) -> ServiceResult<Box<dyn Stream<Item = WebSocketMessage> + Send>> {
-        // TODO: Implement in-process event streaming
-        Err(ErrorBuilder::service_unavailable("Task subscription not available for in-process API"))
+    async fn subscribe_to_task_updates(&self, task_id: &str) -> ServiceResult<Box<dyn Stream<Item = WebSocketMessage> + Send>> {
+        Ok(Box::new(self.service.subscribe_to_task_updates(task_id)))
     }
 }
 
@@ -187,6 +193,14 @@ impl TaskAgentApi for HttpClientApi {
         }
     }
 
+    async fn revert_task(&self, task_id: &str) -> ServiceResult<TaskRevertResponse> {
+        let request_builder = self.build_request(reqwest::Method::POST, &format!("/tasks/{}/revert", task_id)).await?;
+        let response = request_builder.send().await
+            .map_err(|e| ErrorBuilder::service_unavailable(format!("Failed to send request: {}", e)))?;
+
+        self.handle_response(response).await
+    }
+
     async fn get_service_status(&self) -> ServiceResult<ServiceStatus> {
         let request_builder = self.build_request(reqwest::Method::GET, "/status").await?;
         let response = request_builder.send().await
@@ -3,7 +3,8 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{RwLock, Semaphore};
+use futures::Stream;
+use tokio::sync::{broadcast, RwLock, Semaphore};
 use tokio::time::timeout;
 use chrono::Utc;
 use uuid::Uuid;
@@ -12,17 +13,22 @@ use dashmap::DashMap;
 
 use crate::agent::TaskAgent;
 use crate::config::AgentConfig;
-use crate::models::{LanguageModel, LlmModel};
+use crate::execution::{BudgetLimits, OutputLine, OutputStream};
+use crate::models::{LanguageModel, LlmModel, ToolDefinition};
+use crate::tools::{OutputObserver, ToolRegistry};
 use crate::service::types::{
     self as service_types,
-    TaskRequest, TaskResponse, TaskStatus, TaskPlan, TaskMetrics, TaskComplexity,
+    TaskRequest, TaskResponse, TaskRevertResponse, TaskStatus, TaskPlan, TaskMetrics, TaskComplexity,
     BatchTaskRequest, BatchTaskResponse, BatchExecutionMode, BatchStatistics,
     StepType, StepStatus, ExecutionStep,
-    ServiceConfig, ServiceStatus,
+    ServiceConfig, ServiceStatus, WebSocketMessage,
 };
 use crate::service::error::{ServiceResult, ServiceErrorType, ErrorBuilder};
 use crate::service::metrics_simple::{MetricsCollector, MetricsSnapshot};
 
+/// Messages kept for slow task subscribers before they start skipping
+const TASK_UPDATE_CAPACITY: usize = 1024;
+
 /// Task Agent Service
 ///
 /// A general-purpose service for executing various types of tasks through AI agents.
@@ -40,6 +46,10 @@ pub struct TaskAgentService {
     task_semaphore: Arc<Semaphore>,
     /// Available tools
     available_tools: Vec<String>,
+    /// The agent's tool registry, reachable while a task holds the agent
+    tools: Arc<ToolRegistry>,
+    /// Live task updates, such as streamed command output
+    updates: broadcast::Sender<WebSocketMessage>,
 }
 
 /// Task execution context
@@ -73,15 +83,29 @@ impl TaskAgentService {
         let model = create_model_from_config(&agent_config)?;
         let agent = TaskAgent::new(model, agent_config.clone());
 
-        // Register basic tools
-        register_basic_tools(&agent).await?;
+        // Register basic tools, configured plugins and MCP server tools
+        agent.register_default_tools().await;
+
+        // Whatever survived the enabled/disabled filter of the registry
+        let mut available_tools = agent.get_tools().get_tool_names().await;
+        available_tools.sort();
+
+        // Stream command output of running tools to task subscribers
+        let (updates, _) = broadcast::channel(TASK_UPDATE_CAPACITY);
+        let active_tasks = Arc::new(DashMap::new());
+        agent.get_tools().set_output_observer(Some(Arc::new(TaskOutputForwarder {
+            updates: updates.clone(),
+            active_tasks: Arc::clone(&active_tasks),
+        })));
 
         let service = Self {
-            available_tools: get_available_tools(),
+            available_tools,
+            tools: agent.get_tools(),
+            updates,
             task_semaphore: Arc::new(Semaphore::new(config.max_concurrent_tasks as usize)),
             metrics: Arc::new(MetricsCollector::new()),
             agent: Arc::new(RwLock::new(agent)),
-            active_tasks: Arc::new(DashMap::new()),
+            active_tasks,
             config,
         };
 
@@ -161,6 +185,8 @@ impl TaskAgentService {
                 Ok(task_result)
             }
             Err(_) => {
+                // The run was dropped before it could clean up; stop its background processes and sessions
+                self.tools.finish_task(&task_id).await;
                 let error = ErrorBuilder::task_timeout(&task_id);
                 self.metrics.record_task_completion(0.0, false).await;
                 self.metrics.record_error("timeout").await;
@@ -286,10 +312,39 @@ impl TaskAgentService {
         }
     }
 
+    /// Revert the file changes a finished task made
+    ///
+    /// Fails with `CONFLICT` when any of the files changed since the task ran.
+    pub async fn revert_task(&self, task_id: &str) -> ServiceResult<TaskRevertResponse> {
+        if self.active_tasks.contains_key(task_id) {
+            return Err(ErrorBuilder::conflict(format!("Task {} is still running", task_id)));
+        }
+
+        let store = self.agent.read().await.get_journal_store().cloned()
+            .ok_or_else(|| ErrorBuilder::configuration_error("Change journal is disabled"))?;
+
+        let report = store.revert(Some(task_id)).await.map_err(|e| match e {
+            crate::errors::AgentError::InvalidState(message) if message.starts_with("No change journal") => {
+                ErrorBuilder::task_not_found(task_id)
+            }
+            e => ServiceErrorType::from(e).to_service_error(),
+        })?;
+        info!("Task {} reverted", task_id);
+
+        Ok(TaskRevertResponse {
+            task_id: report.task_id,
+            restored_files: report.restored_files,
+            deleted_files: report.deleted_files,
+            reverted_at: report.reverted_at,
+        })
+    }
+
     /// Cancel a running task
     pub async fn cancel_task(&self, task_id: &str) -> ServiceResult<()> {
         if let Some(mut task_context) = self.active_tasks.get_mut(task_id) {
             task_context.status = TaskStatus::Cancelled;
+            drop(task_context);
+            self.tools.finish_task(task_id).await;
             info!("Task {} cancelled", task_id);
             Ok(())
         } else {
@@ -297,6 +352,24 @@ impl TaskAgentService {
         }
     }
 
+    /// Live updates of one task, such as `TaskProgress` messages for each line of command output
+    ///
+    /// The stream ends when the service is dropped. A subscriber that falls
+    /// more than `TASK_UPDATE_CAPACITY` messages behind skips the oldest ones.
+    /// The HTTP server serves it as a WebSocket at `/api/v1/tasks/:task_id/updates`.
+    pub fn subscribe_to_task_updates(&self, task_id: &str) -> impl Stream<Item = WebSocketMessage> + Send + 'static {
+        let receiver = self.updates.subscribe();
+        futures::stream::unfold((receiver, task_id.to_string()), |(mut receiver, task_id)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) if message.task_id() == task_id => return Some((message, (receiver, task_id))),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
     /// Get service status
     pub async fn get_service_status(&self) -> ServiceResult<ServiceStatus> {
         let metrics_snapshot = self.metrics.get_metrics_snapshot().await;
@@ -322,6 +395,11 @@ impl TaskAgentService {
         })
     }
 
+    /// Definitions of the registered tools, with JSON Schema parameters
+    pub async fn list_tools(&self) -> ServiceResult<Vec<ToolDefinition>> {
+        Ok(self.tools.registered_definitions().await)
+    }
+
     /// Get metrics snapshot
     pub async fn get_metrics(&self) -> ServiceResult<MetricsSnapshot> {
         Ok(self.metrics.get_metrics_snapshot().await)
@@ -373,7 +451,22 @@ impl TaskAgentService {
         // Execute task using the agent
         let agent_result = {
             let mut agent = self.agent.write().await;
-            match agent.process_task(&task_request.task).await {
+
+            // Per-task constraints override the configured run budget
+            let mut limits = BudgetLimits::from_config(agent.get_config());
+            if let Some(constraints) = task_request.context.as_ref().and_then(|c| c.constraints.as_ref()) {
+                if let Some(max_steps) = constraints.max_steps {
+                    limits.max_steps = Some(max_steps);
+                }
+                if let Some(max_execution_time) = constraints.max_execution_time {
+                    limits.max_duration = Some(Duration::from_secs(max_execution_time));
+                }
+            }
+
+            // Per-task tool allowlist
+            let allowed_tools = task_request.context.as_ref().and_then(|c| c.tools.as_deref());
+
+            match agent.process_task_scoped(&task_id, &task_request.task, limits, allowed_tools).await {
                 Ok(result) => {
                     // Update planning step
                     let planning_duration = planning_start.elapsed().as_millis() as u64;
@@ -491,6 +584,21 @@ impl TaskAgentService {
                 context.metrics.planning_time_ms = Some(planning_start.elapsed().as_millis() as u64);
                 context.metrics.execution_time_ms = Some(execution_start.elapsed().as_millis() as u64);
                 context.metrics.steps_executed = steps.len() as u32;
+                if let Some(usage) = &agent_result.budget {
+                    context.metrics.tokens_used = Some(usage.total_tokens);
+                    context.metrics.tool_calls = usage.tool_calls;
+                    context.metrics.model_calls = usage.model_calls;
+                }
+            }
+
+            // Report budget exhaustion as a structured error
+            let error = agent_result
+                .budget
+                .as_ref()
+                .filter(|usage| usage.exhausted.is_some())
+                .map(|_| ErrorBuilder::budget_exceeded(agent_result.summary.clone()));
+            if let Some(error) = &error {
+                self.metrics.record_error(&error.code).await;
             }
 
             // Get final metrics
@@ -513,7 +621,7 @@ impl TaskAgentService {
                 plan: agent_result.task_plan.map(|p| p.with_service_fields()),
                 steps,
                 metrics,
-                error: None,
+                error,
                 created_at: Utc::now(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
@@ -549,25 +657,42 @@ fn create_model_from_config(config: &AgentConfig) -> Result<Box<dyn LanguageMode
         .map_err(|e| ServiceErrorType::ConfigurationError(format!("Failed to create model: {}", e)))
 }
 
-/// Register basic tools with the agent
-async fn register_basic_tools(agent: &TaskAgent) -> ServiceResult<()> {
-    use crate::tools::{ReadFileTool, WriteFileTool, RunCommandTool, ListFilesTool};
-
-    agent.register_tool(ReadFileTool).await;
-    agent.register_tool(WriteFileTool).await;
-    agent.register_tool(RunCommandTool).await;
-    agent.register_tool(ListFilesTool).await;
-
-    Ok(())
+/// Publishes each line of tool output as a `TaskProgress` message of its task
+struct TaskOutputForwarder {
+    updates: broadcast::Sender<WebSocketMessage>,
+    active_tasks: Arc<DashMap<String, TaskContext>>,
 }
 
-/// Get list of available tools
-fn get_available_tools() -> Vec<String> {
-    vec![
-        "read_file".to_string(),
-        "write_file".to_string(),
-        "run_command".to_string(),
-        "list_files".to_string(),
-    ]
+impl OutputObserver for TaskOutputForwarder {
+    fn on_output(&self, task_id: Option<&str>, tool: &str, line: &OutputLine) {
+        let Some(task_id) = task_id else { return };
+        if self.updates.receiver_count() == 0 {
+            return;
+        }
+        let stream = match line.stream {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        };
+        let step_number = self.active_tasks.get(task_id).map(|context| context.current_step).unwrap_or(0);
+        let step = ExecutionStep {
+            step_number,
+            step_type: StepType::CommandExecution,
+            description: format!("{} {}", tool, stream),
+            status: StepStatus::Running,
+            output: Some(line.line.clone()),
+            error: None,
+            started_at: Some(line.timestamp),
+            completed_at: None,
+            duration_ms: None,
+            input: Some(serde_json::json!({"tool": tool, "stream": stream})),
+            execution_time_ms: None,
+            timestamp: Some(line.timestamp),
+        };
+        // No subscriber left is not an error
+        let _ = self.updates.send(WebSocketMessage::TaskProgress {
+            task_id: task_id.to_string(),
+            step: Box::new(step),
+        });
+    }
 }
 
@@ -22,6 +22,12 @@ pub enum ServiceErrorType {
     #[error("Task timeout: {0}")]
     TaskTimeout(String),
 
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
 
@@ -69,6 +75,8 @@ impl ServiceErrorType {
             ServiceErrorType::TaskNotFound(_) => "TASK_NOT_FOUND".to_string(),
             ServiceErrorType::TaskExecutionFailed(_) => "TASK_EXECUTION_FAILED".to_string(),
             ServiceErrorType::TaskTimeout(_) => "TASK_TIMEOUT".to_string(),
+            ServiceErrorType::BudgetExceeded(_) => "BUDGET_EXCEEDED".to_string(),
+            ServiceErrorType::Conflict(_) => "CONFLICT".to_string(),
             ServiceErrorType::ConfigurationError(_) => "CONFIGURATION_ERROR".to_string(),
             ServiceErrorType::ModelError(_) => "MODEL_ERROR".to_string(),
             ServiceErrorType::ToolError(_) => "TOOL_ERROR".to_string(),
@@ -97,6 +105,11 @@ impl From<crate::errors::AgentError> for ServiceErrorType {
             crate::errors::AgentError::NetworkError(e) => ServiceErrorType::ServiceUnavailable(e),
             crate::errors::AgentError::TimeoutError => ServiceErrorType::TaskTimeout("Task execution timeout".to_string()),
             crate::errors::AgentError::ConfigError(e) => ServiceErrorType::ConfigurationError(e),
+            crate::errors::AgentError::InvalidState(e) => ServiceErrorType::InternalError(e),
+            crate::errors::AgentError::ExecutionError(e) => ServiceErrorType::TaskExecutionFailed(e),
+            crate::errors::AgentError::Aborted(e) => ServiceErrorType::TaskExecutionFailed(format!("Aborted: {}", e)),
+            error @ crate::errors::AgentError::BudgetExceeded { .. } => ServiceErrorType::BudgetExceeded(error.to_string()),
+            crate::errors::AgentError::Conflict(e) => ServiceErrorType::Conflict(e),
             crate::errors::AgentError::UnknownError(e) => ServiceErrorType::InternalError(e),
         }
     }
@@ -129,8 +142,10 @@ impl ServiceError {
             "AUTHENTICATION_ERROR" => 401,
             "AUTHORIZATION_ERROR" => 403,
             "TASK_NOT_FOUND" => 404,
+            "CONFLICT" => 409,
             "RATE_LIMIT_EXCEEDED" => 429,
             "TASK_TIMEOUT" => 408,
+            "BUDGET_EXCEEDED" => 422,
             "SERVICE_UNAVAILABLE" => 503,
             "CONFIGURATION_ERROR" => 500,
             "MODEL_ERROR" => 502,
@@ -151,6 +166,15 @@ impl ServiceError {
     }
 }
 
+/// Errors returned from HTTP handlers become a JSON body with the mapped status code
+impl axum::response::IntoResponse for ServiceError {
+    fn into_response(self) -> axum::response::Response {
+        let status = axum::http::StatusCode::from_u16(self.http_status_code())
+            .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        (status, axum::Json(self)).into_response()
+    }
+}
+
 /// Error builder for creating service errors
 pub struct ErrorBuilder;
 
@@ -175,6 +199,16 @@ impl ErrorBuilder {
         ServiceErrorType::TaskTimeout(task_id.into()).to_service_error()
     }
 
+    /// Create a budget exceeded error
+    pub fn budget_exceeded(message: impl Into<String>) -> ServiceError {
+        ServiceErrorType::BudgetExceeded(message.into()).to_service_error()
+    }
+
+    /// Create a conflict error
+    pub fn conflict(message: impl Into<String>) -> ServiceError {
+        ServiceErrorType::Conflict(message.into()).to_service_error()
+    }
+
     /// Create a configuration error
     pub fn configuration_error(message: impl Into<String>) -> ServiceError {
         ServiceErrorType::ConfigurationError(message.into()).to_service_error()
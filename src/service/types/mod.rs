@@ -35,7 +35,7 @@ pub mod websocket;
 
 // Re-export commonly used types for convenience
 pub use task::{
-    TaskRequest, TaskResponse, TaskStatus, TaskResult, TaskPriority,
+    TaskRequest, TaskResponse, TaskRevertResponse, TaskStatus, TaskResult, TaskPriority,
     TaskContext, TaskConstraints,
     ExecutionStep, StepType, StepStatus, TaskMetrics,
     TaskArtifact, ArtifactType, ServiceError,
@@ -28,7 +28,7 @@ pub struct TaskContext {
     pub working_directory: Option<String>,
     /// Environment variables
     pub environment: Option<HashMap<String, String>>,
-    /// Available tools
+    /// Tools this task may use (names or glob patterns); every registered tool when unset
     pub tools: Option<Vec<String>>,
     /// Execution constraints
     pub constraints: Option<TaskConstraints>,
@@ -81,6 +81,19 @@ pub struct TaskResponse {
     pub completed_at: Option<DateTime<Utc>>,
 }
 
+/// Response to reverting a task's file changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRevertResponse {
+    /// Task ID
+    pub task_id: String,
+    /// Files restored to their contents before the task
+    pub restored_files: Vec<String>,
+    /// Files created by the task and now removed
+    pub deleted_files: Vec<String>,
+    /// When the revert happened
+    pub reverted_at: DateTime<Utc>,
+}
+
 /// Task status in service context
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
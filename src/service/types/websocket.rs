@@ -38,3 +38,14 @@ pub enum WebSocketMessage {
     },
 }
 
+impl WebSocketMessage {
+    /// Task the message is about
+    pub fn task_id(&self) -> &str {
+        match self {
+            Self::TaskStarted { task_id }
+            | Self::TaskProgress { task_id, .. }
+            | Self::TaskCompleted { task_id, .. }
+            | Self::TaskFailed { task_id, .. } => task_id,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Helpers shared by the unit tests
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Uniquely named directory under the system temp dir, removed on drop
+///
+/// Removal happens even when an assertion fails, so failing tests do not
+/// leave workspaces behind.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create `<temp dir>/<prefix>_<uuid>`
+    pub(crate) fn new(prefix: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{}_{}", prefix, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Create `<prefix>_<uuid>` under the current directory, for tools that only accept relative paths
+    pub(crate) fn relative(prefix: &str) -> Self {
+        let path = PathBuf::from(format!("{}_{}", prefix, uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl From<&TempDir> for PathBuf {
+    fn from(dir: &TempDir) -> Self {
+        dir.0.clone()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
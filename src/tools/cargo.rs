@@ -0,0 +1,607 @@
+//! Structured cargo runs
+//!
+//! Runs `cargo build/check/test/clippy` with `--message-format=json` and turns
+//! the output into records instead of a wall of text: compiler diagnostics with
+//! location, severity and suggestion, and the outcome of every test with the
+//! captured output of failing ones. [`CargoReport::to_context`] renders a
+//! compact form for model prompts.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationTarget, OperationType};
+use super::{check_guardrails, Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Builds and test runs can be slow
+const CARGO_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Diagnostics and failing tests listed in [`CargoReport::to_context`]
+const CONTEXT_ITEMS: usize = 20;
+
+/// Captured output of a failing test is cut to this many lines in the context
+const CONTEXT_OUTPUT_LINES: usize = 15;
+
+/// Cargo subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CargoCommand {
+    Build,
+    Check,
+    Test,
+    Clippy,
+}
+
+impl CargoCommand {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "build" => Some(Self::Build),
+            "check" => Some(Self::Check),
+            "test" => Some(Self::Test),
+            "clippy" => Some(Self::Clippy),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Build => "build",
+            Self::Check => "check",
+            Self::Test => "test",
+            Self::Clippy => "clippy",
+        }
+    }
+}
+
+/// A cargo run to perform
+#[derive(Debug, Clone)]
+pub struct CargoInvocation {
+    pub command: CargoCommand,
+    pub package: Option<String>,
+    /// Test name filter (`cargo test <filter>`)
+    pub filter: Option<String>,
+    pub features: Vec<String>,
+    pub release: bool,
+}
+
+impl CargoInvocation {
+    pub fn new(command: CargoCommand) -> Self {
+        Self { command, package: None, filter: None, features: Vec::new(), release: false }
+    }
+
+    /// Recognise a plain `cargo build|check|test|clippy` command line
+    ///
+    /// Only `-p/--package`, `--release` and a test filter are understood; any
+    /// other flag returns `None` so the caller can fall back to running the
+    /// command as is.
+    pub fn from_command_line(command_line: &str) -> Option<Self> {
+        let mut words = command_line.split_whitespace();
+        if words.next() != Some("cargo") {
+            return None;
+        }
+        let mut invocation = Self::new(CargoCommand::parse(words.next()?)?);
+
+        while let Some(word) = words.next() {
+            match word {
+                "-p" | "--package" => invocation.package = Some(words.next()?.to_string()),
+                "--release" => invocation.release = true,
+                "--quiet" | "-q" => {}
+                filter if !filter.starts_with('-') && invocation.command == CargoCommand::Test && invocation.filter.is_none() => {
+                    invocation.filter = Some(filter.to_string());
+                }
+                _ => return None,
+            }
+        }
+        Some(invocation)
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![self.command.as_str().to_string(), "--message-format=json".to_string()];
+        if let Some(package) = &self.package {
+            args.push("--package".to_string());
+            args.push(package.clone());
+        }
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.release {
+            args.push("--release".to_string());
+        }
+        if self.command == CargoCommand::Test {
+            args.push("--no-fail-fast".to_string());
+            args.extend(self.filter.clone());
+        }
+        args
+    }
+
+    /// The command line as a user would type it
+    pub fn command_line(&self) -> String {
+        format!("cargo {}", self.args().join(" "))
+    }
+}
+
+// ============================================================================
+// Report
+// ============================================================================
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// One compiler or clippy diagnostic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Error or lint code, e.g. `E0308` or `clippy::needless_return`
+    pub code: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// First `help:` of the diagnostic, with the suggested replacement if any
+    pub suggestion: Option<String>,
+    /// The diagnostic as rustc prints it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
+}
+
+impl Diagnostic {
+    /// Parse the `message` of a `compiler-message` record
+    fn from_json(message: &serde_json::Value) -> Option<Self> {
+        let severity = match message["level"].as_str()? {
+            "error" | "error: internal compiler error" => Severity::Error,
+            "warning" => Severity::Warning,
+            "note" => Severity::Note,
+            "help" => Severity::Help,
+            _ => return None,
+        };
+        let text = message["message"].as_str()?.to_string();
+        let spans = message["spans"].as_array().cloned().unwrap_or_default();
+        // "aborting due to 2 previous errors", "3 warnings emitted" and the like
+        if spans.is_empty() && (text.starts_with("aborting due to") || text.ends_with("emitted")) {
+            return None;
+        }
+        let primary = spans.iter().find(|span| span["is_primary"].as_bool() == Some(true)).or(spans.first());
+
+        let suggestion = message["children"].as_array().and_then(|children| {
+            children.iter().find(|child| child["level"] == "help").map(|child| {
+                let help = child["message"].as_str().unwrap_or_default();
+                let replacement = child["spans"]
+                    .as_array()
+                    .and_then(|spans| spans.iter().find_map(|span| span["suggested_replacement"].as_str()));
+                match replacement {
+                    Some(replacement) => format!("{}: `{}`", help, replacement),
+                    None => help.to_string(),
+                }
+            })
+        });
+
+        Some(Diagnostic {
+            severity,
+            message: text,
+            code: message["code"]["code"].as_str().map(str::to_string),
+            file: primary.and_then(|span| span["file_name"].as_str()).map(str::to_string),
+            line: primary.and_then(|span| span["line_start"].as_u64()).map(|line| line as usize),
+            column: primary.and_then(|span| span["column_start"].as_u64()).map(|column| column as usize),
+            suggestion,
+            rendered: message["rendered"].as_str().map(str::to_string),
+        })
+    }
+
+    /// `file:line:column` when known
+    pub fn location(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", file, line, column),
+            (Some(line), None) => format!("{}:{}", file, line),
+            _ => file.clone(),
+        })
+    }
+}
+
+/// `error[E0308]: message (file:line:column)`, followed by the suggestion on its own line
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        };
+        let code = self.code.as_ref().map(|code| format!("[{}]", code)).unwrap_or_default();
+        let location = self.location().unwrap_or_else(|| "no location".to_string());
+        write!(f, "{}{}: {} ({})", severity, code, self.message, location)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n  help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a single test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// One test from the libtest output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestOutcome {
+    pub name: String,
+    pub status: TestStatus,
+    /// Captured stdout/panic message of a failing test
+    pub output: Option<String>,
+}
+
+/// Parsed result of a cargo run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CargoReport {
+    pub command: String,
+    pub success: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    pub tests: Vec<TestOutcome>,
+    /// Tail of stderr when cargo failed without any diagnostic or test failure
+    pub error: Option<String>,
+}
+
+impl CargoReport {
+    /// Parse cargo's stdout: JSON messages interleaved with libtest output
+    pub fn parse(command: &str, success: bool, stdout: &str) -> Self {
+        let mut report = CargoReport { command: command.to_string(), success, ..Default::default() };
+        let mut harness = String::new();
+
+        for line in stdout.lines() {
+            let message = match line.starts_with('{').then(|| serde_json::from_str::<serde_json::Value>(line)) {
+                Some(Ok(message)) => message,
+                _ => {
+                    harness.push_str(line);
+                    harness.push('\n');
+                    continue;
+                }
+            };
+            if message["reason"] != "compiler-message" {
+                continue;
+            }
+            // The same diagnostic is reported once per target (lib, lib test, ...)
+            if let Some(diagnostic) = Diagnostic::from_json(&message["message"]) {
+                if !report.diagnostics.contains(&diagnostic) {
+                    report.diagnostics.push(diagnostic);
+                }
+            }
+        }
+
+        report.tests = parse_test_output(&harness);
+        report
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning)
+    }
+
+    pub fn failed_tests(&self) -> impl Iterator<Item = &TestOutcome> {
+        self.tests.iter().filter(|t| t.status == TestStatus::Failed)
+    }
+
+    fn count_tests(&self, status: TestStatus) -> usize {
+        self.tests.iter().filter(|t| t.status == status).count()
+    }
+
+    /// One-line summary, e.g. `cargo test failed: 1 error, 2 warnings`
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            format!("{} errors", self.errors().count()),
+            format!("{} warnings", self.warnings().count()),
+        ];
+        if !self.tests.is_empty() {
+            parts.push(format!(
+                "{} tests passed, {} failed, {} ignored",
+                self.count_tests(TestStatus::Passed),
+                self.count_tests(TestStatus::Failed),
+                self.count_tests(TestStatus::Ignored)
+            ));
+        }
+        let verb = if self.success { "succeeded" } else { "failed" };
+        format!("{} {}: {}", self.command, verb, parts.join(", "))
+    }
+
+    /// Compact rendering for model prompts: errors first, then failing tests and warnings
+    pub fn to_context(&self) -> String {
+        let mut lines = vec![self.summary()];
+        let problems = self.errors().chain(self.warnings());
+        for diagnostic in problems.take(CONTEXT_ITEMS) {
+            lines.push(diagnostic.to_string());
+        }
+        for test in self.failed_tests().take(CONTEXT_ITEMS) {
+            lines.push(format!("test {} FAILED", test.name));
+            for output in test.output.iter().flat_map(|output| output.lines().take(CONTEXT_OUTPUT_LINES)) {
+                lines.push(format!("  {}", output));
+            }
+        }
+        if let Some(error) = &self.error {
+            lines.push(error.clone());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Parse libtest's human-readable output
+///
+/// Stable cargo has no JSON test output, so `test <name> ... <result>` lines and
+/// the `---- <name> stdout ----` sections of failing tests are read directly.
+fn parse_test_output(output: &str) -> Vec<TestOutcome> {
+    let mut tests = Vec::new();
+    let mut section: Option<(String, Vec<&str>)> = None;
+
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|rest| rest.strip_suffix(" stdout ----")) {
+            close_section(section.take(), &mut tests);
+            section = Some((name.to_string(), Vec::new()));
+            continue;
+        }
+        if let Some((_, lines)) = &mut section {
+            // The failure sections end with the list of failed test names
+            if line == "failures:" {
+                close_section(section.take(), &mut tests);
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("test ") else { continue };
+        let Some((name, result)) = rest.rsplit_once(" ... ") else { continue };
+        let status = match result {
+            "ok" => TestStatus::Passed,
+            "FAILED" => TestStatus::Failed,
+            result if result.starts_with("ignored") => TestStatus::Ignored,
+            _ => continue,
+        };
+        tests.push(TestOutcome { name: name.to_string(), status, output: None });
+    }
+    close_section(section, &mut tests);
+
+    tests
+}
+
+/// Attach a finished `---- name stdout ----` section to its test
+fn close_section(section: Option<(String, Vec<&str>)>, tests: &mut [TestOutcome]) {
+    if let Some((name, lines)) = section {
+        if let Some(test) = tests.iter_mut().rev().find(|test| test.name == name && test.output.is_none()) {
+            test.output = Some(lines.join("\n").trim().to_string());
+        }
+    }
+}
+
+/// Run cargo in `dir` and parse its output
+pub async fn run_cargo(dir: &Path, invocation: &CargoInvocation) -> Result<CargoReport, ToolError> {
+    let output = tokio::process::Command::new("cargo")
+        .args(invocation.args())
+        .current_dir(dir)
+        .env("CARGO_TERM_COLOR", "never")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to run cargo: {}", e)))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut report = CargoReport::parse(&invocation.command_line(), output.status.success(), &stdout);
+    if !report.success && report.errors().next().is_none() && report.failed_tests().next().is_none() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: Vec<&str> = stderr.lines().rev().take(CONTEXT_OUTPUT_LINES).collect();
+        report.error = Some(tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+    }
+    Ok(report)
+}
+
+// ============================================================================
+// Tool
+// ============================================================================
+
+/// Cargo build/test tool with structured results
+#[derive(Default)]
+pub struct CargoTool {
+    guardrails: GuardrailEngine,
+}
+
+impl CargoTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+}
+
+/// A value placed in cargo's argv; leading `-` is rejected so it cannot be read as an option
+fn argument(key: &str, value: String) -> Result<String, ToolError> {
+    if value.starts_with('-') || value.trim().is_empty() {
+        return Err(ToolError::InvalidParameters(format!("Invalid value for '{}': {}", key, value)));
+    }
+    Ok(value)
+}
+
+fn optional_argument(args: &ToolArgs, key: &str) -> Result<Option<String>, ToolError> {
+    if !args.contains(key) {
+        return Ok(None);
+    }
+    argument(key, args.get_string(key)?).map(Some)
+}
+
+#[async_trait]
+impl Tool for CargoTool {
+    fn name(&self) -> &str {
+        "cargo"
+    }
+
+    fn description(&self) -> &str {
+        "Run cargo build, check, test or clippy and get compiler diagnostics \
+         (file, line, severity, message, suggestion) and per-test results"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("command", "Cargo subcommand").with_enum(["build", "check", "test", "clippy"]),
+            Parameter::optional("path", "Directory containing Cargo.toml").with_default(serde_json::json!(".")),
+            Parameter::optional("package", "Package to build or test"),
+            Parameter::optional("test_filter", "Only run tests whose name contains this string"),
+            Parameter::optional("features", "Features to enable")
+                .with_items(Parameter::required("feature", "Feature")),
+            Parameter::optional("release", "Build in release mode")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(false)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating()
+            .with_operation(OperationType::CommandWrite)
+            .with_timeout(CARGO_TIMEOUT)
+            .with_max_concurrency(1)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string_or("path", ".");
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let command = args.get_string("command")?;
+        let invocation = CargoInvocation {
+            command: CargoCommand::parse(&command)
+                .ok_or_else(|| ToolError::InvalidParameters(format!("Unsupported cargo command: {}", command)))?,
+            package: optional_argument(args, "package")?,
+            filter: optional_argument(args, "test_filter")?,
+            features: if args.contains("features") {
+                args.get_string_array("features")?
+                    .into_iter()
+                    .map(|feature| argument("features", feature))
+                    .collect::<Result<_, _>>()?
+            } else {
+                Vec::new()
+            },
+            release: args.get_bool_or("release", false),
+        };
+
+        check_guardrails(
+            &self.guardrails,
+            self.metadata().operation_type(),
+            &invocation.command_line(),
+            vec![OperationTarget {
+                resource_type: "directory".to_string(),
+                path: path.clone(),
+                is_protected: false,
+                snapshot: None,
+            }],
+        )?;
+
+        let report = run_cargo(&PathBuf::from(path), &invocation).await?;
+        let mut result = ToolResult::text(report.to_context());
+        result.summary = report.summary();
+        result.data = Some(serde_json::to_value(&report).map_err(|e| ToolError::ExecutionError(e.to_string()))?);
+        if !report.success {
+            result.success = false;
+            result.error = Some(report.summary());
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPILER_MESSAGE: &str = r#"{"reason":"compiler-message","message":{"rendered":"error[E0308]: mismatched types","code":{"code":"E0308","explanation":null},"level":"error","message":"mismatched types","spans":[{"file_name":"src/lib.rs","line_start":3,"column_start":5,"is_primary":true,"suggested_replacement":null}],"children":[{"level":"help","message":"try using a conversion method","spans":[{"file_name":"src/lib.rs","line_start":3,"column_start":5,"is_primary":true,"suggested_replacement":"x.to_string()"}],"children":[]}]}}"#;
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let aborting = r#"{"reason":"compiler-message","message":{"rendered":null,"code":null,"level":"error","message":"aborting due to 1 previous error","spans":[],"children":[]}}"#;
+        let stdout = [COMPILER_MESSAGE, COMPILER_MESSAGE, aborting, r#"{"reason":"build-finished","success":false}"#].join("\n");
+        let report = CargoReport::parse("cargo build --message-format=json", false, &stdout);
+
+        assert_eq!(report.diagnostics.len(), 1);
+        let diagnostic = &report.diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.code.as_deref(), Some("E0308"));
+        assert_eq!(diagnostic.location().as_deref(), Some("src/lib.rs:3:5"));
+        assert_eq!(diagnostic.suggestion.as_deref(), Some("try using a conversion method: `x.to_string()`"));
+        assert!(report.to_context().contains("error[E0308]: mismatched types (src/lib.rs:3:5)"));
+    }
+
+    #[test]
+    fn test_parse_test_results() {
+        let stdout = "\
+running 3 tests
+test tests::adds ... ok
+test tests::slow ... ignored, takes a minute
+test tests::subtracts ... FAILED
+
+failures:
+
+---- tests::subtracts stdout ----
+thread 'tests::subtracts' panicked at src/lib.rs:10:9:
+assertion `left == right` failed
+
+failures:
+    tests::subtracts
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let report = CargoReport::parse("cargo test", false, stdout);
+
+        let statuses: Vec<TestStatus> = report.tests.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, vec![TestStatus::Passed, TestStatus::Ignored, TestStatus::Failed]);
+        let failed: Vec<&TestOutcome> = report.failed_tests().collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].output.as_deref().unwrap().ends_with("assertion `left == right` failed"));
+        assert!(report.summary().ends_with("1 tests passed, 1 failed, 1 ignored"));
+    }
+
+    #[test]
+    fn test_invocation_from_command_line() {
+        let invocation = CargoInvocation::from_command_line("cargo test -p core parser --quiet").unwrap();
+        assert_eq!(invocation.command, CargoCommand::Test);
+        assert_eq!(invocation.package.as_deref(), Some("core"));
+        assert_eq!(invocation.filter.as_deref(), Some("parser"));
+        assert_eq!(
+            invocation.command_line(),
+            "cargo test --message-format=json --package core --no-fail-fast parser"
+        );
+
+        assert!(CargoInvocation::from_command_line("cargo run").is_none());
+        assert!(CargoInvocation::from_command_line("cargo build --target wasm32").is_none());
+        assert!(CargoInvocation::from_command_line("make test").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_option_like_values_are_rejected() {
+        let tool = CargoTool::new();
+        for (key, value) in [
+            ("package", serde_json::json!("--config=build.rustc-wrapper='sh'")),
+            ("test_filter", serde_json::json!("-Zunstable-options")),
+            ("features", serde_json::json!(["serde", "--manifest-path=/etc"])),
+        ] {
+            let args = ToolArgs::from_map(serde_json::from_value(serde_json::json!({ "command": "test", key: value })).unwrap());
+            assert!(matches!(tool.execute(&args).await, Err(ToolError::InvalidParameters(_))), "{} accepted", key);
+        }
+    }
+}
@@ -0,0 +1,758 @@
+//! Precise file edits
+//!
+//! Two edit formats are supported, both applied all-or-nothing:
+//!
+//! - search/replace blocks: each `search` text must occur exactly once. When it
+//!   does not occur verbatim, a line match that ignores whitespace differences
+//!   is tried and the replacement is re-indented to the matched lines.
+//! - unified diffs: each hunk is located by its context and removed lines,
+//!   starting at the line number from its `@@` header and searching outwards,
+//!   so hunks with stale line numbers still apply.
+//!
+//! When any block or hunk cannot be applied, the file is left untouched and the
+//! error names every failed block or hunk together with the reason.
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationTarget, OperationType};
+use super::{check_guardrails, Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Largest `old x new` line product for which an exact line diff is computed
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+// ============================================================================
+// Edit formats
+// ============================================================================
+
+/// Replace the single occurrence of `search` with `replace`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchReplace {
+    pub search: String,
+    pub replace: String,
+}
+
+impl SearchReplace {
+    pub fn new(search: impl Into<String>, replace: impl Into<String>) -> Self {
+        Self { search: search.into(), replace: replace.into() }
+    }
+}
+
+/// A line of a unified diff hunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// One hunk of a unified diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The `@@ -a,b +c,d @@` header
+    pub header: String,
+    /// 1-based line of the original file where the hunk starts
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// Lines the hunk expects to find: context and removed lines
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+            HunkLine::Added(_) => None,
+        }).collect()
+    }
+
+    /// Lines the hunk leaves behind: context and added lines
+    fn new_lines(&self) -> Vec<String> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Added(text) => Some(text.clone()),
+            HunkLine::Removed(_) => None,
+        }).collect()
+    }
+}
+
+/// An edit to a single file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEdit {
+    SearchReplace(Vec<SearchReplace>),
+    UnifiedDiff(Vec<Hunk>),
+}
+
+impl FileEdit {
+    /// Parse a unified diff for a single file
+    ///
+    /// `---`/`+++` file headers are optional. Hunk line counts are not trusted;
+    /// a hunk ends at the next header or at the first line that is not part of it.
+    pub fn parse_diff(diff: &str) -> Result<Self, ToolError> {
+        let header_re = Regex::new(r"^@@ -(\d+)(?:,\d+)? \+\d+(?:,\d+)? @@")
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let mut hunks = Vec::new();
+        let mut current: Option<Hunk> = None;
+        let mut files = 0;
+
+        let lines: Vec<&str> = diff.lines().map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+        for (index, line) in lines.iter().enumerate() {
+            if line.starts_with("@@") {
+                let captures = header_re.captures(line).ok_or_else(|| {
+                    ToolError::InvalidParameters(format!("Malformed hunk header: {}", line))
+                })?;
+                hunks.extend(current.take());
+                current = Some(Hunk {
+                    header: line.to_string(),
+                    old_start: captures[1].parse().unwrap_or(0),
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+
+            // A `---`/`+++` pair is a file header, anything else is hunk content
+            if line.starts_with("--- ") && lines.get(index + 1).is_some_and(|next| next.starts_with("+++ ")) {
+                hunks.extend(current.take());
+                files += 1;
+                continue;
+            }
+            if line.starts_with("+++ ") && index > 0 && lines[index - 1].starts_with("--- ") {
+                continue;
+            }
+
+            let hunk_line = match line.chars().next() {
+                Some(' ') => Some(HunkLine::Context(line[1..].to_string())),
+                Some('-') => Some(HunkLine::Removed(line[1..].to_string())),
+                Some('+') => Some(HunkLine::Added(line[1..].to_string())),
+                // Models often drop the leading space of empty context lines
+                None => Some(HunkLine::Context(String::new())),
+                _ => None,
+            };
+
+            match (&mut current, hunk_line) {
+                (Some(hunk), Some(hunk_line)) => hunk.lines.push(hunk_line),
+                // "\ No newline at end of file"
+                (Some(_), None) if line.starts_with('\\') => {}
+                (Some(_), None) => hunks.extend(current.take()),
+                (None, _) => {}
+            }
+        }
+        hunks.extend(current);
+
+        if files > 1 {
+            return Err(ToolError::InvalidParameters(
+                "Diff touches more than one file; send one diff per file".to_string(),
+            ));
+        }
+        // Trailing empty lines are artifacts of splitting, not context
+        for hunk in &mut hunks {
+            while matches!(hunk.lines.last(), Some(HunkLine::Context(text)) if text.is_empty()) {
+                hunk.lines.pop();
+            }
+        }
+        hunks.retain(|hunk| !hunk.lines.is_empty());
+        if hunks.is_empty() {
+            return Err(ToolError::InvalidParameters("Diff contains no hunks".to_string()));
+        }
+
+        Ok(FileEdit::UnifiedDiff(hunks))
+    }
+
+    /// Parse `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks
+    pub fn parse_search_replace_blocks(text: &str) -> Vec<SearchReplace> {
+        enum State {
+            Outside,
+            Search(Vec<String>),
+            Replace(Vec<String>, Vec<String>),
+        }
+
+        let join = |lines: &[String]| {
+            if lines.is_empty() { String::new() } else { format!("{}\n", lines.join("\n")) }
+        };
+
+        let mut blocks = Vec::new();
+        let mut state = State::Outside;
+        for line in text.lines() {
+            let marker = line.trim();
+            state = match state {
+                State::Outside if marker == "<<<<<<< SEARCH" => State::Search(Vec::new()),
+                State::Outside => State::Outside,
+                State::Search(search) if marker == "=======" => State::Replace(search, Vec::new()),
+                State::Search(mut search) => {
+                    search.push(line.to_string());
+                    State::Search(search)
+                }
+                State::Replace(search, replace) if marker == ">>>>>>> REPLACE" => {
+                    blocks.push(SearchReplace::new(join(&search), join(&replace)));
+                    State::Outside
+                }
+                State::Replace(search, mut replace) => {
+                    replace.push(line.to_string());
+                    State::Replace(search, replace)
+                }
+            };
+        }
+
+        blocks
+    }
+
+    /// Extract an edit from free-form model output, preferring search/replace blocks
+    pub fn from_response(text: &str) -> Option<Self> {
+        let blocks = Self::parse_search_replace_blocks(text);
+        if !blocks.is_empty() {
+            return Some(FileEdit::SearchReplace(blocks));
+        }
+        if text.lines().any(|line| line.starts_with("@@ -")) {
+            return Self::parse_diff(text).ok();
+        }
+        None
+    }
+
+    /// Number of blocks or hunks
+    pub fn len(&self) -> usize {
+        match self {
+            FileEdit::SearchReplace(blocks) => blocks.len(),
+            FileEdit::UnifiedDiff(hunks) => hunks.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply the edit to `content`, failing without partial results
+    pub fn apply(&self, content: &str) -> Result<AppliedEdit, EditError> {
+        let (new_content, notes, failures) = match self {
+            FileEdit::SearchReplace(blocks) => apply_search_replace(content, blocks),
+            FileEdit::UnifiedDiff(hunks) => apply_hunks(content, hunks),
+        };
+
+        if !failures.is_empty() {
+            return Err(EditError { failures, total: self.len() });
+        }
+
+        Ok(AppliedEdit {
+            changes: LineChanges::between(content, &new_content),
+            content: new_content,
+            notes,
+        })
+    }
+}
+
+// ============================================================================
+// Results
+// ============================================================================
+
+/// Lines added and removed in one file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineChanges {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl LineChanges {
+    /// Count changed lines between two versions of a file
+    ///
+    /// Uses a longest-common-subsequence line diff; very large rewrites fall
+    /// back to counting every differing line in the changed region.
+    pub fn between(old: &str, new: &str) -> Self {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..].iter().rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old = &old[prefix..old.len() - suffix];
+        let new = &new[prefix..new.len() - suffix];
+
+        let common = if old.len().saturating_mul(new.len()) <= MAX_DIFF_CELLS {
+            longest_common_subsequence(old, new)
+        } else {
+            0
+        };
+
+        Self { added: new.len() - common, removed: old.len() - common }
+    }
+
+    pub fn total(&self) -> usize {
+        self.added + self.removed
+    }
+}
+
+impl std::ops::AddAssign for LineChanges {
+    fn add_assign(&mut self, other: Self) {
+        self.added += other.added;
+        self.removed += other.removed;
+    }
+}
+
+impl fmt::Display for LineChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} -{}", self.added, self.removed)
+    }
+}
+
+/// An edit that applied cleanly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedEdit {
+    /// New file content
+    pub content: String,
+    pub changes: LineChanges,
+    /// Blocks matched ignoring whitespace and hunks applied at an offset
+    pub notes: Vec<String>,
+}
+
+/// A block or hunk that could not be applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditFailure {
+    /// e.g. `block 2` or `hunk 1 (@@ -10,3 +10,4 @@)`
+    pub label: String,
+    pub reason: String,
+}
+
+/// An edit that was rejected; the file must be left unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditError {
+    pub failures: Vec<EditFailure>,
+    /// Number of blocks or hunks in the edit
+    pub total: usize,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} edits failed, no changes written", self.failures.len(), self.total)?;
+        for failure in &self.failures {
+            write!(f, "\n- {}: {}", failure.label, failure.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// ============================================================================
+// Application
+// ============================================================================
+
+/// File content split into lines, remembering the line ending style
+struct Lines {
+    lines: Vec<String>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+impl Lines {
+    fn split(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(str::to_string).collect(),
+            newline: if content.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    fn join(&self) -> String {
+        let mut content = self.lines.join(self.newline);
+        if self.trailing_newline && !self.lines.is_empty() {
+            content.push_str(self.newline);
+        }
+        content
+    }
+}
+
+/// Collapse runs of whitespace so indentation and spacing differences match
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Start indices where `needle` matches `haystack` line by line, ignoring whitespace
+fn fuzzy_matches(haystack: &[String], needle: &[&str]) -> Vec<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return Vec::new();
+    }
+    let needle: Vec<String> = needle.iter().map(|line| normalize(line)).collect();
+    (0..=haystack.len() - needle.len())
+        .filter(|&start| {
+            haystack[start..start + needle.len()]
+                .iter()
+                .zip(&needle)
+                .all(|(line, expected)| normalize(line) == *expected)
+        })
+        .collect()
+}
+
+fn apply_search_replace(content: &str, blocks: &[SearchReplace]) -> (String, Vec<String>, Vec<EditFailure>) {
+    let mut content = content.to_string();
+    let mut notes = Vec::new();
+    let mut failures = Vec::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let label = format!("block {}", index + 1);
+        let fail = |reason: String| EditFailure { label: label.clone(), reason };
+
+        if block.search.trim().is_empty() {
+            if content.trim().is_empty() {
+                content = block.replace.clone();
+            } else {
+                failures.push(fail("Empty search text is only allowed for an empty or new file".to_string()));
+            }
+            continue;
+        }
+
+        match content.matches(block.search.as_str()).count() {
+            1 => {
+                content = content.replacen(block.search.as_str(), &block.replace, 1);
+                continue;
+            }
+            0 => {}
+            count => {
+                failures.push(fail(format!(
+                    "Search text matches {} times; include more surrounding lines to make it unique",
+                    count
+                )));
+                continue;
+            }
+        }
+
+        // Fall back to a whitespace-insensitive line match
+        let search_lines: Vec<&str> = block.search.lines().collect();
+        let leading = search_lines.iter().take_while(|line| line.trim().is_empty()).count();
+        let trailing = search_lines.iter().rev().take_while(|line| line.trim().is_empty()).count();
+        let needle = &search_lines[leading..search_lines.len() - trailing];
+
+        let mut lines = Lines::split(&content);
+        match fuzzy_matches(&lines.lines, needle).as_slice() {
+            [start] => {
+                let start = *start;
+                let search_indent = indentation(needle[0]);
+                let matched_indent = indentation(&lines.lines[start]).to_string();
+
+                let replace_lines: Vec<&str> = block.replace.lines().collect();
+                let skip_leading = replace_lines.iter().take(leading).take_while(|line| line.trim().is_empty()).count();
+                let skip_trailing = replace_lines[skip_leading..].iter().rev().take(trailing)
+                    .take_while(|line| line.trim().is_empty()).count();
+                let replacement: Vec<String> = replace_lines[skip_leading..replace_lines.len() - skip_trailing]
+                    .iter()
+                    .map(|line| match line.strip_prefix(search_indent) {
+                        Some(rest) if search_indent != matched_indent => format!("{}{}", matched_indent, rest),
+                        _ => line.to_string(),
+                    })
+                    .collect();
+
+                lines.lines.splice(start..start + needle.len(), replacement);
+                content = lines.join();
+                notes.push(format!("{} matched at line {} ignoring whitespace", label, start + 1));
+            }
+            [] => failures.push(fail(format!(
+                "Search text not found, even ignoring whitespace (first line: {:?})",
+                needle[0].trim()
+            ))),
+            matches => failures.push(fail(format!(
+                "Search text matches {} times ignoring whitespace; include more surrounding lines",
+                matches.len()
+            ))),
+        }
+    }
+
+    (content, notes, failures)
+}
+
+/// Position of `needle` in `haystack`, searching outwards from `expected`
+///
+/// Exact matches are preferred over whitespace-insensitive ones.
+fn locate_hunk(haystack: &[String], needle: &[&str], expected: usize) -> Option<(usize, bool)> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    let last = haystack.len() - needle.len();
+    let expected = expected.min(last);
+
+    let exact = |start: usize| haystack[start..start + needle.len()].iter().zip(needle).all(|(a, b)| a == b);
+    let normalized: Vec<String> = needle.iter().map(|line| normalize(line)).collect();
+    let fuzzy = |start: usize| {
+        haystack[start..start + needle.len()].iter().zip(&normalized).all(|(a, b)| normalize(a) == *b)
+    };
+
+    for (matches, is_fuzzy) in [(&exact as &dyn Fn(usize) -> bool, false), (&fuzzy, true)] {
+        for distance in 0..=last.max(expected) {
+            let candidates = [expected.checked_sub(distance), Some(expected + distance)];
+            for start in candidates.into_iter().flatten().filter(|&start| start <= last) {
+                if matches(start) {
+                    return Some((start, is_fuzzy));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn apply_hunks(content: &str, hunks: &[Hunk]) -> (String, Vec<String>, Vec<EditFailure>) {
+    let mut lines = Lines::split(content);
+    let mut notes = Vec::new();
+    let mut failures = Vec::new();
+    // Shift between original line numbers and the current content
+    let mut offset: isize = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let label = format!("hunk {} ({})", index + 1, hunk.header);
+        let old = hunk.old_lines();
+        let new = hunk.new_lines();
+
+        if old.is_empty() {
+            // Pure insertion after line `old_start`
+            let position = (hunk.old_start as isize + offset).clamp(0, lines.lines.len() as isize) as usize;
+            offset += new.len() as isize;
+            lines.lines.splice(position..position, new);
+            continue;
+        }
+
+        let expected = (hunk.old_start as isize - 1 + offset).max(0) as usize;
+        match locate_hunk(&lines.lines, &old, expected) {
+            Some((start, fuzzy)) => {
+                if start != expected {
+                    notes.push(format!("{} applied at line {} (offset {:+})", label, start + 1, start as isize - expected as isize));
+                }
+                if fuzzy {
+                    notes.push(format!("{} matched ignoring whitespace", label));
+                }
+                offset += start as isize - expected as isize + new.len() as isize - old.len() as isize;
+                lines.lines.splice(start..start + old.len(), new);
+            }
+            None => failures.push(EditFailure {
+                label,
+                reason: format!(
+                    "Context and removed lines not found in the file (expected near line {}, first line: {:?})",
+                    hunk.old_start,
+                    old[0].trim()
+                ),
+            }),
+        }
+    }
+
+    (lines.join(), notes, failures)
+}
+
+fn longest_common_subsequence(old: &[&str], new: &[&str]) -> usize {
+    let mut previous = vec![0usize; new.len() + 1];
+    let mut current = vec![0usize; new.len() + 1];
+    for old_line in old {
+        for (j, new_line) in new.iter().enumerate() {
+            current[j + 1] = if old_line == new_line {
+                previous[j] + 1
+            } else {
+                previous[j + 1].max(current[j])
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[new.len()]
+}
+
+// ============================================================================
+// Tool
+// ============================================================================
+
+/// Edit file tool
+///
+/// Applies search/replace blocks or a unified diff to one file. The edit is
+/// checked by the guardrails first, so protected paths are refused.
+#[derive(Default)]
+pub struct EditFileTool {
+    guardrails: GuardrailEngine,
+}
+
+impl EditFileTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    fn parse_edit(args: &ToolArgs) -> Result<FileEdit, ToolError> {
+        match (args.get("edits"), args.get("diff")) {
+            (Some(_), None) => {
+                let blocks = args.get_array("edits")?
+                    .into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<Result<Vec<SearchReplace>, _>>()
+                    .map_err(|e| ToolError::InvalidParameters(format!("Invalid edits: {}", e)))?;
+                if blocks.is_empty() {
+                    return Err(ToolError::InvalidParameters("'edits' must not be empty".to_string()));
+                }
+                Ok(FileEdit::SearchReplace(blocks))
+            }
+            (None, Some(_)) => FileEdit::parse_diff(&args.get_string("diff")?),
+            _ => Err(ToolError::InvalidParameters("Provide exactly one of 'edits' or 'diff'".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for EditFileTool {
+    fn name(&self) -> &str {
+        "edit_file"
+    }
+
+    fn description(&self) -> &str {
+        "Edit part of a file with exact search/replace blocks or a unified diff. \
+         Each search text must match exactly one place in the file."
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("path", "File path to edit"),
+            Parameter::optional("edits", "Search/replace blocks, applied in order")
+                .with_items(Parameter::required("edit", "Search/replace block").with_properties(vec![
+                    Parameter::required("search", "Exact text to find; must match once"),
+                    Parameter::required("replace", "Replacement text"),
+                ])),
+            Parameter::optional("diff", "Unified diff with @@ hunks for this file")
+                .with_type(ParameterType::String),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_operation(OperationType::FileModify)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string("path")?;
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let edit = Self::parse_edit(args)?;
+
+        check_guardrails(
+            &self.guardrails,
+            OperationType::FileModify,
+            &format!("Editing {}", path),
+            vec![OperationTarget {
+                resource_type: "file".to_string(),
+                path: path.clone(),
+                is_protected: false,
+                snapshot: None,
+            }],
+        )?;
+
+        let original = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(ToolError::ExecutionError(e.to_string())),
+        };
+
+        let applied = match edit.apply(&original) {
+            Ok(applied) => applied,
+            Err(error) => {
+                let mut result = ToolResult::error(error.to_string());
+                result.data = Some(serde_json::json!({ "failed": error.failures }));
+                return Ok(result);
+            }
+        };
+
+        if let Some(parent) = std::path::Path::new(&path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        }
+        tokio::fs::write(&path, &applied.content)
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let mut summary = format!("Edited {} ({} lines)", path, applied.changes);
+        for note in &applied.notes {
+            summary.push_str(&format!("\n{}", note));
+        }
+        let mut result = ToolResult::text(summary);
+        result.data = Some(serde_json::json!({
+            "path": path,
+            "lines_added": applied.changes.added,
+            "lines_removed": applied.changes.removed,
+        }));
+        Ok(result)
+    }
+
+    fn modified_paths(&self, args: &ToolArgs) -> Vec<String> {
+        args.get_string("path").into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n\nfn helper() {\n    let x = 1;\n}\n";
+
+    #[test]
+    fn test_search_replace_uniqueness_and_fuzzy_whitespace() {
+        let edit = FileEdit::SearchReplace(vec![SearchReplace::new("let x = 1;", "let x = 2;")]);
+        let error = edit.apply(SOURCE).unwrap_err();
+        assert!(error.failures[0].reason.contains("matches 2 times"));
+
+        // Indented differently than the file; re-indented on replacement
+        let edit = FileEdit::SearchReplace(vec![SearchReplace::new(
+            "fn helper() {\nlet  x = 1;\n",
+            "fn helper() {\nlet x = 3;\nlet y = 4;\n",
+        )]);
+        let applied = edit.apply(SOURCE).unwrap();
+        assert!(applied.content.ends_with("fn helper() {\nlet x = 3;\nlet y = 4;\n}\n"));
+        assert_eq!(applied.changes, LineChanges { added: 2, removed: 1 });
+        assert_eq!(applied.notes.len(), 1);
+    }
+
+    #[test]
+    fn test_unified_diff_with_stale_line_numbers() {
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -20,3 +20,3 @@\n fn helper() {\n-    let x = 1;\n+    let x = 5;\n }\n";
+        let edit = FileEdit::parse_diff(diff).unwrap();
+        let applied = edit.apply(SOURCE).unwrap();
+
+        assert!(applied.content.contains("fn helper() {\n    let x = 5;\n}"));
+        assert!(applied.content.contains("    let x = 1;\n    println!"));
+        assert_eq!(applied.changes, LineChanges { added: 1, removed: 1 });
+        assert!(applied.notes[0].contains("offset"));
+    }
+
+    #[test]
+    fn test_reports_each_failed_hunk() {
+        let diff = "@@ -2,1 +2,1 @@\n-    let x = 1;\n+    let x = 9;\n@@ -7,1 +7,1 @@\n-    missing();\n+    found();\n";
+        let error = FileEdit::parse_diff(diff).unwrap().apply(SOURCE).unwrap_err();
+
+        assert_eq!(error.total, 2);
+        assert_eq!(error.failures.len(), 1);
+        assert!(error.failures[0].label.starts_with("hunk 2 (@@ -7,1 +7,1 @@)"));
+        assert!(error.to_string().contains("no changes written"));
+    }
+
+    #[test]
+    fn test_from_response_detects_format() {
+        let response = "Here you go:\n<<<<<<< SEARCH\n    let x = 1;\n    println!\n=======\n    let x = 2;\n>>>>>>> REPLACE\n";
+        match FileEdit::from_response(response) {
+            Some(FileEdit::SearchReplace(blocks)) => {
+                assert_eq!(blocks, vec![SearchReplace::new("    let x = 1;\n    println!\n", "    let x = 2;\n")]);
+            }
+            other => panic!("unexpected edit: {:?}", other),
+        }
+        assert!(matches!(FileEdit::from_response("```diff\n@@ -1 +1 @@\n-a\n+b\n```"), Some(FileEdit::UnifiedDiff(_))));
+        assert_eq!(FileEdit::from_response("fn main() {}"), None);
+    }
+
+    #[tokio::test]
+    async fn test_edit_file_tool_refuses_protected_paths() {
+        let tool = EditFileTool::new();
+        let args = ToolArgs::from_map(
+            serde_json::from_value(serde_json::json!({"path": ".git/config", "diff": "@@ -1 +1 @@\n-a\n+b\n"})).unwrap(),
+        );
+        assert!(matches!(tool.execute(&args).await, Err(ToolError::PermissionDenied(_))));
+    }
+}
@@ -0,0 +1,108 @@
+//! Name-based tool filtering
+//!
+//! A [`ToolFilter`] decides which tools a registry accepts, from
+//! `ToolConfig.enabled_tools` and `disabled_tools`, and which tools a single
+//! task may use. Names may be glob patterns such as `docs_*`, which covers
+//! every tool imported from one MCP server.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use crate::config::ToolConfig;
+use crate::errors::ToolError;
+
+/// Allowlist and denylist of tool names
+#[derive(Debug, Clone)]
+pub struct ToolFilter {
+    enabled: Vec<String>,
+    enabled_set: GlobSet,
+    disabled_set: GlobSet,
+}
+
+impl Default for ToolFilter {
+    fn default() -> Self {
+        Self::new(Vec::new(), Vec::new())
+    }
+}
+
+impl ToolFilter {
+    /// A filter allowing `enabled` (every tool when empty) minus `disabled`
+    pub fn new(enabled: Vec<String>, disabled: Vec<String>) -> Self {
+        Self {
+            enabled_set: build_set(&enabled),
+            disabled_set: build_set(&disabled),
+            enabled,
+        }
+    }
+
+    /// The filter configured in `ToolConfig`
+    pub fn from_config(config: &ToolConfig) -> Self {
+        Self::new(config.enabled_tools.clone(), config.disabled_tools.clone())
+    }
+
+    /// A filter allowing only `names`, e.g. the tools of one task
+    pub fn allow_only<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        // An empty allowlist means "no tools" here, not "every tool"
+        let enabled = if names.is_empty() { vec![String::new()] } else { names };
+        Self::new(enabled, Vec::new())
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        let enabled = self.enabled.is_empty() || self.enabled_set.is_match(name);
+        enabled && !self.disabled_set.is_match(name)
+    }
+
+    /// Fail with `PermissionDenied` naming the allowed tools if `name` is filtered out
+    pub fn check(&self, name: &str) -> Result<(), ToolError> {
+        if self.allows(name) {
+            return Ok(());
+        }
+        let allowed: Vec<&str> = self.enabled.iter().map(String::as_str).filter(|name| !name.is_empty()).collect();
+        let allowed = if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") };
+        let reason = if self.disabled_set.is_match(name) { "is disabled" } else { "is not in scope" };
+        Err(ToolError::PermissionDenied(format!(
+            "Tool '{}' {} for this task; allowed tools: {}",
+            name, reason, allowed
+        )))
+    }
+}
+
+/// Patterns that are not valid globs match literally
+fn build_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).or_else(|_| Glob::new(&globset::escape(pattern)));
+        if let Ok(glob) = glob {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_disabled_and_globs() {
+        let all = ToolFilter::default();
+        assert!(all.allows("read_file"));
+
+        let filter = ToolFilter::new(
+            vec!["read_file".to_string(), "docs_*".to_string()],
+            vec!["docs_delete".to_string()],
+        );
+        assert!(filter.allows("read_file"));
+        assert!(filter.allows("docs_search"));
+        assert!(!filter.allows("docs_delete"));
+        assert!(!filter.allows("run_command"));
+
+        let error = filter.check("run_command").unwrap_err().to_string();
+        assert!(error.contains("'run_command' is not in scope"));
+        assert!(error.contains("read_file, docs_*"));
+        assert!(filter.check("docs_delete").unwrap_err().to_string().contains("is disabled"));
+
+        let nothing = ToolFilter::allow_only(Vec::<String>::new());
+        assert!(!nothing.allows("read_file"));
+        assert!(nothing.check("read_file").unwrap_err().to_string().ends_with("allowed tools: none"));
+    }
+}
@@ -0,0 +1,419 @@
+//! Recursive file discovery
+//!
+//! Finds files (and optionally directories) by glob pattern across a
+//! workspace walk, with depth, size, age and type filters. Results carry size,
+//! line count and modification time, and can be rendered as a compact tree
+//! for prompts.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::errors::ToolError;
+use super::walk::{read_text, relative_path, WorkspaceWalk};
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Kind of entry to return
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Dir,
+    Any,
+}
+
+/// A discovered file or directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoundEntry {
+    /// Path relative to the search root, `/`-separated
+    pub path: String,
+    pub is_dir: bool,
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+    /// Line count for text files
+    pub lines: Option<usize>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Filters for [`find_files`]
+#[derive(Debug, Clone)]
+pub struct FindQuery {
+    /// Globs such as `src/**/*.rs`; patterns without `/` match the file name at any depth
+    pub patterns: Vec<String>,
+    pub exclude: Vec<String>,
+    pub kind: EntryKind,
+    pub max_depth: Option<usize>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Only entries modified within this long ago
+    pub changed_within: Option<Duration>,
+    pub max_results: usize,
+}
+
+impl Default for FindQuery {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            exclude: Vec::new(),
+            kind: EntryKind::File,
+            max_depth: None,
+            min_size: None,
+            max_size: None,
+            changed_within: None,
+            max_results: 500,
+        }
+    }
+}
+
+/// Entries found by [`find_files`], in path order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindResults {
+    pub entries: Vec<FoundEntry>,
+    /// Whether `max_results` was reached before the walk finished
+    pub truncated: bool,
+}
+
+impl FindResults {
+    /// One line per entry: path, size, line count and modification time
+    pub fn render_list(&self) -> String {
+        self.entries.iter()
+            .map(|entry| {
+                let mut line = entry.path.clone();
+                if entry.is_dir {
+                    line.push('/');
+                } else {
+                    line.push_str(&format!("  {}", format_size(entry.size)));
+                    if let Some(lines) = entry.lines {
+                        line.push_str(&format!("  {} lines", lines));
+                    }
+                }
+                if let Some(modified) = entry.modified {
+                    line.push_str(&format!("  {}", modified.format("%Y-%m-%d %H:%M")));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Indented tree, two spaces per level, directories suffixed with `/`
+    ///
+    /// Parent directories of matched files are shown even when they did not
+    /// match themselves, so the structure stays readable.
+    pub fn render_tree(&self) -> String {
+        let mut output = Vec::new();
+        let mut open: Vec<&str> = Vec::new();
+
+        for entry in &self.entries {
+            let components: Vec<&str> = entry.path.split('/').collect();
+            let (name, parents) = match components.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let common = open.iter().zip(parents).take_while(|(a, b)| a == b).count();
+            open.truncate(common);
+            for parent in &parents[common..] {
+                output.push(format!("{}{}/", "  ".repeat(open.len()), parent));
+                open.push(parent);
+            }
+
+            let indent = "  ".repeat(open.len());
+            if entry.is_dir {
+                output.push(format!("{}{}/", indent, name));
+                open.push(name);
+            } else {
+                match entry.lines {
+                    Some(lines) => output.push(format!("{}{} ({} lines)", indent, name, lines)),
+                    None => output.push(format!("{}{} ({})", indent, name, format_size(entry.size))),
+                }
+            }
+        }
+
+        output.join("\n")
+    }
+}
+
+/// Find entries under `root`
+///
+/// This is blocking file I/O; async callers should run it on a blocking thread.
+pub fn find_files(root: &Path, query: &FindQuery) -> Result<FindResults, ToolError> {
+    let patterns = compile_patterns(&query.patterns)?;
+    let cutoff = query.changed_within.and_then(|age| SystemTime::now().checked_sub(age));
+    let entries = WorkspaceWalk::new(root)
+        .with_exclude(query.exclude.clone())
+        .with_max_depth(query.max_depth)
+        .entries()?;
+
+    let mut results = FindResults::default();
+    for entry in entries {
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let wanted = match query.kind {
+            EntryKind::File => !is_dir,
+            EntryKind::Dir => is_dir,
+            EntryKind::Any => true,
+        };
+        if !wanted {
+            continue;
+        }
+
+        let relative = relative_path(root, entry.path());
+        if patterns.as_ref().is_some_and(|patterns| !patterns.is_match(&relative)) {
+            continue;
+        }
+
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let size = if is_dir { 0 } else { metadata.len() };
+        if query.min_size.is_some_and(|min| size < min) || query.max_size.is_some_and(|max| size > max) {
+            continue;
+        }
+        let modified = metadata.modified().ok();
+        if let Some(cutoff) = cutoff {
+            if modified.is_none_or(|modified| modified < cutoff) {
+                continue;
+            }
+        }
+
+        if results.entries.len() == query.max_results {
+            results.truncated = true;
+            break;
+        }
+        results.entries.push(FoundEntry {
+            lines: if is_dir { None } else { read_text(entry.path()).map(|text| text.lines().count()) },
+            path: relative,
+            is_dir,
+            size,
+            modified: modified.map(DateTime::<Utc>::from),
+        });
+    }
+
+    Ok(results)
+}
+
+/// Compile glob patterns; `None` matches everything
+///
+/// Patterns containing `/` are matched against the relative path, others
+/// against the file name. `*` never crosses a `/`; use `**` for that.
+fn compile_patterns(patterns: &[String]) -> Result<Option<PatternSet>, ToolError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut paths = GlobSetBuilder::new();
+    let mut names = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_start_matches("./");
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid glob '{}': {}", pattern, e)))?;
+        if pattern.contains('/') {
+            paths.add(glob);
+        } else {
+            names.add(glob);
+        }
+    }
+
+    let build = |builder: GlobSetBuilder| builder.build().map_err(|e| ToolError::InvalidParameters(e.to_string()));
+    Ok(Some(PatternSet { paths: build(paths)?, names: build(names)? }))
+}
+
+struct PatternSet {
+    paths: GlobSet,
+    names: GlobSet,
+}
+
+impl PatternSet {
+    /// Match a relative, `/`-separated path
+    fn is_match(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.paths.is_match(path) || self.names.is_match(name)
+    }
+}
+
+/// Parse a duration such as `30m`, `12h` or `7d` (`s`, `m`, `h`, `d`, `w`)
+fn parse_age(text: &str) -> Result<Duration, ToolError> {
+    let text = text.trim();
+    let invalid = || ToolError::InvalidParameters(format!(
+        "Invalid duration '{}': use a number followed by s, m, h, d or w",
+        text
+    ));
+    let split = text.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: u64 = text[..split].parse().map_err(|_| invalid())?;
+    let unit = match &text[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_secs(amount * unit))
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Recursive file discovery tool
+pub struct FindFilesTool;
+
+#[async_trait]
+impl Tool for FindFilesTool {
+    fn name(&self) -> &str {
+        "find_files"
+    }
+
+    fn description(&self) -> &str {
+        "Find files recursively by glob pattern (e.g. \"src/**/*.rs\"), with depth, size, \
+         age and type filters. Respects .gitignore. Use format \"tree\" for a compact overview."
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::optional("patterns", "Glob patterns; without '/' they match file names at any depth")
+                .with_items(Parameter::required("pattern", "Glob")),
+            Parameter::optional("path", "Directory to search").with_default(serde_json::json!(".")),
+            Parameter::optional("exclude", "Skip paths matching these globs")
+                .with_items(Parameter::required("glob", "Glob")),
+            Parameter::optional("type", "Entries to return")
+                .with_enum(["file", "dir", "any"])
+                .with_default(serde_json::json!("file")),
+            Parameter::optional("max_depth", "Maximum directory depth below path")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None),
+            Parameter::optional("min_size", "Minimum file size in bytes")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), None),
+            Parameter::optional("max_size", "Maximum file size in bytes")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), None),
+            Parameter::optional("changed_within", "Only entries modified within this age, e.g. \"2h\" or \"7d\"")
+                .with_pattern(r"^\d+[smhdw]$"),
+            Parameter::optional("format", "Output format")
+                .with_enum(["list", "tree"])
+                .with_default(serde_json::json!("list")),
+            Parameter::optional("max_results", "Maximum number of entries to return")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), Some(5000.0))
+                .with_default(serde_json::json!(500)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string_or("path", ".");
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let optional_size = |key: &str| -> Result<Option<u64>, ToolError> {
+            Ok(if args.contains(key) { Some(args.get_i64(key)? as u64) } else { None })
+        };
+        let query = FindQuery {
+            patterns: if args.contains("patterns") { args.get_string_array("patterns")? } else { Vec::new() },
+            exclude: if args.contains("exclude") { args.get_string_array("exclude")? } else { Vec::new() },
+            kind: serde_json::from_value(serde_json::json!(args.get_string_or("type", "file")))
+                .map_err(|e| ToolError::InvalidParameters(e.to_string()))?,
+            max_depth: optional_size("max_depth")?.map(|depth| depth as usize),
+            min_size: optional_size("min_size")?,
+            max_size: optional_size("max_size")?,
+            changed_within: if args.contains("changed_within") {
+                Some(parse_age(&args.get_string("changed_within")?)?)
+            } else {
+                None
+            },
+            max_results: args.get_i64_or("max_results", 500) as usize,
+        };
+        let tree = args.get_string_or("format", "list") == "tree";
+
+        let root = PathBuf::from(&path);
+        let results = tokio::task::spawn_blocking(move || find_files(&root, &query))
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))??;
+
+        let mut content = if tree { results.render_tree() } else { results.render_list() };
+        if results.entries.is_empty() {
+            content = "No matching files".to_string();
+        } else if results.truncated {
+            content.push_str(&format!("\n(results truncated at {} entries)", results.entries.len()));
+        }
+
+        let mut result = ToolResult::text(content);
+        result.summary = format!("Found {} entries", results.entries.len());
+        result.data = Some(serde_json::to_value(&results).map_err(|e| ToolError::ExecutionError(e.to_string()))?);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn workspace() -> TempDir {
+        let root = TempDir::new("find_tool");
+        std::fs::create_dir_all(root.join("src/tools")).unwrap();
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        std::fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "mod tools;\n\npub fn run() {}\n").unwrap();
+        std::fs::write(root.join("src/tools/mod.rs"), "pub mod find;\n").unwrap();
+        std::fs::write(root.join("src/tools/big.rs"), "// x\n".repeat(400)).unwrap();
+        std::fs::write(root.join("build/out.rs"), "ignored\n").unwrap();
+        root
+    }
+
+    fn paths(results: &FindResults) -> Vec<&str> {
+        results.entries.iter().map(|entry| entry.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_find_by_glob_depth_and_size() {
+        let root = workspace();
+
+        let query = FindQuery { patterns: vec!["src/**/*.rs".to_string()], ..FindQuery::default() };
+        let results = find_files(&root, &query).unwrap();
+        assert_eq!(paths(&results), vec!["src/lib.rs", "src/tools/big.rs", "src/tools/mod.rs"]);
+        assert_eq!(results.entries[0].lines, Some(3));
+        assert!(results.entries[0].modified.is_some());
+
+        let query = FindQuery { patterns: vec!["*.rs".to_string()], max_depth: Some(2), ..FindQuery::default() };
+        assert_eq!(paths(&find_files(&root, &query).unwrap()), vec!["src/lib.rs"]);
+
+        let query = FindQuery { min_size: Some(1000), ..FindQuery::default() };
+        assert_eq!(paths(&find_files(&root, &query).unwrap()), vec!["src/tools/big.rs"]);
+
+        let query = FindQuery { kind: EntryKind::Dir, ..FindQuery::default() };
+        assert_eq!(paths(&find_files(&root, &query).unwrap()), vec!["src", "src/tools"]);
+    }
+
+    #[test]
+    fn test_tree_rendering_and_age_filter() {
+        let root = workspace();
+
+        let results = find_files(&root, &FindQuery { patterns: vec!["*.rs".to_string()], ..FindQuery::default() }).unwrap();
+        assert_eq!(
+            results.render_tree(),
+            "src/\n  lib.rs (3 lines)\n  tools/\n    big.rs (400 lines)\n    mod.rs (1 lines)"
+        );
+
+        let recent = FindQuery { changed_within: Some(parse_age("1h").unwrap()), ..FindQuery::default() };
+        assert_eq!(find_files(&root, &recent).unwrap().entries.len(), 5);
+        assert!(parse_age("soon").is_err());
+    }
+}
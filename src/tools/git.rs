@@ -0,0 +1,867 @@
+//! Git tools
+//!
+//! Structured wrappers around the local `git` CLI. Status, diff, log and blame
+//! are read-only; checkout and commit change the repository, are flagged as
+//! mutating in their [`ToolMetadata`] and pass the guardrails before running.
+//! Every tool takes an optional `repo` argument (default: current directory).
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationTarget, OperationType};
+use super::{check_guardrails, Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Limit for a single git invocation
+const GIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Diff text longer than this is truncated in the tool output
+const MAX_DIFF_BYTES: usize = 200 * 1024;
+
+// ============================================================================
+// Git runner
+// ============================================================================
+
+/// Runs git in one repository
+struct Git {
+    repo: PathBuf,
+}
+
+impl Git {
+    fn from_args(args: &ToolArgs) -> Result<Self, ToolError> {
+        let repo = args.get_string_or("repo", ".");
+
+        // Safety check
+        if repo.contains("..") || repo.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        Ok(Self { repo: PathBuf::from(repo) })
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<String, ToolError> {
+        let output = tokio::process::Command::new("git")
+            .args(args)
+            .current_dir(&self.repo)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to run git: {}", e)))?;
+
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+        // Some failures ("nothing to commit") are only reported on stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.trim().is_empty() { String::from_utf8_lossy(&output.stdout) } else { stderr };
+        Err(ToolError::ExecutionError(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            message.trim()
+        )))
+    }
+
+    fn target(&self) -> OperationTarget {
+        OperationTarget {
+            resource_type: "directory".to_string(),
+            path: self.repo.to_string_lossy().to_string(),
+            is_protected: false,
+            snapshot: None,
+        }
+    }
+}
+
+/// A revision or branch argument; leading `-` is rejected so it cannot be read as an option
+fn revision(args: &ToolArgs, key: &str) -> Result<Option<String>, ToolError> {
+    if !args.contains(key) {
+        return Ok(None);
+    }
+    let value = args.get_string(key)?;
+    if value.starts_with('-') || value.trim().is_empty() {
+        return Err(ToolError::InvalidParameters(format!("Invalid value for '{}': {}", key, value)));
+    }
+    Ok(Some(value))
+}
+
+fn string_list(args: &ToolArgs, key: &str) -> Result<Vec<String>, ToolError> {
+    if args.contains(key) { args.get_string_array(key) } else { Ok(Vec::new()) }
+}
+
+fn structured(content: String, summary: String, data: &impl Serialize) -> Result<ToolResult, ToolError> {
+    let mut result = ToolResult::text(content);
+    result.summary = summary;
+    result.data = Some(serde_json::to_value(data).map_err(|e| ToolError::ExecutionError(e.to_string()))?);
+    Ok(result)
+}
+
+fn repo_parameter() -> Parameter {
+    Parameter::optional("repo", "Repository directory").with_default(serde_json::json!("."))
+}
+
+// ============================================================================
+// Structured output
+// ============================================================================
+
+/// One entry of `git status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    /// Source path of a rename or copy
+    pub original_path: Option<String>,
+    /// Index (staged) status code, e.g. `M`, `A`, ` `
+    pub index: String,
+    /// Worktree status code
+    pub worktree: String,
+    /// `modified`, `added`, `deleted`, `renamed`, `copied`, `untracked` or `conflicted`
+    pub kind: String,
+}
+
+/// Parsed `git status --porcelain`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitStatus {
+    /// Current branch, `None` on a detached HEAD
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub files: Vec<GitFileStatus>,
+}
+
+impl GitStatus {
+    /// Parse `git status --porcelain=v1 --branch -z` output
+    pub fn parse(output: &str) -> Self {
+        let mut status = GitStatus::default();
+        let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+
+        while let Some(entry) = entries.next() {
+            if let Some(header) = entry.strip_prefix("## ") {
+                status.parse_branch(header);
+                continue;
+            }
+            if entry.len() < 4 {
+                continue;
+            }
+
+            let (index, worktree) = (&entry[0..1], &entry[1..2]);
+            let original_path = if index == "R" || index == "C" {
+                entries.next().map(str::to_string)
+            } else {
+                None
+            };
+            status.files.push(GitFileStatus {
+                path: entry[3..].to_string(),
+                original_path,
+                kind: status_kind(index, worktree).to_string(),
+                index: index.to_string(),
+                worktree: worktree.to_string(),
+            });
+        }
+
+        status
+    }
+
+    fn parse_branch(&mut self, header: &str) {
+        let (names, counts) = match header.split_once(" [") {
+            Some((names, counts)) => (names, counts.trim_end_matches(']')),
+            None => (header, ""),
+        };
+
+        if let Some(branch) = names.strip_prefix("No commits yet on ") {
+            self.branch = Some(branch.to_string());
+        } else if !names.starts_with("HEAD (no branch)") {
+            let (branch, upstream) = match names.split_once("...") {
+                Some((branch, upstream)) => (branch, Some(upstream.to_string())),
+                None => (names, None),
+            };
+            self.branch = Some(branch.to_string());
+            self.upstream = upstream;
+        }
+
+        for count in counts.split(", ") {
+            match count.split_once(' ') {
+                Some(("ahead", n)) => self.ahead = n.parse().unwrap_or(0),
+                Some(("behind", n)) => self.behind = n.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+fn status_kind(index: &str, worktree: &str) -> &'static str {
+    match (index, worktree) {
+        ("?", "?") => "untracked",
+        ("!", "!") => "ignored",
+        ("U", _) | (_, "U") | ("A", "A") | ("D", "D") => "conflicted",
+        ("R", _) => "renamed",
+        ("C", _) => "copied",
+        ("A", _) => "added",
+        ("D", _) | (_, "D") => "deleted",
+        _ => "modified",
+    }
+}
+
+/// Lines changed in one file of a diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitDiffStat {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    pub binary: bool,
+}
+
+impl GitDiffStat {
+    /// Parse `git diff --numstat` output
+    pub fn parse_numstat(output: &str) -> Vec<Self> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let (added, removed, path) = (fields.next()?, fields.next()?, fields.next()?);
+                Some(GitDiffStat {
+                    path: path.to_string(),
+                    added: added.parse().unwrap_or(0),
+                    removed: removed.parse().unwrap_or(0),
+                    binary: added == "-",
+                })
+            })
+            .collect()
+    }
+}
+
+/// One commit from `git log`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitLogEntry {
+    pub commit: String,
+    pub short: String,
+    pub author: String,
+    pub email: String,
+    pub date: String,
+    pub subject: String,
+}
+
+/// `git log` format matching [`GitLogEntry::parse`]: fields split by US, records by RS
+const LOG_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e";
+
+impl GitLogEntry {
+    pub fn parse(output: &str) -> Vec<Self> {
+        output
+            .split('\x1e')
+            .filter_map(|record| {
+                let fields: Vec<&str> = record.trim_start_matches('\n').split('\x1f').collect();
+                match fields.as_slice() {
+                    [commit, short, author, email, date, subject] => Some(GitLogEntry {
+                        commit: commit.to_string(),
+                        short: short.to_string(),
+                        author: author.to_string(),
+                        email: email.to_string(),
+                        date: date.to_string(),
+                        subject: subject.to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// One line of `git blame`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitBlameLine {
+    pub line: usize,
+    pub commit: String,
+    pub author: String,
+    pub date: Option<DateTime<Utc>>,
+    pub summary: String,
+    pub content: String,
+}
+
+impl GitBlameLine {
+    /// Parse `git blame --porcelain` output
+    pub fn parse_porcelain(output: &str) -> Vec<Self> {
+        #[derive(Default, Clone)]
+        struct CommitInfo {
+            author: String,
+            time: Option<i64>,
+            summary: String,
+        }
+
+        let mut commits: HashMap<String, CommitInfo> = HashMap::new();
+        let mut lines = Vec::new();
+        let mut current: Option<(String, usize)> = None;
+
+        for line in output.lines() {
+            if let Some(content) = line.strip_prefix('\t') {
+                if let Some((commit, number)) = current.take() {
+                    let info = commits.get(&commit).cloned().unwrap_or_default();
+                    lines.push(GitBlameLine {
+                        line: number,
+                        author: info.author,
+                        date: info.time.and_then(|time| DateTime::from_timestamp(time, 0)),
+                        summary: info.summary,
+                        commit,
+                        content: content.to_string(),
+                    });
+                }
+                continue;
+            }
+
+            match &current {
+                None => {
+                    let mut fields = line.split(' ');
+                    if let (Some(commit), Some(_), Some(number)) = (fields.next(), fields.next(), fields.next()) {
+                        current = Some((commit.to_string(), number.parse().unwrap_or(0)));
+                        commits.entry(commit.to_string()).or_default();
+                    }
+                }
+                Some((commit, _)) => {
+                    let info = commits.entry(commit.clone()).or_default();
+                    if let Some((key, value)) = line.split_once(' ') {
+                        match key {
+                            "author" => info.author = value.to_string(),
+                            "author-time" => info.time = value.parse().ok(),
+                            "summary" => info.summary = value.to_string(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+// ============================================================================
+// Read-only tools
+// ============================================================================
+
+/// `git status` tool
+pub struct GitStatusTool;
+
+#[async_trait]
+impl Tool for GitStatusTool {
+    fn name(&self) -> &str {
+        "git_status"
+    }
+
+    fn description(&self) -> &str {
+        "Show the current branch, upstream tracking and changed, staged and untracked files"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![repo_parameter()]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only().with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let status = GitStatus::parse(&git.run(&["status", "--porcelain=v1", "--branch", "-z"]).await?);
+
+        let mut content = match (&status.branch, &status.upstream) {
+            (Some(branch), Some(upstream)) => format!(
+                "On branch {} (tracking {}, ahead {}, behind {})",
+                branch, upstream, status.ahead, status.behind
+            ),
+            (Some(branch), None) => format!("On branch {}", branch),
+            (None, _) => "HEAD detached".to_string(),
+        };
+        if status.is_clean() {
+            content.push_str("\nWorking tree clean");
+        }
+        for file in &status.files {
+            match &file.original_path {
+                Some(original) => content.push_str(&format!("\n{}{} {} -> {}", file.index, file.worktree, original, file.path)),
+                None => content.push_str(&format!("\n{}{} {}", file.index, file.worktree, file.path)),
+            }
+        }
+
+        let summary = format!("{} changed files", status.files.len());
+        structured(content, summary, &status)
+    }
+}
+
+/// `git diff` tool
+pub struct GitDiffTool;
+
+#[async_trait]
+impl Tool for GitDiffTool {
+    fn name(&self) -> &str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &str {
+        "Show a diff of unstaged worktree changes, staged changes, or between two refs, \
+         with per-file added/removed line counts"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            repo_parameter(),
+            Parameter::optional("mode", "What to compare")
+                .with_enum(["worktree", "staged", "refs"])
+                .with_default(serde_json::json!("worktree")),
+            Parameter::optional("from", "Base ref (refs mode)"),
+            Parameter::optional("to", "Target ref (refs mode; defaults to the worktree)"),
+            Parameter::optional("paths", "Limit the diff to these paths")
+                .with_items(Parameter::required("path", "Path")),
+            Parameter::optional("context_lines", "Lines of context around changes")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), Some(50.0))
+                .with_default(serde_json::json!(3)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only().with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let context = format!("-U{}", args.get_i64_or("context_lines", 3));
+        let mut command = vec!["diff".to_string(), "--no-color".to_string()];
+
+        match args.get_string_or("mode", "worktree").as_str() {
+            "staged" => command.push("--cached".to_string()),
+            "refs" => {
+                let from = revision(args, "from")?
+                    .ok_or_else(|| ToolError::InvalidParameters("'from' is required in refs mode".to_string()))?;
+                command.push(from);
+                command.extend(revision(args, "to")?);
+            }
+            _ => {}
+        }
+        let paths = string_list(args, "paths")?;
+
+        let run = |extra: &str| {
+            let mut full: Vec<&str> = command.iter().map(String::as_str).collect();
+            full.push(extra);
+            full.push("--");
+            full.extend(paths.iter().map(String::as_str));
+            full.into_iter().map(str::to_string).collect::<Vec<_>>()
+        };
+        let numstat_args = run("--numstat");
+        let diff_args = run(&context);
+        let files = GitDiffStat::parse_numstat(
+            &git.run(&numstat_args.iter().map(String::as_str).collect::<Vec<_>>()).await?,
+        );
+        let mut diff = git.run(&diff_args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+
+        let truncated = diff.len() > MAX_DIFF_BYTES;
+        if truncated {
+            let mut end = MAX_DIFF_BYTES;
+            while !diff.is_char_boundary(end) {
+                end -= 1;
+            }
+            diff.truncate(end);
+            diff.push_str("\n[diff truncated; narrow it with 'paths']");
+        }
+        if diff.is_empty() {
+            diff = "No changes".to_string();
+        }
+
+        let (added, removed) = files.iter().fold((0, 0), |(a, r), file| (a + file.added, r + file.removed));
+        let summary = format!("{} files changed, +{} -{}", files.len(), added, removed);
+        structured(diff, summary, &serde_json::json!({ "files": files, "truncated": truncated }))
+    }
+}
+
+/// `git log` tool
+pub struct GitLogTool;
+
+#[async_trait]
+impl Tool for GitLogTool {
+    fn name(&self) -> &str {
+        "git_log"
+    }
+
+    fn description(&self) -> &str {
+        "List recent commits with hash, author, date and subject"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            repo_parameter(),
+            Parameter::optional("max_count", "Maximum number of commits")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), Some(500.0))
+                .with_default(serde_json::json!(20)),
+            Parameter::optional("ref", "Branch, tag or commit to start from (default HEAD)"),
+            Parameter::optional("path", "Only commits touching this path"),
+            Parameter::optional("since", "Only commits after this date, e.g. \"2 weeks ago\" or \"2024-01-01\""),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only().with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let mut command = vec![
+            "log".to_string(),
+            format!("--max-count={}", args.get_i64_or("max_count", 20)),
+            LOG_FORMAT.to_string(),
+        ];
+        if args.contains("since") {
+            command.push(format!("--since={}", args.get_string("since")?));
+        }
+        command.extend(revision(args, "ref")?);
+        if args.contains("path") {
+            command.push("--".to_string());
+            command.push(args.get_string("path")?);
+        }
+
+        let entries = GitLogEntry::parse(&git.run(&command.iter().map(String::as_str).collect::<Vec<_>>()).await?);
+        let content = if entries.is_empty() {
+            "No commits".to_string()
+        } else {
+            entries.iter()
+                .map(|entry| format!("{} {} {} {}", entry.short, &entry.date[..entry.date.len().min(10)], entry.author, entry.subject))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let summary = format!("{} commits", entries.len());
+        structured(content, summary, &serde_json::json!({ "commits": entries }))
+    }
+}
+
+/// `git blame` tool
+pub struct GitBlameTool;
+
+#[async_trait]
+impl Tool for GitBlameTool {
+    fn name(&self) -> &str {
+        "git_blame"
+    }
+
+    fn description(&self) -> &str {
+        "Show which commit and author last changed each line in a line range of a file"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            repo_parameter(),
+            Parameter::required("path", "File to blame"),
+            Parameter::optional("start_line", "First line")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(1)),
+            Parameter::optional("end_line", "Last line (default: end of file)")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None),
+            Parameter::optional("ref", "Blame the file as of this revision"),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only().with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let start = args.get_i64_or("start_line", 1);
+        let range = match args.get_i64("end_line") {
+            Ok(end) if end < start => {
+                return Err(ToolError::InvalidParameters("'end_line' must not be before 'start_line'".to_string()));
+            }
+            Ok(end) => format!("{},{}", start, end),
+            Err(_) => format!("{},", start),
+        };
+
+        let mut command = vec!["blame".to_string(), "--porcelain".to_string(), "-L".to_string(), range];
+        command.extend(revision(args, "ref")?);
+        command.push("--".to_string());
+        command.push(args.get_string("path")?);
+
+        let lines = GitBlameLine::parse_porcelain(&git.run(&command.iter().map(String::as_str).collect::<Vec<_>>()).await?);
+        let width = lines.last().map(|line| line.line.to_string().len()).unwrap_or(1);
+        let content = lines.iter()
+            .map(|line| format!(
+                "{:>width$} {} {:<16} {} {}",
+                line.line,
+                &line.commit[..line.commit.len().min(8)],
+                line.author,
+                line.date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                line.content,
+                width = width,
+            ))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let summary = format!("Blamed {} lines", lines.len());
+        structured(content, summary, &serde_json::json!({ "lines": lines }))
+    }
+}
+
+// ============================================================================
+// Mutating tools
+// ============================================================================
+
+/// Branch creation and checkout tool
+#[derive(Default)]
+pub struct GitCheckoutTool {
+    guardrails: GuardrailEngine,
+}
+
+impl GitCheckoutTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for GitCheckoutTool {
+    fn name(&self) -> &str {
+        "git_checkout"
+    }
+
+    fn description(&self) -> &str {
+        "Switch to a branch, optionally creating it first from a start point"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            repo_parameter(),
+            Parameter::required("branch", "Branch to switch to"),
+            Parameter::optional("create", "Create the branch first")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(false)),
+            Parameter::optional("start_point", "Ref the new branch starts from (default HEAD)"),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating()
+            .with_operation(OperationType::CommandWrite)
+            .with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let branch = revision(args, "branch")?
+            .ok_or_else(|| ToolError::InvalidParameters("Missing parameter: branch".to_string()))?;
+        let create = args.get_bool_or("create", false);
+
+        check_guardrails(
+            &self.guardrails,
+            self.metadata().operation_type(),
+            &format!("git checkout {}", branch),
+            vec![git.target()],
+        )?;
+
+        let mut command = vec!["switch".to_string()];
+        if create {
+            command.push("-c".to_string());
+        }
+        command.push(branch.clone());
+        if create {
+            command.extend(revision(args, "start_point")?);
+        }
+        git.run(&command.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+        let head = git.run(&["rev-parse", "HEAD"]).await?.trim().to_string();
+
+        let verb = if create { "Created and switched to" } else { "Switched to" };
+        structured(
+            format!("{} branch {} at {}", verb, branch, &head[..head.len().min(8)]),
+            format!("{} {}", verb, branch),
+            &serde_json::json!({ "branch": branch, "created": create, "head": head }),
+        )
+    }
+}
+
+/// Commit tool
+#[derive(Default)]
+pub struct GitCommitTool {
+    guardrails: GuardrailEngine,
+}
+
+impl GitCommitTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &str {
+        "Commit staged changes with a message, optionally staging the given paths or all tracked changes first"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            repo_parameter(),
+            Parameter::required("message", "Commit message"),
+            Parameter::optional("paths", "Paths to stage before committing")
+                .with_items(Parameter::required("path", "Path")),
+            Parameter::optional("all", "Stage all modified and deleted tracked files")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(false)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating()
+            .with_operation(OperationType::CommandWrite)
+            .with_timeout(GIT_TIMEOUT)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let git = Git::from_args(args)?;
+        let message = args.get_string("message")?;
+        if message.trim().is_empty() {
+            return Err(ToolError::InvalidParameters("Commit message must not be empty".to_string()));
+        }
+
+        check_guardrails(
+            &self.guardrails,
+            self.metadata().operation_type(),
+            &format!("git commit: {}", message.lines().next().unwrap_or_default()),
+            vec![git.target()],
+        )?;
+
+        let paths = string_list(args, "paths")?;
+        if !paths.is_empty() {
+            let mut add = vec!["add", "--"];
+            add.extend(paths.iter().map(String::as_str));
+            git.run(&add).await?;
+        }
+
+        let mut command = vec!["commit", "-m", message.as_str()];
+        if args.get_bool_or("all", false) {
+            command.push("-a");
+        }
+        git.run(&command).await?;
+
+        let head = git.run(&["rev-parse", "HEAD"]).await?.trim().to_string();
+        let files = GitDiffStat::parse_numstat(&git.run(&["show", "--numstat", "--format=", "HEAD"]).await?);
+        let subject = message.lines().next().unwrap_or_default();
+
+        structured(
+            format!("Committed {} ({} files): {}", &head[..head.len().min(8)], files.len(), subject),
+            format!("Committed {}", &head[..head.len().min(8)]),
+            &serde_json::json!({ "commit": head, "files": files }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn git(repo: &std::path::Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git").args(args).current_dir(repo).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Repository under the current directory, since tools only accept relative paths
+    fn init_repo() -> TempDir {
+        let repo = TempDir::relative("test_temp_git");
+        let path: &std::path::Path = &repo;
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.name", "Test"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        std::fs::write(path.join("README.md"), "hello\nworld\n").unwrap();
+        std::fs::write(path.join("old.txt"), "old\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "initial"]);
+        repo
+    }
+
+    fn args(value: serde_json::Value) -> ToolArgs {
+        ToolArgs::from_map(serde_json::from_value(value).unwrap())
+    }
+
+    #[test]
+    fn test_parse_status_porcelain() {
+        let output = "## main...origin/main [ahead 2, behind 1]\0M  src/lib.rs\0 D gone.rs\0R  new.rs\0old.rs\0?? notes.md\0UU both.rs\0";
+        let status = GitStatus::parse(output);
+
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        let kinds: Vec<&str> = status.files.iter().map(|f| f.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["modified", "deleted", "renamed", "untracked", "conflicted"]);
+        assert_eq!(status.files[2].original_path.as_deref(), Some("old.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_status_diff_commit_and_log() {
+        let dir = init_repo();
+        let path: &std::path::Path = &dir;
+        let repo = path.to_str().unwrap();
+        std::fs::write(path.join("README.md"), "hello\nthere\n").unwrap();
+        git(path, &["mv", "old.txt", "new.txt"]);
+
+        let status = GitStatusTool.execute(&args(serde_json::json!({"repo": repo}))).await.unwrap();
+        let data = status.data.unwrap();
+        assert_eq!(data["branch"], "main");
+        assert_eq!(data["files"].as_array().unwrap().len(), 2);
+
+        let diff = GitDiffTool.execute(&args(serde_json::json!({"repo": repo}))).await.unwrap();
+        assert!(diff.content.contains("+there"));
+        assert_eq!(diff.data.unwrap()["files"][0], serde_json::json!({"path": "README.md", "added": 1, "removed": 1, "binary": false}));
+
+        let commit = GitCommitTool::new()
+            .execute(&args(serde_json::json!({"repo": repo, "message": "Update readme", "all": true})))
+            .await
+            .unwrap();
+        assert_eq!(commit.data.unwrap()["files"].as_array().unwrap().len(), 2);
+
+        let log = GitLogTool.execute(&args(serde_json::json!({"repo": repo, "max_count": 5}))).await.unwrap();
+        let commits = log.data.unwrap()["commits"].clone();
+        assert_eq!(commits[0]["subject"], "Update readme");
+        assert_eq!(commits[1]["subject"], "initial");
+    }
+
+    #[tokio::test]
+    async fn test_blame_and_checkout() {
+        let dir = init_repo();
+        let path: &std::path::Path = &dir;
+        let repo = path.to_str().unwrap();
+
+        let blame = GitBlameTool
+            .execute(&args(serde_json::json!({"repo": repo, "path": "README.md", "start_line": 2, "end_line": 2})))
+            .await
+            .unwrap();
+        let lines = blame.data.unwrap()["lines"].clone();
+        assert_eq!(lines.as_array().unwrap().len(), 1);
+        assert_eq!(lines[0]["line"], 2);
+        assert_eq!(lines[0]["author"], "Test");
+        assert_eq!(lines[0]["content"], "world");
+
+        let checkout = GitCheckoutTool::new();
+        checkout.execute(&args(serde_json::json!({"repo": repo, "branch": "feature", "create": true}))).await.unwrap();
+        assert_eq!(git(path, &["branch", "--show-current"]), "feature");
+        let error = checkout.execute(&args(serde_json::json!({"repo": repo, "branch": "--orphan"}))).await.unwrap_err();
+        assert!(matches!(error, ToolError::InvalidParameters(_)));
+        assert_eq!(checkout.metadata().operation_type(), OperationType::CommandWrite);
+        assert!(GitLogTool.metadata().read_only);
+    }
+}
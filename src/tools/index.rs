@@ -0,0 +1,590 @@
+//! Persisted workspace index with BM25 retrieval
+//!
+//! Large repositories do not fit in a prompt, so the agent pulls in the
+//! parts relevant to a query instead. A [`WorkspaceIndex`] splits every text
+//! file of the workspace into fixed-size line chunks and records their term
+//! counts; [`WorkspaceIndex::search`] ranks the chunks against a query with
+//! BM25. The index is stored as JSON under a cache directory and brought up
+//! to date incrementally: files whose mtime and size are unchanged are not
+//! read again, and files whose content hash is unchanged are not re-chunked.
+//!
+//! [`ContextRetriever`] holds one index; pass the same `Arc` to the
+//! `retrieve_context` tool and to `SequentialExecutor::with_context_retrieval`
+//! so both read and refresh a single copy.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use crate::config::IndexConfig;
+use crate::errors::ToolError;
+use crate::execution::journal::content_hash;
+use super::walk::{read_text, relative_path, split_words, WorkspaceWalk};
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// File name of the index inside the cache directory
+const INDEX_FILE: &str = "index.json";
+
+/// Bumped whenever the stored format changes; older indexes are rebuilt
+const INDEX_VERSION: u32 = 1;
+
+/// Lines per chunk unless configured otherwise
+pub const DEFAULT_CHUNK_LINES: usize = 40;
+
+/// Snippets returned unless asked for more
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// Most snippets one call may return
+const MAX_TOP_K: usize = 50;
+
+/// Snippet text beyond this many characters is cut
+const MAX_SNIPPET_CHARS: usize = 4000;
+
+/// BM25 term frequency saturation
+const BM25_K1: f64 = 1.2;
+
+/// BM25 document length normalization
+const BM25_B: f64 = 0.75;
+
+// ============================================================================
+// Index
+// ============================================================================
+
+/// Lines `start_line..=end_line` of a file and the counts of their terms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    start_line: usize,
+    end_line: usize,
+    length: u32,
+    terms: HashMap<String, u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    /// Modification time in nanoseconds since the epoch
+    modified: u128,
+    size: u64,
+    hash: String,
+    chunks: Vec<Chunk>,
+}
+
+/// On-disk form of the index
+#[derive(Serialize, Deserialize)]
+struct StoredIndex {
+    version: u32,
+    chunk_lines: usize,
+    files: BTreeMap<String, IndexedFile>,
+}
+
+/// What an [`WorkspaceIndex::update`] changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct IndexUpdate {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+impl IndexUpdate {
+    pub fn is_changed(&self) -> bool {
+        self.added + self.updated + self.removed > 0
+    }
+}
+
+/// A chunk that matched a query, with its current text
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedSnippet {
+    /// Path relative to the workspace root
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f64,
+    pub content: String,
+}
+
+/// Chunked, term-counted view of the text files under a root
+pub struct WorkspaceIndex {
+    root: PathBuf,
+    cache_dir: PathBuf,
+    chunk_lines: usize,
+    files: BTreeMap<String, IndexedFile>,
+    /// Number of chunks containing each term
+    doc_freq: HashMap<String, u32>,
+    total_chunks: usize,
+    total_length: u64,
+}
+
+impl std::fmt::Debug for WorkspaceIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkspaceIndex")
+            .field("root", &self.root)
+            .field("cache_dir", &self.cache_dir)
+            .field("files", &self.files.len())
+            .field("chunks", &self.total_chunks)
+            .finish()
+    }
+}
+
+impl WorkspaceIndex {
+    /// Load the index stored under `cache_dir`, or start an empty one
+    ///
+    /// A missing, unreadable or outdated index is not an error; the next
+    /// [`update`](Self::update) reads every file.
+    pub fn open(root: impl Into<PathBuf>, cache_dir: impl Into<PathBuf>, chunk_lines: usize) -> Self {
+        let mut index = Self {
+            root: root.into(),
+            cache_dir: cache_dir.into(),
+            chunk_lines: chunk_lines.max(1),
+            files: BTreeMap::new(),
+            doc_freq: HashMap::new(),
+            total_chunks: 0,
+            total_length: 0,
+        };
+        let path = index.cache_dir.join(INDEX_FILE);
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<StoredIndex>(&bytes) {
+                Ok(stored) if stored.version == INDEX_VERSION && stored.chunk_lines == index.chunk_lines => {
+                    index.files = stored.files;
+                    index.rebuild_statistics();
+                }
+                Ok(_) => tracing::debug!("Rebuilding outdated workspace index at {}", path.display()),
+                Err(e) => tracing::warn!("Ignoring unreadable workspace index at {}: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to read workspace index at {}: {}", path.display(), e),
+        }
+        index
+    }
+
+    /// Number of indexed files
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Number of indexed chunks
+    pub fn chunk_count(&self) -> usize {
+        self.total_chunks
+    }
+
+    /// Bring the index in line with the files on disk
+    ///
+    /// Only files that are new or whose mtime or size changed are read, and
+    /// only those whose content hash changed are chunked again. Files that
+    /// were deleted, or became binary or ignored, are dropped.
+    pub fn update(&mut self) -> Result<IndexUpdate, ToolError> {
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        let cache_dir = self.cache_dir.canonicalize().ok();
+        let mut update = IndexUpdate::default();
+        let mut seen = HashSet::new();
+
+        for path in WorkspaceWalk::new(&root).files()? {
+            if cache_dir.as_ref().is_some_and(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            let Ok(metadata) = std::fs::metadata(&path) else { continue };
+            let relative = relative_path(&root, &path);
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |elapsed| elapsed.as_nanos());
+            let size = metadata.len();
+
+            if self.files.get(&relative).is_some_and(|file| file.modified == modified && file.size == size) {
+                update.unchanged += 1;
+                seen.insert(relative);
+                continue;
+            }
+            let Some(content) = read_text(&path) else { continue };
+            let hash = content_hash(content.as_bytes());
+
+            match self.files.get_mut(&relative) {
+                Some(file) if file.hash == hash => {
+                    file.modified = modified;
+                    file.size = size;
+                    update.unchanged += 1;
+                }
+                _ => {
+                    let file = IndexedFile { modified, size, hash, chunks: chunk(&content, self.chunk_lines) };
+                    if self.files.insert(relative.clone(), file).is_some() {
+                        update.updated += 1;
+                    } else {
+                        update.added += 1;
+                    }
+                }
+            }
+            seen.insert(relative);
+        }
+
+        let before = self.files.len();
+        self.files.retain(|path, _| seen.contains(path));
+        update.removed = before - self.files.len();
+
+        if update.is_changed() {
+            self.rebuild_statistics();
+        }
+        Ok(update)
+    }
+
+    /// Write the index to its cache directory
+    pub fn save(&self) -> Result<(), ToolError> {
+        std::fs::create_dir_all(&self.cache_dir).map_err(|e| cache_error(&self.cache_dir, e))?;
+        let stored = StoredIndex {
+            version: INDEX_VERSION,
+            chunk_lines: self.chunk_lines,
+            files: self.files.clone(),
+        };
+        let content = serde_json::to_vec(&stored)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to serialize workspace index: {}", e)))?;
+
+        // Write then rename so a reader never sees a partial index
+        let path = self.cache_dir.join(INDEX_FILE);
+        let partial = self.cache_dir.join(format!("{}.partial", INDEX_FILE));
+        std::fs::write(&partial, content).map_err(|e| cache_error(&partial, e))?;
+        std::fs::rename(&partial, &path).map_err(|e| cache_error(&path, e))
+    }
+
+    /// The `k` chunks ranking highest for `query` under BM25, best first
+    ///
+    /// Snippet text is read from the files as they are now, so call
+    /// [`update`](Self::update) first for the line ranges to be current.
+    pub fn search(&self, query: &str, k: usize) -> Vec<RetrievedSnippet> {
+        let terms: HashSet<String> = tokenize(query).collect();
+        if terms.is_empty() || self.total_chunks == 0 || k == 0 {
+            return Vec::new();
+        }
+
+        let chunks = self.total_chunks as f64;
+        let average_length = (self.total_length as f64 / chunks).max(1.0);
+        let idf: Vec<(&str, f64)> = terms
+            .iter()
+            .filter_map(|term| {
+                let df = f64::from(*self.doc_freq.get(term)?);
+                Some((term.as_str(), (1.0 + (chunks - df + 0.5) / (df + 0.5)).ln()))
+            })
+            .collect();
+
+        let mut scored: Vec<(f64, &str, &Chunk)> = Vec::new();
+        for (path, file) in &self.files {
+            for chunk in &file.chunks {
+                let length_norm = 1.0 - BM25_B + BM25_B * f64::from(chunk.length) / average_length;
+                let score: f64 = idf
+                    .iter()
+                    .filter_map(|(term, idf)| {
+                        let tf = f64::from(*chunk.terms.get(*term)?);
+                        Some(idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * length_norm))
+                    })
+                    .sum();
+                if score > 0.0 {
+                    scored.push((score, path, chunk));
+                }
+            }
+        }
+        scored.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| a.1.cmp(b.1))
+                .then_with(|| a.2.start_line.cmp(&b.2.start_line))
+        });
+
+        let mut contents: HashMap<&str, Option<String>> = HashMap::new();
+        scored
+            .into_iter()
+            .filter_map(|(score, path, chunk)| {
+                let text = contents
+                    .entry(path)
+                    .or_insert_with(|| read_text(&self.root.join(path)))
+                    .as_deref()?;
+                Some(RetrievedSnippet {
+                    path: path.to_string(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score,
+                    content: snippet(text, chunk.start_line, chunk.end_line),
+                })
+            })
+            .take(k)
+            .collect()
+    }
+
+    fn rebuild_statistics(&mut self) {
+        self.doc_freq.clear();
+        self.total_chunks = 0;
+        self.total_length = 0;
+        for chunk in self.files.values().flat_map(|file| &file.chunks) {
+            self.total_chunks += 1;
+            self.total_length += u64::from(chunk.length);
+            for term in chunk.terms.keys() {
+                *self.doc_freq.entry(term.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+/// Split `content` into chunks of `chunk_lines` lines, skipping chunks without terms
+fn chunk(content: &str, chunk_lines: usize) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    lines
+        .chunks(chunk_lines)
+        .enumerate()
+        .filter_map(|(i, window)| {
+            let mut terms = HashMap::new();
+            let mut length = 0;
+            for term in window.iter().flat_map(|line| tokenize(line)) {
+                *terms.entry(term).or_insert(0) += 1;
+                length += 1;
+            }
+            (length > 0).then(|| Chunk {
+                start_line: i * chunk_lines + 1,
+                end_line: i * chunk_lines + window.len(),
+                length,
+                terms,
+            })
+        })
+        .collect()
+}
+
+/// Lower-case terms of `text`, splitting identifiers at `_` and camelCase humps
+fn tokenize(text: &str) -> impl Iterator<Item = String> {
+    split_words(text).into_iter().filter(|word| word.chars().count() >= 2)
+}
+
+/// Lines `start..=end` (1-based) of `text`, cut to [`MAX_SNIPPET_CHARS`]
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let content = text.lines().skip(start - 1).take(end + 1 - start).collect::<Vec<_>>().join("\n");
+    match content.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((cut, _)) => format!("{}\n[... snippet truncated]", &content[..cut]),
+        None => content,
+    }
+}
+
+fn cache_error(path: &Path, error: std::io::Error) -> ToolError {
+    ToolError::ExecutionError(format!("Workspace index cache {}: {}", path.display(), error))
+}
+
+/// Snippets as markdown sections headed by their path and line range
+pub fn render_snippets(snippets: &[RetrievedSnippet]) -> String {
+    snippets
+        .iter()
+        .map(|s| format!("## {}:{}-{}\n```\n{}\n```", s.path, s.start_line, s.end_line, s.content))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// ============================================================================
+// Retriever
+// ============================================================================
+
+/// A workspace index loaded on first use and refreshed before every query
+#[derive(Debug)]
+pub struct ContextRetriever {
+    root: PathBuf,
+    cache_dir: PathBuf,
+    chunk_lines: usize,
+    top_k: usize,
+    index: Mutex<Option<WorkspaceIndex>>,
+}
+
+impl ContextRetriever {
+    pub fn new(root: impl Into<PathBuf>, cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            cache_dir: cache_dir.into(),
+            chunk_lines: DEFAULT_CHUNK_LINES,
+            top_k: DEFAULT_TOP_K,
+            index: Mutex::new(None),
+        }
+    }
+
+    pub fn from_config(config: &IndexConfig) -> Self {
+        Self::new(&config.root, &config.cache_dir)
+            .with_chunk_lines(config.chunk_lines)
+            .with_top_k(config.top_k)
+    }
+
+    /// Index files in chunks of `lines` lines
+    pub fn with_chunk_lines(mut self, lines: usize) -> Self {
+        self.chunk_lines = lines.max(1);
+        self
+    }
+
+    /// Snippets returned when the caller does not ask for a number
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.clamp(1, MAX_TOP_K);
+        self
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    /// Update the index and return the `k` chunks most relevant to `query`
+    ///
+    /// The updated index is saved when anything changed; failing to save it
+    /// only costs the next process a full re-read, so it is logged, not returned.
+    pub async fn retrieve(
+        self: &Arc<Self>,
+        query: &str,
+        k: usize,
+    ) -> Result<(IndexUpdate, Vec<RetrievedSnippet>), ToolError> {
+        let retriever = Arc::clone(self);
+        let query = query.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut guard = retriever.index.lock().unwrap();
+            let index = guard.get_or_insert_with(|| {
+                WorkspaceIndex::open(&retriever.root, &retriever.cache_dir, retriever.chunk_lines)
+            });
+            let update = index.update()?;
+            if update.is_changed() {
+                if let Err(e) = index.save() {
+                    tracing::warn!("Failed to save workspace index: {}", e);
+                }
+            }
+            Ok((update, index.search(&query, k)))
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Workspace index task failed: {}", e)))?
+    }
+}
+
+// ============================================================================
+// Tool
+// ============================================================================
+
+/// Tool returning the workspace chunks most relevant to a query
+pub struct RetrieveContextTool {
+    retriever: Arc<ContextRetriever>,
+}
+
+impl RetrieveContextTool {
+    pub fn new(retriever: Arc<ContextRetriever>) -> Self {
+        Self { retriever }
+    }
+}
+
+#[async_trait]
+impl Tool for RetrieveContextTool {
+    fn name(&self) -> &str {
+        "retrieve_context"
+    }
+
+    fn description(&self) -> &str {
+        "Find the code in the workspace most relevant to a query (BM25 ranking over an index of \
+         file chunks) and return it with file paths and line ranges"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("query", "What to look for: identifiers, error messages or a description"),
+            Parameter::optional("top_k", "Maximum number of snippets to return")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), Some(MAX_TOP_K as f64))
+                .with_default(serde_json::json!(self.retriever.top_k())),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let query = args.get_string("query")?;
+        let top_k = args.get_i64_or("top_k", self.retriever.top_k() as i64).clamp(1, MAX_TOP_K as i64) as usize;
+        let (update, snippets) = self.retriever.retrieve(&query, top_k).await?;
+
+        let summary = format!("{} snippet(s) for '{}'", snippets.len(), query);
+        let content = if snippets.is_empty() {
+            format!("No indexed code matches '{}'", query)
+        } else {
+            render_snippets(&snippets)
+        };
+        let mut result = ToolResult::text(content);
+        result.summary = summary;
+        result.data = Some(serde_json::json!({
+            "index": update,
+            "results": snippets
+                .iter()
+                .map(|s| serde_json::json!({
+                    "path": s.path,
+                    "start_line": s.start_line,
+                    "end_line": s.end_line,
+                    "score": s.score,
+                }))
+                .collect::<Vec<_>>(),
+        }));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn workspace() -> TempDir {
+        let root = TempDir::new("workspace_index");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_update_is_incremental_and_persisted() {
+        let root = workspace();
+        let cache = root.join(".cache/index");
+        std::fs::write(root.join("src/a.rs"), "fn parse_config() {}\n").unwrap();
+        std::fs::write(root.join("src/b.rs"), "fn render_page() {}\n").unwrap();
+
+        let mut index = WorkspaceIndex::open(&root, &cache, 10);
+        assert_eq!(index.update().unwrap(), IndexUpdate { added: 2, ..Default::default() });
+        index.save().unwrap();
+
+        // A fresh process picks up the saved index and reads only what changed
+        std::fs::write(root.join("src/a.rs"), "fn parse_config_file() {}\n").unwrap();
+        std::fs::remove_file(root.join("src/b.rs")).unwrap();
+        std::fs::write(root.join("src/c.rs"), "fn main() {}\n").unwrap();
+        let mut index = WorkspaceIndex::open(&root, &cache, 10);
+        assert_eq!(index.file_count(), 2);
+        assert_eq!(index.update().unwrap(), IndexUpdate { added: 1, updated: 1, removed: 1, unchanged: 0 });
+        assert_eq!(index.update().unwrap(), IndexUpdate { unchanged: 2, ..Default::default() });
+        assert_eq!(index.search("render page", 5).len(), 0);
+        assert_eq!(index.search("config file", 5)[0].path, "src/a.rs");
+
+        // A different chunk size invalidates the stored index
+        index.save().unwrap();
+        assert_eq!(WorkspaceIndex::open(&root, &cache, 20).file_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bm25_ranks_matching_chunk_first() {
+        let root = workspace();
+        let mut handler = String::from("// request handling\n");
+        for i in 0..8 {
+            handler.push_str(&format!("fn handle_{}() {{ log(\"request\"); }}\n", i));
+        }
+        handler.push_str("fn retry_backoff(attempt: u32) -> Duration {\n    backoff_delay(attempt)\n}\n");
+        std::fs::write(root.join("src/server.rs"), handler).unwrap();
+        std::fs::write(root.join("src/client.rs"), "fn send_request() {}\nfn request_timeout() {}\n").unwrap();
+
+        let retriever = Arc::new(ContextRetriever::new(&root, root.join(".cache")).with_chunk_lines(5));
+        let tool = RetrieveContextTool::new(Arc::clone(&retriever));
+        let mut args = HashMap::new();
+        args.insert("query".to_string(), serde_json::json!("retry with backoff"));
+        args.insert("top_k".to_string(), serde_json::json!(2));
+        let result = tool.execute(&ToolArgs::from_map(args)).await.unwrap();
+
+        let data = result.data.unwrap();
+        assert_eq!(data["index"]["added"], 2);
+        assert_eq!(data["results"].as_array().unwrap().len(), 2);
+        assert_eq!(data["results"][0]["path"], "src/server.rs");
+        assert_eq!(data["results"][0]["start_line"], 6);
+        assert!(result.content.contains("## src/server.rs:6-"));
+        assert!(result.content.contains("fn retry_backoff(attempt: u32)"));
+
+        // Rare terms outrank common ones
+        let (update, snippets) = retriever.retrieve("request timeout", 3).await.unwrap();
+        assert!(!update.is_changed());
+        assert_eq!(snippets[0].path, "src/client.rs");
+        assert!(root.join(".cache").join(INDEX_FILE).exists());
+    }
+}
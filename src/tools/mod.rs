@@ -0,0 +1,1284 @@
+//! Tool system for the AI-Native Code Agent
+
+pub mod schema;
+pub mod edit;
+pub mod search;
+pub mod find;
+pub mod git;
+pub mod cargo;
+pub mod plugin;
+pub mod filter;
+pub mod shell;
+pub mod process;
+pub mod output;
+pub mod index;
+pub(crate) mod walk;
+
+pub use schema::{Parameter, ParameterType, object_schema, validate_args};
+pub use edit::{EditFileTool, FileEdit, SearchReplace, Hunk, HunkLine, LineChanges, AppliedEdit, EditFailure, EditError};
+pub use search::{SearchTool, SearchQuery, SearchMatch, SearchResults, search_workspace};
+pub use find::{FindFilesTool, FindQuery, FindResults, FoundEntry, EntryKind, find_files};
+pub use git::{GitStatusTool, GitDiffTool, GitLogTool, GitBlameTool, GitCheckoutTool, GitCommitTool, GitStatus, GitFileStatus, GitDiffStat, GitLogEntry, GitBlameLine};
+pub use cargo::{CargoTool, CargoCommand, CargoInvocation, CargoReport, Diagnostic, Severity, TestOutcome, TestStatus, run_cargo};
+pub use plugin::{PluginTool, PluginManifest, ManifestParameter, discover_plugins, register_plugins, register_configured_plugins};
+pub use filter::ToolFilter;
+pub use shell::{ShellSessionTool, ShellSession, ShellOutput};
+pub use output::{OutputProcessor, OutputSummarizer, ModelSummarizer, ArtifactStore, Artifact, ReadArtifactTool, READ_ARTIFACT_TOOL};
+pub use index::{WorkspaceIndex, IndexUpdate, RetrievedSnippet, ContextRetriever, RetrieveContextTool, render_snippets, DEFAULT_CHUNK_LINES};
+pub use process::{ProcessManager, BackgroundProcess, ProcessOutput, ProcessStartTool, ProcessReadTool, ProcessSignalTool, ProcessStopTool};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::config::AgentConfig;
+use crate::errors::{CommandOperationError, ToolError};
+use crate::execution::{stream_command, ChangeJournal, OutputLine};
+use crate::execution::snapshot::{SnapshotBackend, SnapshotScope};
+use crate::execution::guardrails::{GuardrailEngine, OperationGuard, OperationRiskLevel, OperationTarget, OperationType};
+use crate::models::ToolDefinition;
+use crate::security::ResourceLimits;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Tool trait
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn parameters(&self) -> Vec<Parameter>;
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError>;
+
+    /// Files this call will create, modify or delete, recorded in the change journal
+    fn modified_paths(&self, _args: &ToolArgs) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Release per-task state, such as a shell session, once a task has finished
+    async fn task_finished(&self, _task_id: &str) {}
+
+    /// Scheduling metadata; tools are treated as mutating with no limits by default
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::default()
+    }
+
+    /// Definition sent to the model, with a JSON Schema generated from `parameters`
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: object_schema(&self.parameters()),
+        }
+    }
+}
+
+/// Receives command output from tools while they run
+///
+/// Set on a registry with [`ToolRegistry::set_output_observer`] to forward
+/// output to a UI, e.g. CLI output or WebSocket progress messages.
+pub trait OutputObserver: Send + Sync {
+    fn on_output(&self, task_id: Option<&str>, tool: &str, line: &OutputLine);
+}
+
+impl<F> OutputObserver for F
+where
+    F: Fn(Option<&str>, &str, &OutputLine) + Send + Sync,
+{
+    fn on_output(&self, task_id: Option<&str>, tool: &str, line: &OutputLine) {
+        self(task_id, tool, line)
+    }
+}
+
+/// Tool arguments
+#[derive(Clone)]
+pub struct ToolArgs {
+    args: HashMap<String, serde_json::Value>,
+    task_id: Option<String>,
+    observer: Option<Arc<dyn OutputObserver>>,
+}
+
+impl std::fmt::Debug for ToolArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolArgs")
+            .field("args", &self.args)
+            .field("task_id", &self.task_id)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl ToolArgs {
+    pub fn from_map(args: HashMap<String, serde_json::Value>) -> Self {
+        Self { args, task_id: None, observer: None }
+    }
+
+    /// Forward output emitted by the tool to `observer`
+    pub fn with_observer(mut self, observer: Option<Arc<dyn OutputObserver>>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Report a line of output to the observer of this call, if any
+    pub fn emit_output(&self, tool: &str, line: &OutputLine) {
+        if let Some(observer) = &self.observer {
+            observer.on_output(self.task_id(), tool, line);
+        }
+    }
+
+    /// Tag the call with the task it belongs to
+    pub fn with_task_id(mut self, task_id: Option<String>) -> Self {
+        self.task_id = task_id;
+        self
+    }
+
+    /// Task the call belongs to, if it runs as part of one
+    pub fn task_id(&self) -> Option<&str> {
+        self.task_id.as_deref()
+    }
+
+    pub fn get_string(&self, key: &str) -> Result<String, ToolError> {
+        self.args.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| ToolError::InvalidParameters(format!("Missing or invalid parameter: {}", key)))
+    }
+
+    pub fn get_string_or(&self, key: &str, default: &str) -> String {
+        self.args.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or(default)
+            .to_string()
+    }
+
+    /// Raw argument value
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.args.get(key)
+    }
+
+    pub fn get_i64(&self, key: &str) -> Result<i64, ToolError> {
+        self.get_typed(key, "an integer", serde_json::Value::as_i64)
+    }
+
+    pub fn get_i64_or(&self, key: &str, default: i64) -> i64 {
+        self.get_i64(key).unwrap_or(default)
+    }
+
+    pub fn get_f64(&self, key: &str) -> Result<f64, ToolError> {
+        self.get_typed(key, "a number", serde_json::Value::as_f64)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Result<bool, ToolError> {
+        self.get_typed(key, "a boolean", serde_json::Value::as_bool)
+    }
+
+    pub fn get_bool_or(&self, key: &str, default: bool) -> bool {
+        self.get_bool(key).unwrap_or(default)
+    }
+
+    pub fn get_array(&self, key: &str) -> Result<Vec<serde_json::Value>, ToolError> {
+        self.get_typed(key, "an array", |v| v.as_array().cloned())
+    }
+
+    /// Array of strings; non-string items are rejected
+    pub fn get_string_array(&self, key: &str) -> Result<Vec<String>, ToolError> {
+        self.get_array(key)?
+            .iter()
+            .map(|v| v.as_str().map(str::to_string).ok_or_else(|| {
+                ToolError::InvalidParameters(format!("Parameter '{}' must contain only strings", key))
+            }))
+            .collect()
+    }
+
+    pub fn get_object(&self, key: &str) -> Result<serde_json::Map<String, serde_json::Value>, ToolError> {
+        self.get_typed(key, "an object", |v| v.as_object().cloned())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.args.contains_key(key)
+    }
+
+    /// All arguments
+    pub fn as_map(&self) -> &HashMap<String, serde_json::Value> {
+        &self.args
+    }
+
+    fn get_typed<T>(
+        &self,
+        key: &str,
+        expected: &str,
+        convert: impl Fn(&serde_json::Value) -> Option<T>,
+    ) -> Result<T, ToolError> {
+        match self.args.get(key) {
+            Some(value) => convert(value).ok_or_else(|| {
+                ToolError::InvalidParameters(format!("Parameter '{}' must be {}, got {}", key, expected, value))
+            }),
+            None => Err(ToolError::InvalidParameters(format!("Missing parameter: {}", key))),
+        }
+    }
+}
+
+/// Tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub success: bool,
+    pub content: String,
+    pub summary: String,
+    pub data: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+impl ToolResult {
+    pub fn text(content: String) -> Self {
+        Self {
+            success: true,
+            summary: content.clone(),
+            content,
+            data: None,
+            error: None,
+        }
+    }
+
+    pub fn json(data: serde_json::Value) -> Self {
+        Self {
+            success: true,
+            summary: "Operation completed successfully".to_string(),
+            content: "Operation completed successfully".to_string(),
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn error(error: String) -> Self {
+        Self {
+            success: false,
+            summary: error.clone(),
+            content: String::new(),
+            data: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Tool call
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub args: ToolArgs,
+}
+
+/// Scheduling metadata for a tool
+///
+/// Read-only tools never change the workspace, so independent calls to them
+/// can run in parallel. [`ToolRegistry::execute_batch`] relies on this to run
+/// a batch's read-only calls together and its mutating calls one at a time,
+/// in order. [`ToolRegistry::execute`] does not order calls: callers running
+/// calls concurrently outside a batch must serialize mutating ones themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolMetadata {
+    /// Maximum time a single invocation may run before it is cancelled
+    pub timeout: Option<Duration>,
+    /// Maximum number of invocations running at the same time
+    pub max_concurrency: Option<usize>,
+    /// Whether the tool only reads state
+    pub read_only: bool,
+    /// How guardrails classify a call; derived from `read_only` when unset
+    pub operation: Option<OperationType>,
+}
+
+impl ToolMetadata {
+    /// Metadata for a tool that only reads state
+    pub fn read_only() -> Self {
+        Self { read_only: true, ..Self::default() }
+    }
+
+    /// Metadata for a tool that may change state
+    pub fn mutating() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
+    pub fn with_operation(mut self, operation: OperationType) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    /// Operation type used for guardrail checks
+    pub fn operation_type(&self) -> OperationType {
+        match &self.operation {
+            Some(operation) => operation.clone(),
+            None if self.read_only => OperationType::CommandRead,
+            None => OperationType::CommandWrite,
+        }
+    }
+}
+
+/// Check a tool call against the guardrails
+///
+/// Tool calls cannot ask for confirmation, so anything rated high risk or
+/// above (protected paths, dangerous patterns) is refused.
+pub(crate) fn check_guardrails(
+    guardrails: &GuardrailEngine,
+    operation: OperationType,
+    description: &str,
+    targets: Vec<OperationTarget>,
+) -> Result<OperationGuard, ToolError> {
+    let guard = guardrails
+        .check_operation(operation, description, targets)
+        .map_err(|e| ToolError::PermissionDenied(e.to_string()))?;
+    if guard.risk_level >= OperationRiskLevel::High {
+        return Err(ToolError::PermissionDenied(format!(
+            "{} is {:?} risk and needs confirmation",
+            description, guard.risk_level
+        )));
+    }
+    Ok(guard)
+}
+
+/// Task that snapshots of calls made outside any task belong to
+const DEFAULT_SNAPSHOT_TASK: &str = "default";
+
+/// Restore every file captured in `snapshot_id`
+async fn restore_snapshot(backend: &dyn SnapshotBackend, snapshot_id: &str) -> Result<(), crate::errors::AgentError> {
+    let plan = backend.rollback_plan(snapshot_id, None).await?;
+    backend.execute_rollback(&plan).await
+}
+
+/// A registered tool together with its metadata and concurrency limit
+#[derive(Clone)]
+struct RegisteredTool {
+    tool: Arc<dyn Tool>,
+    metadata: ToolMetadata,
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl RegisteredTool {
+    fn new(tool: Arc<dyn Tool>, metadata: ToolMetadata) -> Self {
+        let permits = metadata.max_concurrency.map(|n| Arc::new(Semaphore::new(n)));
+        Self { tool, metadata, permits }
+    }
+}
+
+/// Tool registry with internal locking for thread-safe access
+///
+/// This registry uses async `RwLock` internally to allow multiple concurrent readers
+/// while ensuring exclusive access for writes. Tools are stored as shared handles,
+/// so the lock is only held while looking a tool up and never while it runs.
+///
+/// The registry's filter (from `ToolConfig`) decides which tools can be
+/// registered at all; a task scope further limits which registered tools are
+/// visible and callable while one task runs.
+pub struct ToolRegistry {
+    tools: tokio::sync::RwLock<HashMap<String, RegisteredTool>>,
+    journal: std::sync::RwLock<Option<Arc<ChangeJournal>>>,
+    filter: ToolFilter,
+    scope: std::sync::RwLock<Option<ToolFilter>>,
+    task: std::sync::RwLock<Option<String>>,
+    observer: std::sync::RwLock<Option<Arc<dyn OutputObserver>>>,
+    output: std::sync::RwLock<Option<Arc<OutputProcessor>>>,
+    snapshots: std::sync::RwLock<Option<Arc<dyn SnapshotBackend>>>,
+    /// Snapshots taken before successful calls, per task, oldest first
+    snapshot_ids: std::sync::Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::with_filter(ToolFilter::default())
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry").field("task", &self.task()).finish_non_exhaustive()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry that only accepts tools allowed by `filter`
+    pub fn with_filter(filter: ToolFilter) -> Self {
+        Self {
+            tools: tokio::sync::RwLock::new(HashMap::new()),
+            journal: std::sync::RwLock::new(None),
+            filter,
+            scope: std::sync::RwLock::new(None),
+            task: std::sync::RwLock::new(None),
+            observer: std::sync::RwLock::new(None),
+            output: std::sync::RwLock::new(None),
+            snapshots: std::sync::RwLock::new(None),
+            snapshot_ids: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register a new tool using the metadata it declares
+    ///
+    /// Returns whether the tool was added. This method acquires a write lock,
+    /// so it should be called during initialization rather than in hot paths.
+    pub async fn register<T: Tool + 'static>(&self, tool: T) -> bool {
+        let metadata = tool.metadata();
+        self.register_with_metadata(tool, metadata).await
+    }
+
+    /// Register a new tool, overriding the metadata it declares
+    ///
+    /// Tools the registry's filter does not allow are skipped and `false` is
+    /// returned.
+    pub async fn register_with_metadata<T: Tool + 'static>(&self, tool: T, metadata: ToolMetadata) -> bool {
+        if !self.filter.allows(tool.name()) {
+            tracing::info!("Tool '{}' is disabled by configuration and was not registered", tool.name());
+            return false;
+        }
+        let mut tools = self.tools.write().await;
+        tools.insert(tool.name().to_string(), RegisteredTool::new(Arc::new(tool), metadata));
+        true
+    }
+
+    /// Execute a tool call
+    ///
+    /// The read lock is only held while looking up the tool, so slow tools
+    /// do not block registration. Arguments are validated against the tool's
+    /// parameters, with defaults applied and types coerced, before the tool
+    /// runs. The call waits for a free slot if the tool limits concurrency
+    /// and fails with `ToolError::TimeoutError` if it exceeds its timeout.
+    /// Tools outside the current task scope fail with `PermissionDenied`.
+    /// With a snapshot backend set, the files a tool reports it modifies are
+    /// snapshotted first and restored if the call fails.
+    /// Results are compacted by the output processor, if one is set.
+    pub async fn execute(&self, tool_call: &ToolCall) -> Result<ToolResult, ToolError> {
+        self.check_scope(&tool_call.name)?;
+        let registered = self.lookup(&tool_call.name).await?;
+        let tool = &registered.tool;
+
+        let args = ToolArgs::from_map(validate_args(tool.name(), &tool.parameters(), tool_call.args.as_map())?)
+            .with_task_id(tool_call.args.task_id().map(str::to_string).or_else(|| self.task()))
+            .with_observer(self.output_observer());
+
+        let _permit = match &registered.permits {
+            Some(permits) => Some(permits.clone().acquire_owned().await
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?),
+            None => None,
+        };
+
+        let journal = self.journal();
+        let snapshots = self.snapshot_backend();
+        let modified_paths = match (&journal, &snapshots) {
+            (None, None) => Vec::new(),
+            _ => tool.modified_paths(&args),
+        };
+        if let Some(journal) = &journal {
+            for path in &modified_paths {
+                journal.record_before(path).await
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+            }
+        }
+        let snapshot = match &snapshots {
+            Some(backend) if !modified_paths.is_empty() => {
+                let scope = SnapshotScope {
+                    task_id: args.task_id().unwrap_or(DEFAULT_SNAPSHOT_TASK).to_string(),
+                    step_id: format!("{}-{}", tool_call.name, uuid::Uuid::new_v4()),
+                    step_name: tool_call.name.clone(),
+                };
+                let snapshot_id = backend.create_snapshot(&scope, &modified_paths).await
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                Some((Arc::clone(backend), scope.task_id, snapshot_id))
+            }
+            _ => None,
+        };
+
+        let result = match registered.metadata.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, tool.execute(&args)).await
+                .unwrap_or_else(|_| {
+                    tracing::warn!("Tool '{}' timed out after {:?}", tool_call.name, timeout);
+                    Err(ToolError::TimeoutError)
+                }),
+            None => tool.execute(&args).await,
+        };
+
+        if let Some((backend, task_id, snapshot_id)) = snapshot {
+            if result.is_err() {
+                if let Err(e) = restore_snapshot(backend.as_ref(), &snapshot_id).await {
+                    tracing::error!("Failed to restore snapshot {} after '{}' failed: {}", snapshot_id, tool_call.name, e);
+                }
+            } else {
+                self.snapshot_ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .entry(task_id)
+                    .or_default()
+                    .push(snapshot_id);
+            }
+        }
+        if let Some(journal) = &journal {
+            for path in &modified_paths {
+                if let Err(e) = journal.record_after(path).await {
+                    tracing::warn!("Failed to journal change to {}: {}", path, e);
+                }
+            }
+        }
+
+        match (result, self.output_processor()) {
+            (Ok(result), Some(processor)) => Ok(processor.process(&tool_call.name, args.task_id(), result).await),
+            (result, _) => result,
+        }
+    }
+
+    /// Execute several tool calls, e.g. all calls from one model turn
+    ///
+    /// Consecutive calls to read-only tools run in parallel; a call to a
+    /// mutating (or unknown) tool waits for everything before it and runs on
+    /// its own. Results are returned in the order of `tool_calls`. The
+    /// ordering only holds within the batch, not against other concurrent
+    /// [`execute`](Self::execute) or `execute_batch` calls.
+    pub async fn execute_batch(&self, tool_calls: &[ToolCall]) -> Vec<Result<ToolResult, ToolError>> {
+        let mut results = Vec::with_capacity(tool_calls.len());
+        let mut pending: Vec<&ToolCall> = Vec::new();
+
+        for call in tool_calls {
+            let read_only = self.metadata(&call.name).await.is_some_and(|m| m.read_only);
+            if read_only {
+                pending.push(call);
+                continue;
+            }
+
+            results.extend(self.execute_parallel(&pending).await);
+            pending.clear();
+            results.push(self.execute(call).await);
+        }
+        results.extend(self.execute_parallel(&pending).await);
+
+        results
+    }
+
+    async fn execute_parallel(&self, calls: &[&ToolCall]) -> Vec<Result<ToolResult, ToolError>> {
+        futures::future::join_all(calls.iter().map(|call| self.execute(call))).await
+    }
+
+    async fn lookup(&self, name: &str) -> Result<RegisteredTool, ToolError> {
+        let tools = self.tools.read().await;
+        tools.get(name)
+            .cloned()
+            .ok_or_else(|| ToolError::ToolNotFound(name.to_string()))
+    }
+
+    /// Metadata of a registered tool
+    pub async fn metadata(&self, name: &str) -> Option<ToolMetadata> {
+        let tools = self.tools.read().await;
+        tools.get(name).map(|registered| registered.metadata.clone())
+    }
+
+    /// Parameters of a registered tool
+    pub async fn parameters(&self, name: &str) -> Option<Vec<Parameter>> {
+        let tools = self.tools.read().await;
+        tools.get(name).map(|registered| registered.tool.parameters())
+    }
+
+    /// Record file changes made through this registry in `journal`
+    ///
+    /// Pass `None` to stop journaling, e.g. once a task has finished.
+    pub fn set_journal(&self, journal: Option<Arc<ChangeJournal>>) {
+        *self.journal.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = journal;
+    }
+
+    /// The journal currently recording changes, if any
+    pub fn journal(&self) -> Option<Arc<ChangeJournal>> {
+        self.journal.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Snapshot the files tools modify in `backend`, like the sequential
+    /// executor does for the files a step writes
+    ///
+    /// Pass `None` to stop snapshotting.
+    pub fn set_snapshot_backend(&self, backend: Option<Arc<dyn SnapshotBackend>>) {
+        *self.snapshots.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = backend;
+    }
+
+    /// The backend snapshotting tool changes, if any
+    pub fn snapshot_backend(&self) -> Option<Arc<dyn SnapshotBackend>> {
+        self.snapshots.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Undo the file changes tools made for `task_id`, newest first
+    ///
+    /// Returns the number of calls rolled back. Only calls made while a
+    /// snapshot backend was set can be rolled back.
+    pub async fn rollback_task(&self, task_id: &str) -> Result<usize, ToolError> {
+        let snapshot_ids = self.snapshot_ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(task_id)
+            .unwrap_or_default();
+        let Some(backend) = self.snapshot_backend() else {
+            return Ok(0);
+        };
+        for snapshot_id in snapshot_ids.iter().rev() {
+            restore_snapshot(backend.as_ref(), snapshot_id).await
+                .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        }
+        Ok(snapshot_ids.len())
+    }
+
+    /// Tag calls through this registry with `task_id`
+    pub fn set_task(&self, task_id: Option<String>) {
+        *self.task.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = task_id;
+    }
+
+    /// The task calls are currently tagged with, if any
+    pub fn task(&self) -> Option<String> {
+        self.task.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Forward output that tools emit while running to `observer`
+    pub fn set_output_observer(&self, observer: Option<Arc<dyn OutputObserver>>) {
+        *self.observer.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = observer;
+    }
+
+    /// The observer receiving tool output, if any
+    pub fn output_observer(&self) -> Option<Arc<dyn OutputObserver>> {
+        self.observer.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Compact the results of every call with `processor` before returning them
+    pub fn set_output_processor(&self, processor: Option<Arc<OutputProcessor>>) {
+        *self.output.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = processor;
+    }
+
+    /// The processor compacting results, if any
+    pub fn output_processor(&self) -> Option<Arc<OutputProcessor>> {
+        self.output.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Stop tagging calls with `task_id` and let every tool release its state for it
+    pub async fn finish_task(&self, task_id: &str) {
+        if self.task().as_deref() == Some(task_id) {
+            self.set_task(None);
+        }
+        let tools: Vec<Arc<dyn Tool>> = self.tools.read().await.values().map(|registered| registered.tool.clone()).collect();
+        for tool in tools {
+            tool.task_finished(task_id).await;
+        }
+        self.snapshot_ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(task_id);
+        if let Some(backend) = self.snapshot_backend() {
+            if let Err(e) = backend.finish(task_id).await {
+                tracing::warn!("Failed to clean up snapshots of task {}: {}", task_id, e);
+            }
+        }
+    }
+
+    /// Limit the tools visible and callable to those allowed by `scope`
+    ///
+    /// Pass `None` to lift the limit, e.g. once a task has finished.
+    pub fn set_scope(&self, scope: Option<ToolFilter>) {
+        *self.scope.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = scope;
+    }
+
+    /// Fail with `PermissionDenied` if `name` is outside the current task scope
+    pub fn check_scope(&self, name: &str) -> Result<(), ToolError> {
+        match self.scope.read().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref() {
+            Some(scope) => scope.check(name),
+            None => Ok(()),
+        }
+    }
+
+    /// Definitions of the tools in scope, for `LanguageModel::complete_with_tools`
+    pub async fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = self.registered_definitions().await;
+        definitions.retain(|definition| self.check_scope(&definition.name).is_ok());
+        definitions
+    }
+
+    /// Definitions of every registered tool sorted by name, ignoring the task scope
+    pub async fn registered_definitions(&self) -> Vec<ToolDefinition> {
+        let tools = self.tools.read().await;
+        let mut definitions: Vec<ToolDefinition> = tools.values().map(|registered| registered.tool.definition()).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Get all registered tool names
+    pub async fn get_tool_names(&self) -> Vec<String> {
+        let tools = self.tools.read().await;
+        tools.keys().cloned().collect()
+    }
+
+    /// Get a tool by name (returns a clone of tool info, not the tool itself)
+    pub async fn has_tool(&self, name: &str) -> bool {
+        let tools = self.tools.read().await;
+        tools.contains_key(name)
+    }
+
+    /// Get the number of registered tools
+    pub async fn tool_count(&self) -> usize {
+        let tools = self.tools.read().await;
+        tools.len()
+    }
+}
+
+/// Register the built-in tools; those rejected by the registry's filter are skipped
+///
+/// The process tools share one `ProcessManager`. `read_artifact` is added when
+/// the registry has an output processor and `retrieve_context` when the
+/// workspace index is enabled.
+pub async fn register_builtin_tools(registry: &ToolRegistry, config: &AgentConfig) {
+    registry.register(ReadFileTool).await;
+    registry.register(WriteFileTool).await;
+    registry.register(EditFileTool::new()).await;
+    registry.register(RunCommandTool).await;
+    registry.register(ShellSessionTool::new()).await;
+    let processes = ProcessManager::new();
+    registry.register(ProcessStartTool::new(processes.clone())).await;
+    registry.register(ProcessReadTool::new(processes.clone())).await;
+    registry.register(ProcessSignalTool::new(processes.clone())).await;
+    registry.register(ProcessStopTool::new(processes)).await;
+    if let Some(processor) = registry.output_processor() {
+        registry.register(ReadArtifactTool::new(processor.artifacts()).with_max_chars(processor.max_chars())).await;
+    }
+    if config.execution.index.enabled {
+        registry.register(RetrieveContextTool::new(Arc::new(ContextRetriever::from_config(&config.execution.index)))).await;
+    }
+    registry.register(ListFilesTool).await;
+    registry.register(SearchTool).await;
+    registry.register(FindFilesTool).await;
+    registry.register(GitStatusTool).await;
+    registry.register(GitDiffTool).await;
+    registry.register(GitLogTool).await;
+    registry.register(GitBlameTool).await;
+    registry.register(GitCheckoutTool::new()).await;
+    registry.register(GitCommitTool::new()).await;
+    registry.register(CargoTool::new()).await;
+}
+
+// Basic tool implementations
+
+/// Lines returned by `read_file` in head/tail mode when no limit is given
+const READ_FILE_HEAD_TAIL_LINES: usize = 50;
+
+/// Read file tool
+///
+/// Reads a line range (or the head/tail) of a file with line numbers. Output
+/// larger than the resource limit is truncated with a notice, and binary files
+/// are rejected with a hex preview.
+pub struct ReadFileTool;
+
+#[async_trait]
+impl Tool for ReadFileTool {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read the contents of a file, optionally a line range (offset/limit) or its head/tail, \
+         with line numbers and the total line count"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("path", "File path to read"),
+            Parameter::optional("offset", "First line to read, starting at 1")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None),
+            Parameter::optional("limit", "Maximum number of lines to read")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None),
+            Parameter::optional("mode", "Read a line range, or only the first/last lines")
+                .with_enum(["range", "head", "tail"])
+                .with_default(serde_json::json!("range")),
+            Parameter::optional("line_numbers", "Prefix each line with its line number")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(true)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        use crate::execution::{read_file_range, ReadRange};
+
+        let path = args.get_string("path")?;
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let limit = args.get_i64("limit").ok().map(|limit| limit as usize);
+        let range = match args.get_string_or("mode", "range").as_str() {
+            "head" => ReadRange::Head(limit.unwrap_or(READ_FILE_HEAD_TAIL_LINES)),
+            "tail" => ReadRange::Tail(limit.unwrap_or(READ_FILE_HEAD_TAIL_LINES)),
+            _ => ReadRange::Lines { offset: args.get_i64_or("offset", 1) as usize, limit },
+        };
+
+        let slice = read_file_range(&path, range, &crate::security::ResourceLimits::default())
+            .await
+            .map_err(|e| match e {
+                crate::errors::AgentError::ToolError(error) => error,
+                other => ToolError::ExecutionError(other.to_string()),
+            })?;
+
+        let mut content = if args.get_bool_or("line_numbers", true) { slice.numbered() } else { slice.content() };
+        let summary = if slice.lines.is_empty() {
+            format!("{}: no lines read ({} lines in file)", path, slice.total_lines)
+        } else {
+            format!("{}: lines {}-{} of {}", path, slice.start_line, slice.end_line(), slice.total_lines)
+        };
+        let read_whole_file = slice.start_line == 1 && slice.lines.len() == slice.total_lines;
+        if !read_whole_file || !slice.notices.is_empty() {
+            content.push_str(&format!("\n[{}]", summary));
+            for notice in &slice.notices {
+                content.push_str(&format!("\n[{}]", notice));
+            }
+        }
+
+        let mut result = ToolResult::text(content);
+        result.summary = summary;
+        result.data = Some(serde_json::json!({
+            "path": path,
+            "start_line": slice.start_line,
+            "end_line": slice.end_line(),
+            "total_lines": slice.total_lines,
+            "truncated": slice.truncated,
+            "notices": slice.notices,
+        }));
+        Ok(result)
+    }
+}
+
+/// Write file tool
+pub struct WriteFileTool;
+
+#[async_trait]
+impl Tool for WriteFileTool {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        "Write content to a file"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("path", "File path to write"),
+            Parameter::required("content", "Content to write"),
+        ]
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string("path")?;
+        let content = args.get_string("content")?;
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        tokio::fs::write(path, content)
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        Ok(ToolResult::text("File written successfully".to_string()))
+    }
+
+    fn modified_paths(&self, args: &ToolArgs) -> Vec<String> {
+        args.get_string("path").into_iter().collect()
+    }
+}
+
+/// List files tool
+pub struct ListFilesTool;
+
+#[async_trait]
+impl Tool for ListFilesTool {
+    fn name(&self) -> &str {
+        "list_files"
+    }
+
+    fn description(&self) -> &str {
+        "List files and directories in a given path"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("path", "Directory path to list")
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string("path")?;
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(path)
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        while let Some(entry) = dir.next_entry().await.map_err(|e| ToolError::ExecutionError(e.to_string()))? {
+            let metadata = std::fs::metadata(entry.path()).ok();
+            entries.push((
+                entry.file_name().to_string_lossy().to_string(),
+                metadata.map(|m| m.is_dir()).unwrap_or(false)
+            ));
+        }
+
+        entries.sort_by(|a, b| {
+            // Directories first, then files
+            match (a.1, b.1) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => a.0.cmp(&b.0),
+            }
+        });
+
+        let list_text = entries.iter()
+            .map(|(name, is_dir)| {
+                let prefix = if *is_dir { "DIR  " } else { "FILE " };
+                format!("{}{}", prefix, name)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(ToolResult::text(list_text))
+    }
+}
+
+/// Default limit for a single `run_command` invocation
+const RUN_COMMAND_TIMEOUT_SECS: u64 = 300;
+
+/// Run command tool
+pub struct RunCommandTool;
+
+#[async_trait]
+impl Tool for RunCommandTool {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        "Execute a shell command"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("command", "Command to execute"),
+            Parameter::optional("working_dir", "Working directory").with_default(serde_json::json!(".")),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_timeout(Duration::from_secs(RUN_COMMAND_TIMEOUT_SECS))
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let command = args.get_string("command")?;
+        let working_dir = args.get_string_or("working_dir", ".");
+
+        // Safety checks for dangerous commands
+        let dangerous_commands = vec![
+            "rm -rf /", "format", "fdisk", "dd if=", "shutdown", "reboot",
+        ];
+
+        for dangerous in &dangerous_commands {
+            if command.contains(dangerous) {
+                return Err(ToolError::PermissionDenied(format!("Command '{}' is not allowed", dangerous)));
+            }
+        }
+
+        let limits = ResourceLimits {
+            max_execution_time: Duration::from_secs(RUN_COMMAND_TIMEOUT_SECS),
+            ..ResourceLimits::default()
+        };
+        let output = stream_command(&command, Some(Path::new(&working_dir)), &limits, |line| {
+            args.emit_output(self.name(), line)
+        })
+        .await
+        .map_err(|e| match e {
+            CommandOperationError::Timeout { .. } => ToolError::TimeoutError,
+            e => ToolError::ExecutionError(e.to_string()),
+        })?;
+
+        let mut result = if output.exit_code == Some(0) {
+            ToolResult::text(output.stdout)
+        } else {
+            ToolResult::error(output.stderr)
+        };
+        result.data = Some(serde_json::json!({
+            "exit_code": output.exit_code,
+            "truncated": output.truncated,
+        }));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Sleeps for `delay_ms`, tracking the peak number of overlapping calls
+    struct SlowTool {
+        name: &'static str,
+        delay_ms: u64,
+        running: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+        metadata: ToolMetadata,
+    }
+
+    impl SlowTool {
+        fn new(name: &'static str, delay_ms: u64, metadata: ToolMetadata) -> Self {
+            Self {
+                name,
+                delay_ms,
+                running: Arc::new(AtomicUsize::new(0)),
+                peak: Arc::new(AtomicUsize::new(0)),
+                metadata,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "Sleep for a while"
+        }
+
+        fn parameters(&self) -> Vec<Parameter> {
+            Vec::new()
+        }
+
+        fn metadata(&self) -> ToolMetadata {
+            self.metadata.clone()
+        }
+
+        async fn execute(&self, _args: &ToolArgs) -> Result<ToolResult, ToolError> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(ToolResult::text(self.name.to_string()))
+        }
+    }
+
+    fn call(name: &str) -> ToolCall {
+        ToolCall { name: name.to_string(), args: ToolArgs::from_map(HashMap::new()) }
+    }
+
+    #[tokio::test]
+    async fn test_read_file_tool_line_range() {
+        let path = "test_temp_read_tool.txt";
+        std::fs::write(path, "a\nb\nc\nd\n").unwrap();
+
+        let args = |value: serde_json::Value| ToolArgs::from_map(serde_json::from_value(value).unwrap());
+        let result = ReadFileTool.execute(&args(serde_json::json!({"path": path, "offset": 2, "limit": 2}))).await.unwrap();
+        assert_eq!(result.content, format!("2\tb\n3\tc\n[{}: lines 2-3 of 4]", path));
+        assert_eq!(result.data.as_ref().unwrap()["total_lines"], 4);
+
+        let result = ReadFileTool
+            .execute(&args(serde_json::json!({"path": path, "mode": "tail", "limit": 1, "line_numbers": false})))
+            .await
+            .unwrap();
+        assert!(result.content.starts_with("d\n["));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_register_not_blocked_by_running_tool() {
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(SlowTool::new("slow", 500, ToolMetadata::mutating())).await;
+
+        let running = {
+            let registry = registry.clone();
+            tokio::spawn(async move { registry.execute(&call("slow")).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        tokio::time::timeout(Duration::from_millis(200), registry.register(ReadFileTool))
+            .await
+            .expect("register should not wait for the running tool");
+        assert!(registry.has_tool("read_file").await);
+        assert!(running.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_concurrency_limit() {
+        let registry = ToolRegistry::new();
+        registry.register(SlowTool::new(
+            "hang",
+            5_000,
+            ToolMetadata::mutating().with_timeout(Duration::from_millis(50)),
+        )).await;
+        assert!(matches!(registry.execute(&call("hang")).await, Err(ToolError::TimeoutError)));
+
+        let limited = SlowTool::new("limited", 50, ToolMetadata::read_only().with_max_concurrency(1));
+        let peak = limited.peak.clone();
+        registry.register(limited).await;
+        let results = registry.execute_batch(&[call("limited"), call("limited"), call("limited")]).await;
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_runs_read_only_calls_in_parallel() {
+        let registry = ToolRegistry::new();
+        let reader = SlowTool::new("reader", 100, ToolMetadata::read_only());
+        let reader_peak = reader.peak.clone();
+        registry.register(reader).await;
+        registry.register(SlowTool::new("writer", 10, ToolMetadata::mutating())).await;
+
+        let calls = [call("reader"), call("reader"), call("reader"), call("writer"), call("reader"), call("missing")];
+        let results = registry.execute_batch(&calls).await;
+
+        assert_eq!(results.len(), calls.len());
+        assert_eq!(reader_peak.load(Ordering::SeqCst), 3);
+        assert_eq!(results[3].as_ref().unwrap().content, "writer");
+        assert!(matches!(results[5], Err(ToolError::ToolNotFound(_))));
+        assert_eq!(registry.metadata("reader").await, Some(ToolMetadata::read_only()));
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_task_scope() {
+        let registry = ToolRegistry::with_filter(ToolFilter::new(vec![], vec!["run_command".to_string()]));
+        registry.register(RunCommandTool).await;
+        registry.register(ReadFileTool).await;
+        registry.register(SlowTool::new("slow", 1, ToolMetadata::read_only())).await;
+        assert!(!registry.has_tool("run_command").await);
+
+        registry.set_scope(Some(ToolFilter::allow_only(["slow"])));
+        let names: Vec<String> = registry.definitions().await.into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["slow"]);
+        let error = registry.execute(&call("read_file")).await.unwrap_err();
+        assert!(matches!(&error, ToolError::PermissionDenied(message) if message.contains("allowed tools: slow")));
+        assert!(registry.execute(&call("slow")).await.is_ok());
+
+        registry.set_scope(None);
+        assert_eq!(registry.definitions().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_builtin_tools_follow_the_filter() {
+        let registry = ToolRegistry::with_filter(ToolFilter::new(vec![], vec!["git_*".to_string()]));
+        register_builtin_tools(&registry, &AgentConfig::default()).await;
+
+        let definitions = registry.registered_definitions().await;
+        let names: Vec<&str> = definitions.iter().map(|d| d.name.as_str()).collect();
+        for name in ["shell_session", "process_start", "process_stop", "retrieve_context", "cargo"] {
+            assert!(names.contains(&name), "{} missing from {:?}", name, names);
+        }
+        assert!(!names.iter().any(|name| name.starts_with("git_")));
+        let read_file = definitions.iter().find(|d| d.name == "read_file").unwrap();
+        assert_eq!(read_file.parameters["type"], "object");
+        assert!(read_file.parameters["properties"].get("path").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_run_command_streams_to_observer() {
+        let registry = ToolRegistry::new();
+        registry.register(RunCommandTool).await;
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        registry.set_output_observer(Some(Arc::new(move |task: Option<&str>, tool: &str, line: &OutputLine| {
+            sink.lock().unwrap().push(format!("{:?} {} {:?} {}", task, tool, line.stream, line.line));
+        })));
+        registry.set_task(Some("t1".to_string()));
+
+        let mut args = HashMap::new();
+        args.insert("command".to_string(), serde_json::json!("echo hi; echo oops >&2; exit 2"));
+        let result = registry.execute(&ToolCall { name: "run_command".to_string(), args: ToolArgs::from_map(args) }).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("oops\n"));
+        assert_eq!(result.data.unwrap()["exit_code"], 2);
+
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, vec![
+            "Some(\"t1\") run_command Stderr oops".to_string(),
+            "Some(\"t1\") run_command Stdout hi".to_string(),
+        ]);
+    }
+
+    /// Overwrites `path`, then fails
+    struct WriteThenFail;
+
+    #[async_trait]
+    impl Tool for WriteThenFail {
+        fn name(&self) -> &str {
+            "write_then_fail"
+        }
+
+        fn description(&self) -> &str {
+            "Write a file, then fail"
+        }
+
+        fn parameters(&self) -> Vec<Parameter> {
+            vec![Parameter::required("path", "File to overwrite")]
+        }
+
+        async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+            std::fs::write(args.get_string("path")?, "partial").unwrap();
+            Err(ToolError::ExecutionError("failed halfway".to_string()))
+        }
+
+        fn modified_paths(&self, args: &ToolArgs) -> Vec<String> {
+            args.get_string("path").into_iter().collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_changes_are_snapshotted() {
+        use crate::execution::FileSnapshotStore;
+        use crate::test_support::TempDir;
+
+        let dir = TempDir::new("registry_snapshots");
+        let registry = ToolRegistry::new();
+        registry.register(EditFileTool::new()).await;
+        registry.register(WriteThenFail).await;
+        registry.set_snapshot_backend(Some(Arc::new(FileSnapshotStore::new(dir.join("snapshots")))));
+        registry.set_task(Some("task-s".to_string()));
+
+        // edit_file only accepts relative paths
+        let path = format!("target/registry_snapshot_{}.txt", uuid::Uuid::new_v4());
+        std::fs::write(&path, "fn a() {}\n").unwrap();
+        let call = |name: &str, args: serde_json::Value| ToolCall {
+            name: name.to_string(),
+            args: ToolArgs::from_map(serde_json::from_value(args).unwrap()),
+        };
+
+        registry.execute(&call("edit_file", serde_json::json!({
+            "path": path,
+            "edits": [{"search": "fn a() {}", "replace": "fn b() {}"}],
+        }))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn b() {}\n");
+
+        // A failed call leaves the file as it was
+        assert!(registry.execute(&call("write_then_fail", serde_json::json!({"path": path}))).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn b() {}\n");
+
+        assert_eq!(registry.rollback_task("task-s").await.unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn a() {}\n");
+        assert_eq!(registry.rollback_task("task-s").await.unwrap(), 0);
+
+        registry.finish_task("task-s").await;
+        std::fs::remove_file(&path).unwrap();
+    }
+}
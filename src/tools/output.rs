@@ -0,0 +1,503 @@
+//! Tool output compaction
+//!
+//! Tool results go back to the model verbatim, so a full file, an `ls -R`
+//! or a test log can eat most of its context. An [`OutputProcessor`] set on
+//! the registry post-processes every result: output over the configured
+//! size is stored as an artifact, and the model gets a compact version
+//! instead — repeated lines collapsed, then either an LLM summary or the
+//! first and last lines around an elision marker naming the artifact. The
+//! model pages through the full output with `read_artifact`.
+
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use crate::config::ToolOutputConfig;
+use crate::errors::ToolError;
+use crate::models::LanguageModel;
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Name of the tool that pages through artifacts; its own output is never compacted
+pub const READ_ARTIFACT_TOOL: &str = "read_artifact";
+
+/// Lines `read_artifact` returns unless asked for more
+const DEFAULT_ARTIFACT_PAGE_LINES: i64 = 200;
+
+// ============================================================================
+// Artifacts
+// ============================================================================
+
+/// Full output of a tool call that was compacted
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub id: String,
+    pub tool: String,
+    pub task_id: Option<String>,
+    pub content: String,
+}
+
+impl Artifact {
+    pub fn line_count(&self) -> usize {
+        self.content.lines().count()
+    }
+}
+
+/// The most recent artifacts, oldest evicted first
+#[derive(Debug)]
+pub struct ArtifactStore {
+    artifacts: Mutex<VecDeque<Arc<Artifact>>>,
+    capacity: usize,
+    next_id: Mutex<u64>,
+}
+
+impl ArtifactStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            artifacts: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            next_id: Mutex::new(1),
+        }
+    }
+
+    /// Store `content` and return its id
+    pub fn insert(&self, tool: &str, task_id: Option<&str>, content: String) -> String {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            let id = format!("artifact-{}", *next_id);
+            *next_id += 1;
+            id
+        };
+        let mut artifacts = self.artifacts.lock().unwrap();
+        if artifacts.len() >= self.capacity {
+            artifacts.pop_front();
+        }
+        artifacts.push_back(Arc::new(Artifact {
+            id: id.clone(),
+            tool: tool.to_string(),
+            task_id: task_id.map(str::to_string),
+            content,
+        }));
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Artifact>> {
+        self.artifacts.lock().unwrap().iter().find(|artifact| artifact.id == id).cloned()
+    }
+
+    /// Drop the artifacts of a finished task
+    pub fn remove_task(&self, task_id: &str) {
+        self.artifacts.lock().unwrap().retain(|artifact| artifact.task_id.as_deref() != Some(task_id));
+    }
+
+    pub fn len(&self) -> usize {
+        self.artifacts.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ============================================================================
+// Summarization
+// ============================================================================
+
+/// Condenses large tool output, typically with an LLM
+#[async_trait]
+pub trait OutputSummarizer: Send + Sync {
+    async fn summarize(&self, tool: &str, output: &str) -> Result<String, ToolError>;
+}
+
+/// Summarizes with a language model
+pub struct ModelSummarizer {
+    model: Arc<dyn LanguageModel>,
+    /// Characters of output sent to the model at most
+    max_input_chars: usize,
+}
+
+impl ModelSummarizer {
+    pub fn new(model: Arc<dyn LanguageModel>) -> Self {
+        Self { model, max_input_chars: 100_000 }
+    }
+
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+}
+
+#[async_trait]
+impl OutputSummarizer for ModelSummarizer {
+    async fn summarize(&self, tool: &str, output: &str) -> Result<String, ToolError> {
+        let half = self.max_input_chars / 2;
+        let input = if output.len() > self.max_input_chars {
+            format!("{}\n[...]\n{}", prefix(output, half), suffix(output, half))
+        } else {
+            output.to_string()
+        };
+        let prompt = format!(
+            "Summarize the following output of the `{}` tool for a coding agent. Keep errors, warnings, \
+             failing test names, file paths and line numbers verbatim; drop repetitive progress lines. \
+             Reply with the summary only.\n\n```\n{}\n```",
+            tool, input
+        );
+        let response = self
+            .model
+            .complete(&prompt)
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Summarizing {} output failed: {}", tool, e)))?;
+        Ok(response.content.trim().to_string())
+    }
+}
+
+// ============================================================================
+// Processor
+// ============================================================================
+
+/// Compacts oversized tool results before they reach the model
+pub struct OutputProcessor {
+    config: ToolOutputConfig,
+    artifacts: Arc<ArtifactStore>,
+    summarizer: Option<Arc<dyn OutputSummarizer>>,
+}
+
+impl OutputProcessor {
+    pub fn new(config: ToolOutputConfig) -> Self {
+        Self {
+            artifacts: Arc::new(ArtifactStore::new(config.max_artifacts)),
+            config,
+            summarizer: None,
+        }
+    }
+
+    /// Summarize output longer than `summarize_above_chars` with `summarizer`
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn OutputSummarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Where full outputs are kept, for [`ReadArtifactTool`]
+    pub fn artifacts(&self) -> Arc<ArtifactStore> {
+        Arc::clone(&self.artifacts)
+    }
+
+    /// Output size above which results are compacted
+    pub fn max_chars(&self) -> usize {
+        self.config.max_chars
+    }
+
+    /// Compact the content and error text of `result` if they are too long
+    pub async fn process(&self, tool: &str, task_id: Option<&str>, mut result: ToolResult) -> ToolResult {
+        if !self.config.enabled || tool == READ_ARTIFACT_TOOL {
+            return result;
+        }
+        if let Some(content) = self.compact(tool, task_id, &result.content).await {
+            if result.summary == result.content {
+                result.summary = content.clone();
+            }
+            result.content = content;
+        }
+        if let Some(error) = &result.error {
+            if let Some(compacted) = self.compact(tool, task_id, error).await {
+                if result.summary == *error {
+                    result.summary = compacted.clone();
+                }
+                result.error = Some(compacted);
+            }
+        }
+        result
+    }
+
+    /// The compact form of `text`, or `None` if it fits as is
+    async fn compact(&self, tool: &str, task_id: Option<&str>, text: &str) -> Option<String> {
+        if text.len() <= self.config.max_chars {
+            return None;
+        }
+        let total_lines = text.lines().count();
+        let id = self.artifacts.insert(tool, task_id, text.to_string());
+        let pointer = format!(
+            "full output ({} lines, {} bytes) is artifact {}; page through it with {}",
+            total_lines,
+            text.len(),
+            id,
+            READ_ARTIFACT_TOOL
+        );
+
+        let text = if self.config.dedupe_lines { dedupe_lines(text) } else { text.to_string() };
+        if text.len() <= self.config.max_chars {
+            return Some(format!("{}\n[repeated lines collapsed; {}]", text.trim_end(), pointer));
+        }
+
+        if let (Some(threshold), Some(summarizer)) = (self.config.summarize_above_chars, &self.summarizer) {
+            if text.len() > threshold {
+                match summarizer.summarize(tool, &text).await {
+                    Ok(summary) => return Some(format!("{}\n[summary of the output; {}]", summary, pointer)),
+                    Err(e) => tracing::warn!("Falling back to truncation: {}", e),
+                }
+            }
+        }
+        Some(head_tail(&text, self.config.head_lines, self.config.tail_lines, self.config.max_chars, &pointer))
+    }
+}
+
+/// Collapse runs of identical lines into one line and a repeat count
+pub fn dedupe_lines(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let mut repeats = 0;
+        while lines.peek() == Some(&line) {
+            lines.next();
+            repeats += 1;
+        }
+        output.push_str(line);
+        output.push('\n');
+        if repeats > 0 {
+            output.push_str(&format!("[previous line repeated {} more times]\n", repeats));
+        }
+    }
+    output
+}
+
+/// The first `head` and last `tail` lines around an elision marker, within `max_chars`
+pub fn head_tail(text: &str, head: usize, tail: usize, max_chars: usize, pointer: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let head = head.min(lines.len());
+    let tail = tail.min(lines.len() - head);
+    let mut head_text = lines[..head].join("\n");
+    let mut tail_text = lines[lines.len() - tail..].join("\n");
+
+    // Long lines can still blow the budget; cut by characters as well
+    let half = max_chars / 2;
+    if head_text.len() + tail_text.len() > max_chars {
+        let tail_budget = half.min(tail_text.len());
+        let head_budget = max_chars - tail_budget;
+        head_text = prefix(&head_text, head_budget).to_string();
+        tail_text = suffix(&tail_text, tail_budget).to_string();
+    }
+
+    let elided = text.len() - head_text.len() - tail_text.len();
+    let marker = format!("[... {} bytes elided; {} ...]", elided, pointer);
+    if tail_text.is_empty() {
+        format!("{}\n{}", head_text, marker)
+    } else {
+        format!("{}\n{}\n{}", head_text, marker, tail_text)
+    }
+}
+
+/// At most `max` bytes from the start, cut at a char boundary
+fn prefix(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// At most `max` bytes from the end, cut at a char boundary
+fn suffix(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+// ============================================================================
+// read_artifact
+// ============================================================================
+
+/// Pages through the full output of a compacted tool call
+///
+/// Its output is not compacted, so a page stops at `max_chars` bytes even
+/// if fewer than `limit` lines were read.
+pub struct ReadArtifactTool {
+    artifacts: Arc<ArtifactStore>,
+    max_chars: usize,
+}
+
+impl ReadArtifactTool {
+    pub fn new(artifacts: Arc<ArtifactStore>) -> Self {
+        Self { artifacts, max_chars: ToolOutputConfig::default().max_chars }
+    }
+
+    /// Use the compaction threshold of the processor the artifacts come from
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars.max(1);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for ReadArtifactTool {
+    fn name(&self) -> &str {
+        READ_ARTIFACT_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Read a line range of the full output of an earlier tool call that was shortened, \
+         by the artifact id named in its elision marker"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("artifact_id", "Artifact id, e.g. artifact-3"),
+            Parameter::optional("offset", "First line to read, starting at 1")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(1)),
+            Parameter::optional("limit", "Maximum number of lines to read")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(DEFAULT_ARTIFACT_PAGE_LINES)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let id = args.get_string("artifact_id")?;
+        let artifact = self
+            .artifacts
+            .get(&id)
+            .ok_or_else(|| ToolError::InvalidParameters(format!("No artifact {} (it may have expired)", id)))?;
+        let offset = args.get_i64_or("offset", 1).max(1) as usize;
+        let limit = args.get_i64_or("limit", DEFAULT_ARTIFACT_PAGE_LINES).max(1) as usize;
+
+        let total = artifact.line_count();
+        let mut lines: Vec<&str> = Vec::new();
+        let mut size = 0;
+        let mut truncated = false;
+        for line in artifact.content.lines().skip(offset - 1).take(limit) {
+            if size + line.len() + 1 > self.max_chars {
+                // A single line over the budget is cut rather than skipped
+                if lines.is_empty() {
+                    lines.push(prefix(line, self.max_chars));
+                }
+                truncated = true;
+                break;
+            }
+            size += line.len() + 1;
+            lines.push(line);
+        }
+        let end = offset - 1 + lines.len();
+        let summary = if lines.is_empty() {
+            format!("{}: no lines read ({} lines)", id, total)
+        } else if truncated {
+            format!(
+                "{}: lines {}-{} of {}, cut at {} bytes; continue with offset {}",
+                id, offset, end, total, self.max_chars, end + 1
+            )
+        } else {
+            format!("{}: lines {}-{} of {}", id, offset, end, total)
+        };
+
+        let mut content = lines.join("\n");
+        content.push_str(&format!("\n[{}]", summary));
+        let mut result = ToolResult::text(content);
+        result.summary = summary;
+        result.data = Some(serde_json::json!({
+            "artifact_id": id,
+            "tool": artifact.tool,
+            "start_line": offset,
+            "end_line": end,
+            "total_lines": total,
+            "truncated": truncated,
+        }));
+        Ok(result)
+    }
+
+    async fn task_finished(&self, task_id: &str) {
+        self.artifacts.remove_task(task_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_chars: usize) -> ToolOutputConfig {
+        ToolOutputConfig { max_chars, head_lines: 2, tail_lines: 2, ..ToolOutputConfig::default() }
+    }
+
+    struct FixedSummarizer;
+
+    #[async_trait]
+    impl OutputSummarizer for FixedSummarizer {
+        async fn summarize(&self, tool: &str, output: &str) -> Result<String, ToolError> {
+            Ok(format!("{} wrote {} lines", tool, output.lines().count()))
+        }
+    }
+
+    fn args_for(id: &str, offset: i64, limit: i64) -> ToolArgs {
+        ToolArgs::from_map(serde_json::from_value(serde_json::json!({
+            "artifact_id": id, "offset": offset, "limit": limit,
+        })).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_head_tail_and_artifact_paging() {
+        let processor = OutputProcessor::new(config(60));
+        let text: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+
+        let small = processor.process("run_command", None, ToolResult::text("short".to_string())).await;
+        assert_eq!(small.content, "short");
+        assert!(processor.artifacts().is_empty());
+
+        let result = processor.process("run_command", Some("t"), ToolResult::text(text.clone())).await;
+        assert!(result.content.starts_with("line 1\nline 2\n[... "));
+        assert!(result.content.ends_with("artifact artifact-1; page through it with read_artifact ...]\nline 49\nline 50"));
+        assert_eq!(result.summary, result.content);
+
+        let read = ReadArtifactTool::new(processor.artifacts());
+        let args = args_for("artifact-1", 10, 2);
+        let page = read.execute(&args).await.unwrap();
+        assert_eq!(page.content, "line 10\nline 11\n[artifact-1: lines 10-11 of 50]");
+
+        // Pages stay within the processor's size limit
+        let narrow = ReadArtifactTool::new(processor.artifacts()).with_max_chars(20);
+        let page = narrow.execute(&args_for("artifact-1", 10, 200)).await.unwrap();
+        assert_eq!(page.content, "line 10\nline 11\n[artifact-1: lines 10-11 of 50, cut at 20 bytes; continue with offset 12]");
+        assert_eq!(page.data.unwrap()["truncated"], true);
+
+        read.task_finished("t").await;
+        assert!(read.execute(&args).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_and_summarize() {
+        let processor = OutputProcessor::new(config(200));
+        let log = format!("start\n{}done\n", "retrying connection\n".repeat(30));
+        let result = processor.process("run_command", None, ToolResult::error(log)).await;
+        assert_eq!(
+            result.error.as_deref().unwrap().lines().take(4).collect::<Vec<_>>(),
+            vec!["start", "retrying connection", "[previous line repeated 29 more times]", "done"]
+        );
+        assert!(result.error.unwrap().contains("artifact artifact-1"));
+
+        let processor = OutputProcessor::new(ToolOutputConfig { summarize_above_chars: Some(100), ..config(60) })
+            .with_summarizer(Arc::new(FixedSummarizer));
+        let text: String = (1..=50).map(|i| format!("test {} ok\n", i)).collect();
+        let result = processor.process("cargo", None, ToolResult::text(text)).await;
+        assert!(result.content.starts_with("cargo wrote 50 lines\n[summary of the output; full output (50 lines"));
+    }
+
+    #[tokio::test]
+    async fn test_artifact_page_cuts_an_oversized_line() {
+        let processor = OutputProcessor::new(config(60));
+        processor.process("run_command", None, ToolResult::text("x".repeat(500))).await;
+
+        let read = ReadArtifactTool::new(processor.artifacts()).with_max_chars(100);
+        let page = read.execute(&args_for("artifact-1", 1, 10)).await.unwrap();
+        assert!(page.content.starts_with(&format!("{}\n[artifact-1: lines 1-1 of 1, cut", "x".repeat(100))));
+    }
+
+    #[test]
+    fn test_head_tail_cuts_long_lines() {
+        let text = format!("{}\n{}", "a".repeat(500), "b".repeat(500));
+        let compact = head_tail(&text, 1, 1, 100, "see artifact-1");
+        assert!(compact.starts_with(&format!("{}\n[... 901 bytes elided", "a".repeat(50))));
+        assert!(compact.ends_with(&"b".repeat(50)));
+    }
+}
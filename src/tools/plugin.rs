@@ -0,0 +1,422 @@
+//! External executable tools
+//!
+//! In-house tools can be added without forking the crate: every `*.json`,
+//! `*.yaml` or `*.yml` manifest in `ToolConfig.custom_tools_path` describes a
+//! tool backed by an executable.
+//!
+//! ```yaml
+//! name: ticket_lookup
+//! description: Look up a ticket in the issue tracker
+//! executable: ./ticket_lookup.py   # relative to the manifest
+//! args: ["--json"]                 # optional extra arguments
+//! timeout_secs: 30                 # default 60
+//! risk_level: safe                 # safe, low, medium (default), high, critical
+//! parameters:
+//!   - name: key
+//!     type: string
+//!     description: Ticket key
+//!     required: true
+//! ```
+//!
+//! The executable receives the validated arguments as a JSON object on stdin
+//! and prints a JSON result on stdout: either
+//! `{"content": "...", "summary": "...", "data": ..., "error": "..."}` (all
+//! fields optional) or any other JSON value, which becomes the result data.
+//! A non-zero exit status is reported as an execution error with stderr.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use crate::config::ToolConfig;
+use crate::errors::ToolError;
+use crate::execution::guardrails::{GuardrailEngine, OperationRiskLevel, OperationTarget};
+use super::{check_guardrails, Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolRegistry, ToolResult};
+
+/// Timeout for plugins that do not set `timeout_secs`
+const DEFAULT_PLUGIN_TIMEOUT_SECS: u64 = 60;
+
+/// Tool manifest as written in the plugin directory
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub description: String,
+    /// Path of the executable, relative to the manifest's directory
+    pub executable: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub parameters: Vec<ManifestParameter>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_risk_level")]
+    pub risk_level: String,
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_PLUGIN_TIMEOUT_SECS
+}
+
+fn default_risk_level() -> String {
+    "medium".to_string()
+}
+
+/// A parameter in a manifest, in JSON Schema terms
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestParameter {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type", default = "default_parameter_type")]
+    pub parameter_type: ParameterType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+    #[serde(rename = "enum", default)]
+    pub enum_values: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    pub minimum: Option<f64>,
+    #[serde(default)]
+    pub maximum: Option<f64>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub items: Option<Box<ManifestParameter>>,
+    #[serde(default)]
+    pub properties: Vec<ManifestParameter>,
+}
+
+fn default_parameter_type() -> ParameterType {
+    ParameterType::String
+}
+
+impl From<&ManifestParameter> for Parameter {
+    fn from(manifest: &ManifestParameter) -> Self {
+        let mut parameter = Parameter::new(&manifest.name, &manifest.description, manifest.parameter_type, manifest.required);
+        parameter.default_value = manifest.default.clone();
+        parameter.enum_values = manifest.enum_values.clone();
+        parameter.minimum = manifest.minimum;
+        parameter.maximum = manifest.maximum;
+        parameter.pattern = manifest.pattern.clone();
+        parameter.items = manifest.items.as_deref().map(|items| Box::new(Parameter::from(items)));
+        parameter.properties = manifest.properties.iter().map(Parameter::from).collect();
+        parameter
+    }
+}
+
+impl PluginManifest {
+    /// Parse a manifest file (JSON or YAML by extension)
+    pub fn load(path: &Path) -> Result<Self, ToolError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read {}: {}", path.display(), e)))?;
+        let invalid = |e: String| ToolError::InvalidParameters(format!("Invalid tool manifest {}: {}", path.display(), e));
+        let manifest: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            _ => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+        };
+
+        if manifest.name.is_empty() || !manifest.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(invalid(format!("tool name '{}' must be non-empty and use only letters, digits, '_' and '-'", manifest.name)));
+        }
+        manifest.risk().map_err(invalid)?;
+        Ok(manifest)
+    }
+
+    /// Declared risk level
+    pub fn risk(&self) -> Result<OperationRiskLevel, String> {
+        match self.risk_level.to_lowercase().as_str() {
+            "safe" => Ok(OperationRiskLevel::Safe),
+            "low" => Ok(OperationRiskLevel::Low),
+            "medium" => Ok(OperationRiskLevel::Medium),
+            "high" => Ok(OperationRiskLevel::High),
+            "critical" => Ok(OperationRiskLevel::Critical),
+            other => Err(format!("unknown risk level '{}'", other)),
+        }
+    }
+}
+
+/// A tool backed by an external executable
+pub struct PluginTool {
+    manifest: PluginManifest,
+    parameters: Vec<Parameter>,
+    executable: PathBuf,
+    risk: OperationRiskLevel,
+    guardrails: GuardrailEngine,
+}
+
+impl PluginTool {
+    /// Load the tool described by the manifest at `path`
+    pub fn from_manifest(path: &Path) -> Result<Self, ToolError> {
+        let manifest = PluginManifest::load(path)?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        let executable = base.join(&manifest.executable);
+        if !executable.is_file() {
+            return Err(ToolError::InvalidParameters(format!(
+                "Executable of tool '{}' not found: {}",
+                manifest.name,
+                executable.display()
+            )));
+        }
+
+        Ok(Self {
+            parameters: manifest.parameters.iter().map(Parameter::from).collect(),
+            risk: manifest.risk().map_err(ToolError::InvalidParameters)?,
+            executable,
+            manifest,
+            guardrails: GuardrailEngine::default(),
+        })
+    }
+
+    pub fn with_guardrails(mut self, guardrails: GuardrailEngine) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    pub fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        self.parameters.clone()
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        let metadata = if self.risk == OperationRiskLevel::Safe {
+            ToolMetadata::read_only()
+        } else {
+            ToolMetadata::mutating()
+        };
+        metadata.with_timeout(Duration::from_secs(self.manifest.timeout_secs))
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let description = format!("Run plugin tool {}", self.manifest.name);
+        check_guardrails(
+            &self.guardrails,
+            self.metadata().operation_type(),
+            &description,
+            vec![OperationTarget {
+                resource_type: "command".to_string(),
+                path: self.executable.to_string_lossy().to_string(),
+                is_protected: false,
+                snapshot: None,
+            }],
+        )?;
+        // Tool calls cannot ask for confirmation
+        if self.risk >= OperationRiskLevel::High {
+            return Err(ToolError::PermissionDenied(format!(
+                "{} is declared {:?} risk and needs confirmation",
+                description, self.risk
+            )));
+        }
+
+        let input = serde_json::to_vec(args.as_map()).map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        let mut child = tokio::process::Command::new(&self.executable)
+            .args(&self.manifest.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to start {}: {}", self.executable.display(), e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&input).await.map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        }
+        let output = child.wait_with_output().await.map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(ToolError::ExecutionError(format!(
+                "{} exited with {}: {}",
+                self.manifest.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        parse_output(&self.manifest.name, &output.stdout)
+    }
+}
+
+/// Turn the executable's stdout into a tool result
+fn parse_output(name: &str, stdout: &[u8]) -> Result<ToolResult, ToolError> {
+    let value: serde_json::Value = serde_json::from_slice(stdout).map_err(|e| {
+        let text = String::from_utf8_lossy(stdout);
+        ToolError::ExecutionError(format!(
+            "{} did not print a JSON result ({}): {}",
+            name,
+            e,
+            text.chars().take(200).collect::<String>()
+        ))
+    })?;
+
+    let object = match &value {
+        serde_json::Value::Object(object)
+            if ["content", "summary", "data", "error"].iter().any(|key| object.contains_key(*key)) => object,
+        _ => {
+            let content = serde_json::to_string_pretty(&value).unwrap_or_default();
+            let mut result = ToolResult::text(content);
+            result.summary = format!("{} completed", name);
+            result.data = Some(value);
+            return Ok(result);
+        }
+    };
+
+    let text = |key: &str| object.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let mut result = match text("error") {
+        Some(error) => ToolResult::error(error),
+        None => ToolResult::text(text("content").unwrap_or_default()),
+    };
+    if let Some(content) = text("content") {
+        result.content = content;
+    }
+    if let Some(summary) = text("summary") {
+        result.summary = summary;
+    }
+    result.data = object.get("data").cloned();
+    Ok(result)
+}
+
+/// Load every tool manifest in `dir`
+///
+/// Manifests that fail to load are logged and skipped so one broken plugin
+/// does not keep the agent from starting.
+pub fn discover_plugins(dir: &Path) -> Result<Vec<PluginTool>, ToolError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to read tool directory {}: {}", dir.display(), e)))?;
+
+    let mut manifests: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "yaml" | "yml")))
+        .collect();
+    manifests.sort();
+
+    let mut tools = Vec::new();
+    for path in manifests {
+        match PluginTool::from_manifest(&path) {
+            Ok(tool) => tools.push(tool),
+            Err(e) => tracing::warn!("Skipping tool manifest {}: {}", path.display(), e),
+        }
+    }
+    Ok(tools)
+}
+
+/// Discover plugins in `dir` and register them, returning the registered names
+///
+/// A plugin never replaces a tool that is already registered.
+pub async fn register_plugins(registry: &ToolRegistry, dir: &Path) -> Result<Vec<String>, ToolError> {
+    let mut registered = Vec::new();
+    for tool in discover_plugins(dir)? {
+        let name = tool.name().to_string();
+        if registry.has_tool(&name).await {
+            tracing::warn!("Plugin tool '{}' conflicts with a registered tool and was skipped", name);
+            continue;
+        }
+        if !registry.register(tool).await {
+            tracing::warn!("Plugin tool '{}' is not allowed by enabled_tools/disabled_tools and was skipped", name);
+            continue;
+        }
+        registered.push(name);
+    }
+    Ok(registered)
+}
+
+/// Register the plugins configured in `ToolConfig`
+///
+/// Does nothing unless `auto_discovery` is on and `custom_tools_path` is set.
+/// An unreadable plugin directory is logged rather than treated as fatal.
+pub async fn register_configured_plugins(registry: &ToolRegistry, config: &ToolConfig) -> Vec<String> {
+    let dir = match (&config.auto_discovery, &config.custom_tools_path) {
+        (true, Some(dir)) => dir,
+        _ => return Vec::new(),
+    };
+    match register_plugins(registry, Path::new(dir)).await {
+        Ok(registered) => {
+            tracing::info!("Registered {} plugin tools from {}", registered.len(), dir);
+            registered
+        }
+        Err(e) => {
+            tracing::warn!("Plugin discovery failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::collections::HashMap;
+
+    fn plugin_dir() -> TempDir {
+        let dir = TempDir::new("plugin_tools");
+
+        let script = dir.join("greet.sh");
+        std::fs::write(&script, "#!/bin/sh\nread input\nprintf '{\"content\": \"greeted\", \"data\": %s}' \"$input\"\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::write(
+            dir.join("greet.yaml"),
+            "name: greet\ndescription: Greet someone\nexecutable: greet.sh\nrisk_level: safe\nparameters:\n  - name: who\n    required: true\n  - name: times\n    type: integer\n    default: 1\n",
+        ).unwrap();
+        std::fs::write(
+            dir.join("broken.json"),
+            r#"{"name": "broken", "description": "Missing executable", "executable": "nope.sh"}"#,
+        ).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_discover_and_run_plugin() {
+        let dir = plugin_dir();
+        let registry = ToolRegistry::new();
+
+        let registered = register_plugins(&registry, &dir).await.unwrap();
+        assert_eq!(registered, vec!["greet"]);
+        assert!(registry.metadata("greet").await.unwrap().read_only);
+
+        let mut args = HashMap::new();
+        args.insert("who".to_string(), serde_json::json!("world"));
+        let result = registry.execute(&crate::tools::ToolCall { name: "greet".to_string(), args: ToolArgs::from_map(args) }).await.unwrap();
+        // Arguments arrive validated and with defaults applied
+        assert_eq!(result.content, "greeted");
+        assert_eq!(result.data, Some(serde_json::json!({"who": "world", "times": 1})));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_plugins_are_not_reported() {
+        let dir = plugin_dir();
+        let registry = ToolRegistry::with_filter(crate::tools::ToolFilter::new(vec!["read_file".to_string()], vec![]));
+
+        assert!(register_plugins(&registry, &dir).await.unwrap().is_empty());
+        assert!(!registry.has_tool("greet").await);
+    }
+
+    #[test]
+    fn test_parse_output_shapes() {
+        let result = parse_output("t", br#"{"error": "no such ticket"}"#).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("no such ticket"));
+
+        let result = parse_output("t", b"[1, 2]").unwrap();
+        assert_eq!(result.data, Some(serde_json::json!([1, 2])));
+
+        assert!(parse_output("t", b"not json").is_err());
+    }
+}
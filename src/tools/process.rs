@@ -0,0 +1,847 @@
+//! Background processes
+//!
+//! `run_command` waits for its command to exit, which rules out dev servers
+//! and watchers. These tools start a command in the background and return a
+//! handle (`process_start`), read its stdout and stderr incrementally by byte
+//! offset and wait for a readiness pattern (`process_read`), and send it
+//! signals or stop it (`process_signal`, `process_stop`).
+//!
+//! Every process runs in its own process group so signals reach the whole
+//! tree (`npm run dev` and the server it starts). All four tools share one
+//! [`ProcessManager`]; the processes of a task are stopped when the task
+//! finishes or is cancelled, and a handle only works for the task that
+//! started the process.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Child;
+use tokio::sync::{Mutex, Notify};
+use crate::errors::ToolError;
+use crate::execution::guardrails::OperationType;
+use crate::security::{CommandValidator, ResourceLimits};
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Processes started outside a task belong to this key
+const DEFAULT_TASK: &str = "default";
+
+/// Bytes returned by one `process_read` unless the caller asks for more
+const DEFAULT_READ_BYTES: i64 = 16 * 1024;
+
+/// Time `process_stop` gives a process to exit after SIGTERM
+const DEFAULT_STOP_GRACE_SECS: i64 = 5;
+
+/// Signals `process_signal` can send
+const SIGNALS: [&str; 7] = ["TERM", "INT", "HUP", "KILL", "QUIT", "USR1", "USR2"];
+
+// ============================================================================
+// Output buffers
+// ============================================================================
+
+/// The tail of one output stream, addressed by byte offset from its start
+///
+/// Only the last `capacity` bytes are kept; older output is dropped and
+/// reads from before it start at the oldest byte still held.
+#[derive(Debug)]
+struct OutputBuffer {
+    data: Vec<u8>,
+    /// Offset of `data[0]` in the stream
+    start: u64,
+    capacity: usize,
+    closed: bool,
+}
+
+impl OutputBuffer {
+    fn new(capacity: usize) -> Self {
+        Self { data: Vec::new(), start: 0, capacity: capacity.max(1), closed: false }
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.data.len() as u64
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+        if self.data.len() > self.capacity {
+            let excess = self.data.len() - self.capacity;
+            self.data.drain(..excess);
+            self.start += excess as u64;
+        }
+    }
+
+    /// Up to `max_bytes` from `offset`, the offset after them and how many requested bytes were dropped
+    fn read(&self, offset: u64, max_bytes: usize) -> (String, u64, u64) {
+        let skipped = self.start.saturating_sub(offset);
+        let from = offset.max(self.start).min(self.end());
+        let begin = (from - self.start) as usize;
+        let end = (begin + max_bytes).min(self.data.len());
+        let text = String::from_utf8_lossy(&self.data[begin..end]).to_string();
+        (text, self.start + end as u64, skipped)
+    }
+
+    /// Text from `offset` to the end, for pattern matching
+    fn text_from(&self, offset: u64) -> String {
+        let begin = (offset.max(self.start).min(self.end()) - self.start) as usize;
+        String::from_utf8_lossy(&self.data[begin..]).to_string()
+    }
+}
+
+/// Which stream a read or pattern refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamKind {
+    Stdout,
+    Stderr,
+}
+
+// ============================================================================
+// Process manager
+// ============================================================================
+
+/// A process started in the background
+pub struct BackgroundProcess {
+    pub handle: String,
+    pub task_id: String,
+    pub command: String,
+    pub pid: Option<u32>,
+    pub started_at: DateTime<Utc>,
+    child: Mutex<Child>,
+    stdout: Arc<StdMutex<OutputBuffer>>,
+    stderr: Arc<StdMutex<OutputBuffer>>,
+    exit_code: StdMutex<Option<i32>>,
+    changed: Arc<Notify>,
+}
+
+impl BackgroundProcess {
+    fn buffer(&self, stream: StreamKind) -> &StdMutex<OutputBuffer> {
+        match stream {
+            StreamKind::Stdout => &self.stdout,
+            StreamKind::Stderr => &self.stderr,
+        }
+    }
+
+    /// Exit code once the process has exited; `Some(-1)` if killed by a signal
+    pub async fn exit_code(&self) -> Option<i32> {
+        if let Some(code) = *self.exit_code.lock().unwrap() {
+            return Some(code);
+        }
+        let status = self.child.lock().await.try_wait().ok().flatten()?;
+        let code = status.code().unwrap_or(-1);
+        *self.exit_code.lock().unwrap() = Some(code);
+        Some(code)
+    }
+
+    /// Poll for the exit status for up to `timeout`
+    async fn wait_exit(&self, timeout: Duration) -> Option<i32> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let code = self.exit_code().await;
+            if code.is_some() || tokio::time::Instant::now() >= deadline {
+                return code;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Whether both streams are closed, i.e. no more output will arrive
+    fn output_closed(&self) -> bool {
+        self.stdout.lock().unwrap().closed && self.stderr.lock().unwrap().closed
+    }
+
+    /// Output from the given offsets
+    pub async fn read(&self, stdout_offset: u64, stderr_offset: u64, max_bytes: usize) -> ProcessOutput {
+        let (stdout, stdout_offset, stdout_skipped) = self.stdout.lock().unwrap().read(stdout_offset, max_bytes);
+        let (stderr, stderr_offset, stderr_skipped) = self.stderr.lock().unwrap().read(stderr_offset, max_bytes);
+        let exit_code = self.exit_code().await;
+        ProcessOutput {
+            handle: self.handle.clone(),
+            running: exit_code.is_none(),
+            exit_code,
+            stdout,
+            stderr,
+            stdout_offset,
+            stderr_offset,
+            skipped_bytes: stdout_skipped + stderr_skipped,
+        }
+    }
+
+    /// Wait until `pattern` matches output written after the given offsets
+    ///
+    /// Returns the matched text, or `None` when the timeout passes or the
+    /// process exits and its output is exhausted without a match.
+    pub async fn wait_for(&self, pattern: &Regex, stdout_offset: u64, stderr_offset: u64, timeout: Duration) -> Option<String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // Register for wake-ups before looking, so no output is missed
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            for (stream, offset) in [(StreamKind::Stdout, stdout_offset), (StreamKind::Stderr, stderr_offset)] {
+                let text = self.buffer(stream).lock().unwrap().text_from(offset);
+                if let Some(found) = pattern.find(&text) {
+                    return Some(found.as_str().to_string());
+                }
+            }
+            if self.output_closed() {
+                // Closed pipes mean the process is exiting; let its status catch up
+                self.wait_exit(Duration::from_secs(1)).await;
+                return None;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// Send a signal (`TERM`, `KILL`, ...) to the process group
+    pub async fn signal(&self, signal: &str) -> Result<(), ToolError> {
+        if !SIGNALS.contains(&signal) {
+            return Err(ToolError::InvalidParameters(format!("Unsupported signal: {}", signal)));
+        }
+        if self.exit_code().await.is_some() {
+            return Err(ToolError::ExecutionError(format!("Process {} has already exited", self.handle)));
+        }
+        let pid = self
+            .pid
+            .ok_or_else(|| ToolError::ExecutionError(format!("Process {} has no pid", self.handle)))?;
+
+        let status = tokio::process::Command::new("kill")
+            .args(["-s", signal, "--", &format!("-{}", pid)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to run kill: {}", e)))?;
+        if !status.success() {
+            // The group may be gone already; fall back to the process itself
+            if signal == "KILL" {
+                let _ = self.child.lock().await.start_kill();
+            } else {
+                return Err(ToolError::ExecutionError(format!("Failed to send SIG{} to {}", signal, self.handle)));
+            }
+        }
+        Ok(())
+    }
+
+    /// SIGTERM, then SIGKILL if the process is still running after `grace`
+    pub async fn stop(&self, grace: Duration) -> Option<i32> {
+        if let Some(code) = self.exit_code().await {
+            return Some(code);
+        }
+        if self.signal("TERM").await.is_ok() {
+            if let Some(code) = self.wait_exit(grace).await {
+                return Some(code);
+            }
+        }
+        let _ = self.signal("KILL").await;
+        let mut child = self.child.lock().await;
+        let _ = child.start_kill();
+        let code = child.wait().await.ok().map(|status| status.code().unwrap_or(-1));
+        *self.exit_code.lock().unwrap() = code;
+        code
+    }
+}
+
+impl Drop for BackgroundProcess {
+    /// `kill_on_drop` only reaches the shell itself, so kill the whole group
+    /// when the last handle goes away, e.g. when the manager is dropped
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            let _ = std::process::Command::new("kill")
+                .args(["-s", "KILL", "--", &format!("-{}", pid)])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// Output of a background process since the given offsets
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessOutput {
+    pub handle: String,
+    pub running: bool,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Offsets to pass to the next read
+    pub stdout_offset: u64,
+    pub stderr_offset: u64,
+    /// Requested output that was dropped because the buffer is bounded
+    pub skipped_bytes: u64,
+}
+
+impl ProcessOutput {
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        if self.skipped_bytes > 0 {
+            text.push_str(&format!("[{} earlier bytes were dropped]\n", self.skipped_bytes));
+        }
+        if !self.stdout.is_empty() {
+            text.push_str(&format!("stdout:\n{}\n", self.stdout.trim_end()));
+        }
+        if !self.stderr.is_empty() {
+            text.push_str(&format!("stderr:\n{}\n", self.stderr.trim_end()));
+        }
+        match self.exit_code {
+            Some(code) => text.push_str(&format!("[{} exited with {}]", self.handle, code)),
+            None => text.push_str(&format!(
+                "[{} running; next offsets stdout={} stderr={}]",
+                self.handle, self.stdout_offset, self.stderr_offset
+            )),
+        }
+        text
+    }
+}
+
+/// Background processes of all tasks
+pub struct ProcessManager {
+    processes: StdMutex<HashMap<String, Arc<BackgroundProcess>>>,
+    next_id: AtomicU64,
+    limits: ResourceLimits,
+    validator: CommandValidator,
+}
+
+impl Default for ProcessManager {
+    fn default() -> Self {
+        Self {
+            processes: StdMutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            limits: ResourceLimits::default(),
+            validator: CommandValidator::default(),
+        }
+    }
+}
+
+impl ProcessManager {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// A manager with its own output limits and command validator
+    pub fn with_settings(limits: ResourceLimits, validator: CommandValidator) -> Arc<Self> {
+        Arc::new(Self { limits, validator, ..Self::default() })
+    }
+
+    /// Validate and start `command` in the background for `task_id`
+    pub fn start(&self, task_id: &str, command: &str, working_dir: &str) -> Result<Arc<BackgroundProcess>, ToolError> {
+        self.validator
+            .validate(command)
+            .map_err(|e| ToolError::PermissionDenied(format!("{}: {}", command, e)))?;
+
+        let mut builder = tokio::process::Command::new("sh");
+        builder
+            .arg("-c")
+            .arg(command)
+            .current_dir(working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        builder.process_group(0);
+        let mut child = builder
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to start `{}`: {}", command, e)))?;
+
+        let handle = format!("proc-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        let capacity = self.limits.max_output_size;
+        let stdout = Arc::new(StdMutex::new(OutputBuffer::new(capacity)));
+        let stderr = Arc::new(StdMutex::new(OutputBuffer::new(capacity)));
+        let changed = Arc::new(Notify::new());
+        if let Some(pipe) = child.stdout.take() {
+            tokio::spawn(collect(pipe, stdout.clone(), changed.clone()));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            tokio::spawn(collect(pipe, stderr.clone(), changed.clone()));
+        }
+
+        let process = Arc::new(BackgroundProcess {
+            handle: handle.clone(),
+            task_id: task_id.to_string(),
+            command: command.to_string(),
+            pid: child.id(),
+            started_at: Utc::now(),
+            child: Mutex::new(child),
+            stdout,
+            stderr,
+            exit_code: StdMutex::new(None),
+            changed,
+        });
+        self.processes.lock().unwrap().insert(handle.clone(), process.clone());
+        tracing::info!("Started background process {} for task {}: {}", handle, task_id, command);
+        Ok(process)
+    }
+
+    /// The process `handle` of `task_id`; other tasks' processes are not visible
+    pub fn get(&self, task_id: &str, handle: &str) -> Result<Arc<BackgroundProcess>, ToolError> {
+        self.processes
+            .lock()
+            .unwrap()
+            .get(handle)
+            .filter(|process| process.task_id == task_id)
+            .cloned()
+            .ok_or_else(|| no_such_process(handle))
+    }
+
+    /// Forget the process `handle` of `task_id`, returning it
+    pub fn remove(&self, task_id: &str, handle: &str) -> Result<Arc<BackgroundProcess>, ToolError> {
+        let mut processes = self.processes.lock().unwrap();
+        match processes.get(handle) {
+            Some(process) if process.task_id == task_id => Ok(processes.remove(handle).unwrap()),
+            _ => Err(no_such_process(handle)),
+        }
+    }
+
+    /// Handles of the processes a task started
+    pub fn handles(&self, task_id: &str) -> Vec<String> {
+        let mut handles: Vec<String> = self
+            .processes
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|process| process.task_id == task_id)
+            .map(|process| process.handle.clone())
+            .collect();
+        handles.sort();
+        handles
+    }
+
+    /// Stop and forget every process of a task
+    pub async fn stop_task(&self, task_id: &str) {
+        let processes: Vec<Arc<BackgroundProcess>> = {
+            let mut all = self.processes.lock().unwrap();
+            let handles: Vec<String> = all.values().filter(|p| p.task_id == task_id).map(|p| p.handle.clone()).collect();
+            handles.iter().filter_map(|handle| all.remove(handle)).collect()
+        };
+        for process in processes {
+            let code = process.stop(Duration::from_secs(DEFAULT_STOP_GRACE_SECS as u64)).await;
+            tracing::info!("Stopped background process {} of task {} ({:?})", process.handle, task_id, code);
+        }
+    }
+}
+
+/// Copy a pipe into its buffer, waking waiters on every chunk
+async fn collect(mut pipe: impl AsyncRead + Unpin, buffer: Arc<StdMutex<OutputBuffer>>, changed: Arc<Notify>) {
+    let mut chunk = [0u8; 8192];
+    loop {
+        match pipe.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buffer.lock().unwrap().push(&chunk[..n]),
+        }
+        changed.notify_waiters();
+    }
+    buffer.lock().unwrap().closed = true;
+    changed.notify_waiters();
+}
+
+fn compile_pattern(pattern: &str) -> Result<Regex, ToolError> {
+    Regex::new(pattern).map_err(|e| ToolError::InvalidParameters(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+fn no_such_process(handle: &str) -> ToolError {
+    ToolError::InvalidParameters(format!("No background process with handle {}", handle))
+}
+
+fn task_key(args: &ToolArgs) -> &str {
+    args.task_id().unwrap_or(DEFAULT_TASK)
+}
+
+fn output_result(output: ProcessOutput, success: bool, summary: String) -> ToolResult {
+    let mut result = if success { ToolResult::text(output.to_text()) } else { ToolResult::error(summary.clone()) };
+    if !success {
+        result.content = output.to_text();
+    }
+    result.summary = summary;
+    result.data = serde_json::to_value(&output).ok();
+    result
+}
+
+// ============================================================================
+// Tools
+// ============================================================================
+
+/// Start a command in the background
+pub struct ProcessStartTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessStartTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStartTool {
+    fn name(&self) -> &str {
+        "process_start"
+    }
+
+    fn description(&self) -> &str {
+        "Start a long-running command (dev server, watcher) in the background and return its handle; \
+         optionally wait until its output matches a readiness pattern"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("command", "Command to start"),
+            Parameter::optional("working_dir", "Working directory").with_default(serde_json::json!(".")),
+            Parameter::optional("ready_pattern", "Regex that marks the process as ready, e.g. \"Listening on\""),
+            Parameter::optional("ready_timeout_secs", "How long to wait for the ready pattern")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(30)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_operation(OperationType::CommandWrite)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let command = args.get_string("command")?;
+        let working_dir = args.get_string_or("working_dir", ".");
+
+        // Safety check
+        if working_dir.contains("..") || working_dir.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+        let pattern = match args.get_string("ready_pattern") {
+            Ok(pattern) => Some(compile_pattern(&pattern)?),
+            Err(_) => None,
+        };
+
+        let process = self.manager.start(task_key(args), &command, &working_dir)?;
+        let Some(pattern) = pattern else {
+            let output = process.read(0, 0, DEFAULT_READ_BYTES as usize).await;
+            return Ok(output_result(output, true, format!("Started {} ({})", process.handle, command)));
+        };
+
+        let timeout = Duration::from_secs(args.get_i64_or("ready_timeout_secs", 30).max(1) as u64);
+        let ready = process.wait_for(&pattern, 0, 0, timeout).await;
+        let output = process.read(0, 0, DEFAULT_READ_BYTES as usize).await;
+        let (success, summary) = match (&ready, output.exit_code) {
+            (Some(found), _) => (true, format!("Started {} ({}); ready: {}", process.handle, command, found)),
+            (None, Some(code)) => (false, format!("{} exited with {} before becoming ready", process.handle, code)),
+            (None, None) => (
+                false,
+                format!("{} is running but not ready after {}s", process.handle, timeout.as_secs()),
+            ),
+        };
+        Ok(output_result(output, success, summary))
+    }
+
+    async fn task_finished(&self, task_id: &str) {
+        self.manager.stop_task(task_id).await;
+    }
+}
+
+/// Read the output of a background process
+pub struct ProcessReadTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessReadTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessReadTool {
+    fn name(&self) -> &str {
+        "process_read"
+    }
+
+    fn description(&self) -> &str {
+        "Read new stdout/stderr of a background process from byte offsets returned by the previous read, \
+         optionally waiting until the new output matches a pattern"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("handle", "Handle returned by process_start"),
+            Parameter::optional("stdout_offset", "Read stdout from this byte offset")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), None)
+                .with_default(serde_json::json!(0)),
+            Parameter::optional("stderr_offset", "Read stderr from this byte offset")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), None)
+                .with_default(serde_json::json!(0)),
+            Parameter::optional("wait_for", "Regex to wait for in the new output"),
+            Parameter::optional("timeout_secs", "How long to wait for the pattern")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(10)),
+            Parameter::optional("max_bytes", "Maximum bytes returned per stream")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None)
+                .with_default(serde_json::json!(DEFAULT_READ_BYTES)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let process = self.manager.get(task_key(args), &args.get_string("handle")?)?;
+        let stdout_offset = args.get_i64_or("stdout_offset", 0).max(0) as u64;
+        let stderr_offset = args.get_i64_or("stderr_offset", 0).max(0) as u64;
+        let max_bytes = args.get_i64_or("max_bytes", DEFAULT_READ_BYTES).max(1) as usize;
+
+        let mut summary = format!("Output of {}", process.handle);
+        let mut success = true;
+        if let Ok(pattern) = args.get_string("wait_for") {
+            let regex = compile_pattern(&pattern)?;
+            let timeout = Duration::from_secs(args.get_i64_or("timeout_secs", 10).max(1) as u64);
+            match process.wait_for(&regex, stdout_offset, stderr_offset, timeout).await {
+                Some(found) => summary = format!("{} matched: {}", process.handle, found),
+                None => {
+                    success = false;
+                    summary = format!("'{}' did not appear in the output of {}", pattern, process.handle);
+                }
+            }
+        }
+
+        let output = process.read(stdout_offset, stderr_offset, max_bytes).await;
+        Ok(output_result(output, success, summary))
+    }
+}
+
+/// Send a signal to a background process
+pub struct ProcessSignalTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessSignalTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessSignalTool {
+    fn name(&self) -> &str {
+        "process_signal"
+    }
+
+    fn description(&self) -> &str {
+        "Send a signal (TERM, INT, HUP, KILL, QUIT, USR1, USR2) to a background process and its children"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("handle", "Handle returned by process_start"),
+            Parameter::required("signal", "Signal to send").with_enum(SIGNALS),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_operation(OperationType::CommandWrite)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let process = self.manager.get(task_key(args), &args.get_string("handle")?)?;
+        let signal = args.get_string("signal")?;
+        process.signal(&signal).await?;
+        Ok(ToolResult::text(format!("Sent SIG{} to {}", signal, process.handle)))
+    }
+}
+
+/// Stop a background process
+pub struct ProcessStopTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessStopTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStopTool {
+    fn name(&self) -> &str {
+        "process_stop"
+    }
+
+    fn description(&self) -> &str {
+        "Stop a background process and release its handle: SIGTERM, then SIGKILL if it is still running \
+         after a grace period"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("handle", "Handle returned by process_start"),
+            Parameter::optional("grace_secs", "Seconds to wait after SIGTERM before SIGKILL")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), None)
+                .with_default(serde_json::json!(DEFAULT_STOP_GRACE_SECS)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_operation(OperationType::CommandWrite)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let process = self.manager.remove(task_key(args), &args.get_string("handle")?)?;
+        let grace = Duration::from_secs(args.get_i64_or("grace_secs", DEFAULT_STOP_GRACE_SECS).max(0) as u64);
+        let code = process.stop(grace).await;
+        let mut result = ToolResult::text(format!("Stopped {} (exit code {:?})", process.handle, code));
+        result.data = Some(serde_json::json!({"handle": process.handle, "exit_code": code}));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(value: serde_json::Value) -> ToolArgs {
+        ToolArgs::from_map(serde_json::from_value(value).unwrap()).with_task_id(Some("task".to_string()))
+    }
+
+    #[test]
+    fn test_output_buffer_offsets() {
+        let mut buffer = OutputBuffer::new(8);
+        buffer.push(b"hello ");
+        assert_eq!(buffer.read(0, 3), ("hel".to_string(), 3, 0));
+        buffer.push(b"world");
+        // "hel" was dropped; a read from 0 starts at the oldest byte held
+        assert_eq!(buffer.read(0, 100), ("lo world".to_string(), 11, 3));
+        assert_eq!(buffer.read(11, 100), (String::new(), 11, 0));
+    }
+
+    #[tokio::test]
+    async fn test_start_read_and_stop() {
+        let manager = ProcessManager::with_settings(ResourceLimits::default(), {
+            let mut validator = CommandValidator::new();
+            validator.set_strict_mode(false);
+            validator
+        });
+        let start = ProcessStartTool::new(manager.clone());
+        let read = ProcessReadTool::new(manager.clone());
+
+        let started = start
+            .execute(&args(serde_json::json!({
+                "command": "echo booting; sleep 0.2; echo 'listening on 8080'; echo warn >&2; sleep 30",
+                "ready_pattern": "listening on \\d+",
+                "ready_timeout_secs": 5,
+            })))
+            .await
+            .unwrap();
+        assert!(started.success, "{}", started.summary);
+        assert!(started.summary.ends_with("ready: listening on 8080"));
+        let handle = started.data.as_ref().unwrap()["handle"].as_str().unwrap().to_string();
+
+        let first = read
+            .execute(&args(serde_json::json!({"handle": handle, "wait_for": "warn", "timeout_secs": 5})))
+            .await
+            .unwrap();
+        let data = first.data.unwrap();
+        assert_eq!(data["stdout"], "booting\nlistening on 8080\n");
+        assert_eq!(data["stderr"], "warn\n");
+        assert_eq!(data["running"], true);
+
+        // Nothing new after the returned offsets
+        let next = process_read(&read, &handle, &data).await;
+        assert_eq!(next["stdout"], "");
+
+        assert_eq!(manager.handles("task"), vec![handle.clone()]);
+        start.task_finished("task").await;
+        assert!(manager.handles("task").is_empty());
+        assert!(manager.get("task", &handle).is_err());
+    }
+
+    async fn process_read(tool: &ProcessReadTool, handle: &str, previous: &serde_json::Value) -> serde_json::Value {
+        tool.execute(&args(serde_json::json!({
+            "handle": handle,
+            "stdout_offset": previous["stdout_offset"],
+            "stderr_offset": previous["stderr_offset"],
+        })))
+        .await
+        .unwrap()
+        .data
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exit_before_ready_and_signals() {
+        let manager = ProcessManager::new();
+        let start = ProcessStartTool::new(manager.clone());
+        let failed = start
+            .execute(&args(serde_json::json!({"command": "echo nope", "ready_pattern": "ready"})))
+            .await
+            .unwrap();
+        assert!(!failed.success);
+        assert!(failed.summary.contains("exited with 0 before becoming ready"));
+
+        assert!(matches!(
+            start.execute(&args(serde_json::json!({"command": "sudo reboot"}))).await,
+            Err(ToolError::PermissionDenied(_))
+        ));
+
+        let running = manager.start("task", "cat", ".").unwrap();
+        let signal = ProcessSignalTool::new(manager.clone());
+        let stop = ProcessStopTool::new(manager.clone());
+
+        // Handles are only usable by the task that started the process
+        let other = ToolArgs::from_map(serde_json::from_value(serde_json::json!({"handle": running.handle, "signal": "KILL"})).unwrap())
+            .with_task_id(Some("other".to_string()));
+        assert!(signal.execute(&other).await.is_err());
+        assert!(stop.execute(&other).await.is_err());
+        assert!(running.exit_code().await.is_none());
+
+        signal
+            .execute(&args(serde_json::json!({"handle": running.handle, "signal": "INT"})))
+            .await
+            .unwrap();
+        let stopped = stop.execute(&args(serde_json::json!({"handle": running.handle}))).await.unwrap();
+        assert!(stopped.success);
+        assert!(running.exit_code().await.is_some());
+        assert!(manager.get("task", &running.handle).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_the_manager_kills_process_groups() {
+        let manager = ProcessManager::with_settings(ResourceLimits::default(), {
+            let mut validator = CommandValidator::new();
+            validator.set_strict_mode(false);
+            validator
+        });
+        let process = manager.start("task", "sleep 30 & wait", ".").unwrap();
+        let pid = process.pid.unwrap();
+        drop(process);
+        drop(manager);
+
+        // The killed shell may linger as a zombie until it is reaped, so only
+        // members that are still running count
+        let group_alive = || {
+            let output = std::process::Command::new("ps").args(["-eo", "pgid=,stat="]).output().unwrap();
+            String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+                let mut fields = line.split_whitespace();
+                fields.next() == Some(pid.to_string().as_str())
+                    && !fields.next().is_some_and(|stat| stat.starts_with('Z'))
+            })
+        };
+        for _ in 0..50 {
+            if !group_alive() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("process group {} survived the manager", pid);
+    }
+}
@@ -0,0 +1,455 @@
+//! Tool parameter schemas
+//!
+//! [`Parameter`] describes one tool argument: its type, default, and optional
+//! constraints (enum values, numeric range, string pattern, array item type and
+//! nested object properties). The same description is used to
+//!
+//! - generate the JSON Schema sent to the model in `ToolDefinition.parameters`
+//! - validate, default and coerce arguments before a tool runs
+//!
+//! Validation errors name the offending argument path (`options.depth`,
+//! `files[2]`) and what was expected, so the model can fix its call.
+
+use crate::errors::ToolError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// JSON type of a tool parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Array,
+    Object,
+}
+
+impl ParameterType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ParameterType::String => "string",
+            ParameterType::Integer => "integer",
+            ParameterType::Number => "number",
+            ParameterType::Boolean => "boolean",
+            ParameterType::Array => "array",
+            ParameterType::Object => "object",
+        }
+    }
+}
+
+/// Tool parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+    pub parameter_type: ParameterType,
+    pub default_value: Option<Value>,
+    /// Allowed values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<Value>>,
+    /// Inclusive lower bound for numbers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// Inclusive upper bound for numbers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// Regular expression strings must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Item schema for arrays
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Parameter>>,
+    /// Property schemas for objects
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Parameter>,
+}
+
+impl Parameter {
+    /// A parameter of any type
+    pub fn new(name: &str, description: &str, parameter_type: ParameterType, required: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            required,
+            parameter_type,
+            default_value: None,
+            enum_values: None,
+            minimum: None,
+            maximum: None,
+            pattern: None,
+            items: None,
+            properties: Vec::new(),
+        }
+    }
+
+    /// A required string parameter
+    pub fn required(name: &str, description: &str) -> Self {
+        Self::new(name, description, ParameterType::String, true)
+    }
+
+    /// An optional string parameter
+    pub fn optional(name: &str, description: &str) -> Self {
+        Self::new(name, description, ParameterType::String, false)
+    }
+
+    pub fn with_type(mut self, parameter_type: ParameterType) -> Self {
+        self.parameter_type = parameter_type;
+        self
+    }
+
+    /// Default applied when the argument is missing (makes the parameter optional)
+    pub fn with_default(mut self, value: Value) -> Self {
+        self.default_value = Some(value);
+        self.required = false;
+        self
+    }
+
+    pub fn with_enum<I, V>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        self.enum_values = Some(values.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_range(mut self, minimum: Option<f64>, maximum: Option<f64>) -> Self {
+        self.minimum = minimum;
+        self.maximum = maximum;
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: &str) -> Self {
+        self.pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// Array whose items follow `items` (the item's name is ignored)
+    pub fn with_items(mut self, items: Parameter) -> Self {
+        self.parameter_type = ParameterType::Array;
+        self.items = Some(Box::new(items));
+        self
+    }
+
+    /// Object with the given properties
+    pub fn with_properties(mut self, properties: Vec<Parameter>) -> Self {
+        self.parameter_type = ParameterType::Object;
+        self.properties = properties;
+        self
+    }
+
+    /// JSON Schema for this parameter
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = Map::new();
+        schema.insert("type".to_string(), json!(self.parameter_type.as_str()));
+        if !self.description.is_empty() {
+            schema.insert("description".to_string(), json!(self.description));
+        }
+        if let Some(default) = &self.default_value {
+            schema.insert("default".to_string(), default.clone());
+        }
+        if let Some(values) = &self.enum_values {
+            schema.insert("enum".to_string(), json!(values));
+        }
+        if let Some(minimum) = self.minimum {
+            schema.insert("minimum".to_string(), json!(minimum));
+        }
+        if let Some(maximum) = self.maximum {
+            schema.insert("maximum".to_string(), json!(maximum));
+        }
+        if let Some(pattern) = &self.pattern {
+            schema.insert("pattern".to_string(), json!(pattern));
+        }
+        if let Some(items) = &self.items {
+            schema.insert("items".to_string(), items.to_json_schema());
+        }
+        if self.parameter_type == ParameterType::Object && !self.properties.is_empty() {
+            if let Value::Object(object) = object_schema(&self.properties) {
+                schema.extend(object.into_iter().filter(|(key, _)| key != "type"));
+            }
+        }
+        Value::Object(schema)
+    }
+}
+
+/// JSON Schema of an object with the given properties
+pub fn object_schema(parameters: &[Parameter]) -> Value {
+    let properties: Map<String, Value> = parameters
+        .iter()
+        .map(|parameter| (parameter.name.clone(), parameter.to_json_schema()))
+        .collect();
+    let required: Vec<&str> = parameters
+        .iter()
+        .filter(|parameter| parameter.required)
+        .map(|parameter| parameter.name.as_str())
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// Validate `args` against `parameters`, applying defaults and coercing types
+///
+/// Returns the normalized arguments or `ToolError::InvalidParameters` naming
+/// the first problem found.
+pub fn validate_args(
+    tool_name: &str,
+    parameters: &[Parameter],
+    args: &HashMap<String, Value>,
+) -> Result<HashMap<String, Value>, ToolError> {
+    let object: Map<String, Value> = args.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    validate_object(parameters, object, "")
+        .map(|validated| validated.into_iter().collect())
+        .map_err(|message| ToolError::InvalidParameters(format!("{}: {}", tool_name, message)))
+}
+
+fn validate_object(parameters: &[Parameter], mut object: Map<String, Value>, path: &str) -> Result<Map<String, Value>, String> {
+    if let Some(unknown) = object.keys().find(|key| !parameters.iter().any(|p| &p.name == *key)) {
+        let expected: Vec<&str> = parameters.iter().map(|p| p.name.as_str()).collect();
+        return Err(format!(
+            "unknown parameter '{}'; expected one of: {}",
+            join_path(path, unknown),
+            expected.join(", ")
+        ));
+    }
+
+    let mut validated = Map::new();
+    for parameter in parameters {
+        let field_path = join_path(path, &parameter.name);
+        match object.remove(&parameter.name) {
+            Some(Value::Null) | None => {
+                if let Some(default) = &parameter.default_value {
+                    validated.insert(parameter.name.clone(), default.clone());
+                } else if parameter.required {
+                    return Err(format!(
+                        "missing required parameter '{}' ({}: {})",
+                        field_path,
+                        parameter.parameter_type.as_str(),
+                        parameter.description
+                    ));
+                }
+            }
+            Some(value) => {
+                validated.insert(parameter.name.clone(), validate_value(parameter, value, &field_path)?);
+            }
+        }
+    }
+    Ok(validated)
+}
+
+fn validate_value(parameter: &Parameter, value: Value, path: &str) -> Result<Value, String> {
+    let value = coerce(parameter.parameter_type, value)
+        .map_err(|value| format!("parameter '{}' must be {}, got {}", path, article(parameter.parameter_type), describe(&value)))?;
+
+    if let Some(allowed) = &parameter.enum_values {
+        if !allowed.contains(&value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            return Err(format!("parameter '{}' must be one of [{}], got {}", path, allowed.join(", "), value));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = parameter.minimum.filter(|minimum| number < *minimum) {
+            return Err(format!("parameter '{}' must be >= {}, got {}", path, minimum, value));
+        }
+        if let Some(maximum) = parameter.maximum.filter(|maximum| number > *maximum) {
+            return Err(format!("parameter '{}' must be <= {}, got {}", path, maximum, value));
+        }
+    }
+
+    if let (Some(pattern), Some(text)) = (&parameter.pattern, value.as_str()) {
+        let regex = regex::Regex::new(pattern)
+            .map_err(|e| format!("parameter '{}' has an invalid pattern '{}': {}", path, pattern, e))?;
+        if !regex.is_match(text) {
+            return Err(format!("parameter '{}' must match pattern '{}', got {:?}", path, pattern, text));
+        }
+    }
+
+    match value {
+        Value::Array(items) => match &parameter.items {
+            Some(item) => items
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| validate_value(item, value, &format!("{}[{}]", path, index)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            None => Ok(Value::Array(items)),
+        },
+        Value::Object(object) if !parameter.properties.is_empty() => {
+            validate_object(&parameter.properties, object, path).map(Value::Object)
+        }
+        value => Ok(value),
+    }
+}
+
+/// Coerce common near-misses from models (numbers as strings, JSON as strings, ...)
+fn coerce(parameter_type: ParameterType, value: Value) -> Result<Value, Value> {
+    match (parameter_type, value) {
+        (ParameterType::String, Value::String(s)) => Ok(Value::String(s)),
+        (ParameterType::String, value @ (Value::Number(_) | Value::Bool(_))) => Ok(Value::String(value.to_string())),
+
+        (ParameterType::Integer, Value::Number(n)) => match n.as_i64().or_else(|| {
+            n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64).map(|f| f as i64)
+        }) {
+            Some(i) => Ok(json!(i)),
+            None => Err(Value::Number(n)),
+        },
+        (ParameterType::Integer, Value::String(s)) => s.trim().parse::<i64>().map(|i| json!(i)).map_err(|_| Value::String(s)),
+
+        (ParameterType::Number, Value::Number(n)) => Ok(Value::Number(n)),
+        (ParameterType::Number, Value::String(s)) => match s.trim().parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(json!(f)),
+            _ => Err(Value::String(s)),
+        },
+
+        (ParameterType::Boolean, Value::Bool(b)) => Ok(Value::Bool(b)),
+        (ParameterType::Boolean, Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(Value::Bool(true)),
+            "false" | "no" | "0" => Ok(Value::Bool(false)),
+            _ => Err(Value::String(s)),
+        },
+
+        (ParameterType::Array, Value::Array(items)) => Ok(Value::Array(items)),
+        (ParameterType::Object, Value::Object(object)) => Ok(Value::Object(object)),
+        (ParameterType::Array | ParameterType::Object, Value::String(s)) => {
+            match serde_json::from_str::<Value>(&s) {
+                Ok(parsed @ Value::Array(_)) if parameter_type == ParameterType::Array => Ok(parsed),
+                Ok(parsed @ Value::Object(_)) if parameter_type == ParameterType::Object => Ok(parsed),
+                // A lone value where an array is expected becomes a one-element array
+                _ if parameter_type == ParameterType::Array => Ok(Value::Array(vec![Value::String(s)])),
+                _ => Err(Value::String(s)),
+            }
+        }
+        (ParameterType::Array, value @ (Value::Number(_) | Value::Bool(_))) => Ok(Value::Array(vec![value])),
+
+        (_, value) => Err(value),
+    }
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn article(parameter_type: ParameterType) -> &'static str {
+    match parameter_type {
+        ParameterType::String => "a string",
+        ParameterType::Integer => "an integer",
+        ParameterType::Number => "a number",
+        ParameterType::Boolean => "a boolean",
+        ParameterType::Array => "an array",
+        ParameterType::Object => "an object",
+    }
+}
+
+fn describe(value: &Value) -> String {
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let mut shown = value.to_string();
+    if shown.len() > 60 {
+        let cut = (0..=57).rev().find(|i| shown.is_char_boundary(*i)).unwrap_or(0);
+        shown.truncate(cut);
+        shown.push_str("...");
+    }
+    format!("{} {}", kind, shown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::required("path", "File path").with_pattern(r"^[^/]"),
+            Parameter::optional("limit", "Max results")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), Some(100.0))
+                .with_default(json!(10)),
+            Parameter::optional("mode", "Match mode").with_enum(["exact", "fuzzy"]),
+            Parameter::optional("globs", "Glob filters").with_items(Parameter::required("glob", "Glob")),
+            Parameter::optional("options", "Extra options").with_properties(vec![
+                Parameter::required("depth", "Depth").with_type(ParameterType::Integer),
+                Parameter::optional("hidden", "Include hidden").with_type(ParameterType::Boolean),
+            ]),
+        ]
+    }
+
+    fn args(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_json_schema_generation() {
+        let schema = object_schema(&parameters());
+        assert_eq!(schema["required"], json!(["path"]));
+        assert_eq!(schema["properties"]["limit"]["type"], "integer");
+        assert_eq!(schema["properties"]["limit"]["maximum"], json!(100.0));
+        assert_eq!(schema["properties"]["mode"]["enum"], json!(["exact", "fuzzy"]));
+        assert_eq!(schema["properties"]["globs"]["items"]["type"], "string");
+        assert_eq!(schema["properties"]["options"]["required"], json!(["depth"]));
+        assert_eq!(schema["properties"]["options"]["properties"]["hidden"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_validate_applies_defaults_and_coerces() {
+        let validated = validate_args(
+            "search",
+            &parameters(),
+            &args(json!({"path": "src", "globs": "*.rs", "options": {"depth": "2", "hidden": "true"}})),
+        )
+        .unwrap();
+
+        assert_eq!(validated["limit"], json!(10));
+        assert_eq!(validated["globs"], json!(["*.rs"]));
+        assert_eq!(validated["options"], json!({"depth": 2, "hidden": true}));
+    }
+
+    #[test]
+    fn test_validate_reports_precise_errors() {
+        let error = |value: Value| validate_args("search", &parameters(), &args(value)).unwrap_err().to_string();
+
+        assert!(error(json!({})).contains("missing required parameter 'path'"));
+        assert!(error(json!({"path": "src", "limit": 500})).contains("'limit' must be <= 100"));
+        assert!(error(json!({"path": "src", "mode": "regex"})).contains("must be one of [\"exact\", \"fuzzy\"]"));
+        assert!(error(json!({"path": "/etc"})).contains("must match pattern"));
+        assert!(error(json!({"path": "src", "options": {"depth": "deep"}})).contains("'options.depth' must be an integer"));
+        assert!(error(json!({"path": "src", "globs": [1, {}]})).contains("'globs[1]' must be a string"));
+        assert!(error(json!({"path": "src", "recursive": true})).contains("unknown parameter 'recursive'"));
+    }
+
+    #[tokio::test]
+    async fn test_registry_validates_before_execute() {
+        use crate::tools::{ReadFileTool, ToolArgs, ToolCall, ToolRegistry};
+
+        let registry = ToolRegistry::new();
+        registry.register(ReadFileTool).await;
+
+        let definitions = registry.definitions().await;
+        assert_eq!(definitions[0].parameters["required"], json!(["path"]));
+
+        let call = ToolCall { name: "read_file".to_string(), args: ToolArgs::from_map(HashMap::new()) };
+        let error = registry.execute(&call).await.unwrap_err();
+        assert!(matches!(error, ToolError::InvalidParameters(ref message) if message.contains("'path'")));
+    }
+}
@@ -0,0 +1,277 @@
+//! Workspace content search
+//!
+//! A native replacement for shelling out to `grep`/`rg`: literal or regex
+//! search over the files of a workspace walk (`tools::walk`), so
+//! ignored and protected paths are never read. Binary and oversized files are
+//! skipped and results are capped.
+
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::errors::ToolError;
+use super::walk::{read_text, relative_path, WorkspaceWalk};
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Snippets longer than this many characters are truncated
+const MAX_SNIPPET_CHARS: usize = 240;
+
+/// One matching line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchMatch {
+    /// Path relative to the search root
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column (in characters) of the first match on the line
+    pub column: usize,
+    pub snippet: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<String>,
+}
+
+/// What to search for and where
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// Treat `pattern` as a regular expression instead of literal text
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub context_lines: usize,
+    pub max_results: usize,
+}
+
+impl SearchQuery {
+    pub fn literal(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            regex: false,
+            case_sensitive: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            context_lines: 0,
+            max_results: 100,
+        }
+    }
+
+    pub fn regex(pattern: &str) -> Self {
+        Self { regex: true, ..Self::literal(pattern) }
+    }
+
+    fn compile(&self) -> Result<Regex, ToolError> {
+        let pattern = if self.regex { self.pattern.clone() } else { regex::escape(&self.pattern) };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid regex '{}': {}", self.pattern, e)))
+    }
+}
+
+/// Matches found by a search
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    pub files_skipped: usize,
+    /// Whether `max_results` was reached before the walk finished
+    pub truncated: bool,
+}
+
+/// Search the workspace under `root`
+///
+/// This is blocking file I/O; async callers should run it on a blocking thread.
+pub fn search_workspace(root: &Path, query: &SearchQuery) -> Result<SearchResults, ToolError> {
+    if query.pattern.is_empty() {
+        return Err(ToolError::InvalidParameters("Search pattern must not be empty".to_string()));
+    }
+    let regex = query.compile()?;
+    let files = WorkspaceWalk::new(root)
+        .with_include(query.include.clone())
+        .with_exclude(query.exclude.clone())
+        .files()?;
+
+    let mut results = SearchResults::default();
+    for path in files {
+        let content = match read_text(&path) {
+            Some(content) => content,
+            None => {
+                results.files_skipped += 1;
+                continue;
+            }
+        };
+        results.files_searched += 1;
+
+        let lines: Vec<&str> = content.lines().collect();
+        for (index, line) in lines.iter().enumerate() {
+            let found = match regex.find(line) {
+                Some(found) => found,
+                None => continue,
+            };
+            if results.matches.len() == query.max_results {
+                results.truncated = true;
+                return Ok(results);
+            }
+
+            let before = index.saturating_sub(query.context_lines);
+            let after = (index + 1 + query.context_lines).min(lines.len());
+            results.matches.push(SearchMatch {
+                path: relative_path(root, &path),
+                line: index + 1,
+                column: line[..found.start()].chars().count() + 1,
+                snippet: truncate(line),
+                context_before: lines[before..index].iter().map(|l| truncate(l)).collect(),
+                context_after: lines[index + 1..after].iter().map(|l| truncate(l)).collect(),
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+fn truncate(line: &str) -> String {
+    match line.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Workspace search tool
+pub struct SearchTool;
+
+#[async_trait]
+impl Tool for SearchTool {
+    fn name(&self) -> &str {
+        "search"
+    }
+
+    fn description(&self) -> &str {
+        "Search file contents across the workspace for literal text or a regex. \
+         Respects .gitignore and skips protected and binary files."
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("pattern", "Text or regular expression to search for"),
+            Parameter::optional("path", "Directory to search").with_default(serde_json::json!(".")),
+            Parameter::optional("regex", "Treat pattern as a regular expression")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(false)),
+            Parameter::optional("case_sensitive", "Match case")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(true)),
+            Parameter::optional("include", "Only search files matching these globs, e.g. \"*.rs\"")
+                .with_items(Parameter::required("glob", "Glob")),
+            Parameter::optional("exclude", "Skip files matching these globs")
+                .with_items(Parameter::required("glob", "Glob")),
+            Parameter::optional("context_lines", "Lines of context around each match")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(0.0), Some(10.0))
+                .with_default(serde_json::json!(0)),
+            Parameter::optional("max_results", "Maximum number of matches to return")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), Some(1000.0))
+                .with_default(serde_json::json!(100)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::read_only()
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let path = args.get_string_or("path", ".");
+
+        // Safety check
+        if path.contains("..") || path.starts_with("/") {
+            return Err(ToolError::PermissionDenied("Access to this path is not allowed".to_string()));
+        }
+
+        let query = SearchQuery {
+            pattern: args.get_string("pattern")?,
+            regex: args.get_bool_or("regex", false),
+            case_sensitive: args.get_bool_or("case_sensitive", true),
+            include: if args.contains("include") { args.get_string_array("include")? } else { Vec::new() },
+            exclude: if args.contains("exclude") { args.get_string_array("exclude")? } else { Vec::new() },
+            context_lines: args.get_i64_or("context_lines", 0) as usize,
+            max_results: args.get_i64_or("max_results", 100) as usize,
+        };
+
+        let root = PathBuf::from(&path);
+        let results = tokio::task::spawn_blocking(move || search_workspace(&root, &query))
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))??;
+
+        let mut content = results.matches.iter()
+            .map(|m| format!("{}:{}:{}: {}", m.path, m.line, m.column, m.snippet))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if results.matches.is_empty() {
+            content = format!("No matches in {} files", results.files_searched);
+        } else if results.truncated {
+            content.push_str(&format!("\n(results truncated at {} matches)", results.matches.len()));
+        }
+
+        let mut result = ToolResult::text(content);
+        result.summary = format!("{} matches in {} files", results.matches.len(), results.files_searched);
+        result.data = Some(serde_json::to_value(&results).map_err(|e| ToolError::ExecutionError(e.to_string()))?);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn workspace() -> TempDir {
+        let root = TempDir::new("search_tool");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules/dep")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn alpha() {}\n// TODO: beta\nfn gamma() { alpha(); }\n").unwrap();
+        std::fs::write(root.join("src/notes.md"), "TODO in docs\n").unwrap();
+        std::fs::write(root.join("target/debug/out.rs"), "TODO ignored\n").unwrap();
+        std::fs::write(root.join("node_modules/dep/index.js"), "// TODO protected\n").unwrap();
+        std::fs::write(root.join("src/blob.bin"), b"TODO\0binary").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_search_respects_ignores_and_globs() {
+        let root = workspace();
+
+        let results = search_workspace(&root, &SearchQuery::literal("TODO")).unwrap();
+        let paths: Vec<&str> = results.matches.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/lib.rs", "src/notes.md"]);
+        assert_eq!(results.files_skipped, 1);
+        assert_eq!((results.matches[0].line, results.matches[0].column), (2, 4));
+
+        let query = SearchQuery { include: vec!["*.rs".to_string()], ..SearchQuery::literal("TODO") };
+        assert_eq!(search_workspace(&root, &query).unwrap().matches.len(), 1);
+        let query = SearchQuery { exclude: vec!["src/*.md".to_string()], ..SearchQuery::literal("TODO") };
+        assert_eq!(search_workspace(&root, &query).unwrap().matches.len(), 1);
+    }
+
+    #[test]
+    fn test_regex_context_and_cap() {
+        let root = workspace();
+
+        let query = SearchQuery { context_lines: 1, ..SearchQuery::regex(r"fn \w+\(") };
+        let results = search_workspace(&root, &query).unwrap();
+        assert_eq!(results.matches.len(), 2);
+        assert_eq!(results.matches[1].context_before, vec!["// TODO: beta"]);
+        assert!(results.matches[1].context_after.is_empty());
+
+        let query = SearchQuery { max_results: 1, case_sensitive: false, ..SearchQuery::literal("alpha") };
+        let results = search_workspace(&root, &query).unwrap();
+        assert_eq!(results.matches.len(), 1);
+        assert!(results.truncated);
+
+        assert!(search_workspace(&root, &SearchQuery::regex("(")).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Persistent shell sessions
+//!
+//! `run_command` starts a fresh `sh -c` for every call, so a `cd` or an
+//! exported variable is gone by the next step.
+//! [`ShellSessionTool`] keeps one long-lived `sh` per task instead. Each
+//! command is syntax-checked, run with `eval` in the session shell with
+//! stderr merged into stdout, and followed by a sentinel line carrying its
+//! exit code and the shell's working directory.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+use crate::errors::ToolError;
+use crate::execution::guardrails::OperationType;
+use crate::security::{CommandValidator, ResourceLimits};
+use super::{Parameter, ParameterType, Tool, ToolArgs, ToolMetadata, ToolResult};
+
+/// Session used for calls that do not belong to a task
+const DEFAULT_SESSION: &str = "default";
+
+/// Shell builtins that change session state, allowed on top of the validator's whitelist
+///
+/// `source`, `.` and `alias` are left out: they would run or rename commands
+/// the validator never saw.
+const SESSION_BUILTINS: &[&str] = &["cd", "export", "unset", "set", "pushd", "popd"];
+
+/// Variables a command may not assign, since they change which program a
+/// whitelisted command name resolves to or how the shell splits words
+const PROTECTED_VARIABLES: &[&str] = &["PATH", "IFS", "LD_PRELOAD", "LD_LIBRARY_PATH"];
+
+/// The protected variable `command` assigns, if any
+fn assigned_protected_variable(command: &str) -> Option<&'static str> {
+    PROTECTED_VARIABLES.iter().copied().find(|name| {
+        command.match_indices(&format!("{}=", name)).any(|(start, _)| {
+            command[..start].chars().next_back().is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+        })
+    })
+}
+
+/// Outcome of one command in a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellOutput {
+    /// Combined stdout and stderr, cut at the output limit
+    pub output: String,
+    pub exit_code: i32,
+    /// Working directory of the shell after the command
+    pub cwd: String,
+    pub truncated: bool,
+}
+
+/// A long-lived `sh` process
+pub struct ShellSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ShellSession {
+    /// Start a shell in the current directory
+    pub fn spawn() -> Result<Self, ToolError> {
+        let mut child = tokio::process::Command::new("sh")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to start shell: {}", e)))?;
+
+        let missing = || ToolError::ExecutionError("Shell has no stdio pipes".to_string());
+        let stdin = child.stdin.take().ok_or_else(missing)?;
+        let stdout = BufReader::new(child.stdout.take().ok_or_else(missing)?);
+        Ok(Self { child, stdin, stdout })
+    }
+
+    /// Run `command` and wait for its sentinel
+    ///
+    /// A command that does not finish within `timeout` leaves the shell in an
+    /// unknown state; the caller should drop the session.
+    pub async fn run(&mut self, command: &str, timeout: Duration, max_output: usize) -> Result<ShellOutput, ToolError> {
+        let sentinel = format!("__AGENT_SHELL_DONE_{}__", uuid::Uuid::new_v4().simple());
+        // A syntax error would make a non-interactive shell exit, so check first.
+        // The command reads from /dev/null so it cannot consume the next command.
+        let script = format!(
+            "__agent_cmd='{}'\n\
+             if sh -n -c \"$__agent_cmd\" 2>/dev/null; then eval \"$__agent_cmd\" </dev/null; else sh -n -c \"$__agent_cmd\"; fi 2>&1\n\
+             __agent_status=$?; printf '\\n{} %s %s\\n' \"$__agent_status\" \"$PWD\"\n",
+            command.replace('\'', r"'\''"),
+            sentinel
+        );
+        self.stdin
+            .write_all(script.as_bytes())
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Shell session is gone: {}", e)))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Shell session is gone: {}", e)))?;
+
+        match tokio::time::timeout(timeout, self.read_until(&sentinel, max_output)).await {
+            Ok(result) => result,
+            Err(_) => Err(ToolError::TimeoutError),
+        }
+    }
+
+    async fn read_until(&mut self, sentinel: &str, max_output: usize) -> Result<ShellOutput, ToolError> {
+        let mut output: Vec<u8> = Vec::new();
+        let mut truncated = false;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = self
+                .stdout
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to read shell output: {}", e)))?;
+            if read == 0 {
+                return Err(ToolError::ExecutionError(
+                    "The shell exited; the next command starts a new session".to_string(),
+                ));
+            }
+
+            let text = String::from_utf8_lossy(&line);
+            if let Some(rest) = text.strip_prefix(sentinel) {
+                let mut fields = rest.trim().splitn(2, ' ');
+                let exit_code = fields.next().and_then(|code| code.parse().ok()).unwrap_or(-1);
+                let cwd = fields.next().unwrap_or_default().to_string();
+                // Drop the newline printed before the sentinel
+                if output.last() == Some(&b'\n') {
+                    output.pop();
+                }
+                return Ok(ShellOutput {
+                    output: String::from_utf8_lossy(&output).to_string(),
+                    exit_code,
+                    cwd,
+                    truncated,
+                });
+            }
+
+            let room = max_output.saturating_sub(output.len());
+            if line.len() > room {
+                truncated = true;
+            }
+            output.extend_from_slice(&line[..line.len().min(room)]);
+        }
+    }
+
+    /// Stop the shell
+    pub async fn close(mut self) {
+        let _ = self.child.start_kill();
+        let _ = self.child.wait().await;
+    }
+}
+
+/// Shell session tool
+///
+/// Keeps one shell per task, so state carries over between its commands.
+/// Every command must pass the [`CommandValidator`] before it is written to
+/// the shell; session builtins such as `cd` and `export` are allowed in
+/// addition to the validator's whitelist, but `PATH`, `IFS` and the dynamic
+/// loader variables cannot be assigned.
+pub struct ShellSessionTool {
+    sessions: Mutex<HashMap<String, Arc<Mutex<Option<ShellSession>>>>>,
+    validator: CommandValidator,
+    limits: ResourceLimits,
+}
+
+impl Default for ShellSessionTool {
+    fn default() -> Self {
+        let mut allowed = CommandValidator::new().allowed_commands().to_vec();
+        allowed.extend(SESSION_BUILTINS.iter().map(|name| name.to_string()));
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            validator: CommandValidator::with_allowed_commands(allowed),
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl ShellSessionTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_validator(mut self, validator: CommandValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Default command timeout and output cap come from `limits`
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    async fn session(&self, key: &str) -> Arc<Mutex<Option<ShellSession>>> {
+        self.sessions.lock().await.entry(key.to_string()).or_default().clone()
+    }
+
+    /// Close the shell of `key`, if one is running
+    pub async fn close_session(&self, key: &str) {
+        let session = self.sessions.lock().await.remove(key);
+        if let Some(session) = session {
+            if let Some(shell) = session.lock().await.take() {
+                shell.close().await;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ShellSessionTool {
+    fn name(&self) -> &str {
+        "shell_session"
+    }
+
+    fn description(&self) -> &str {
+        "Run a command in a persistent shell kept for the whole task, so cd and exported \
+         variables carry over between calls. Returns the combined output, \
+         exit code and working directory"
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::required("command", "Command to run in the session shell"),
+            Parameter::optional("timeout_secs", "Maximum time the command may run")
+                .with_type(ParameterType::Integer)
+                .with_range(Some(1.0), None),
+            Parameter::optional("restart", "Start a fresh shell before running the command")
+                .with_type(ParameterType::Boolean)
+                .with_default(serde_json::json!(false)),
+        ]
+    }
+
+    fn metadata(&self) -> ToolMetadata {
+        ToolMetadata::mutating().with_operation(OperationType::CommandWrite)
+    }
+
+    async fn execute(&self, args: &ToolArgs) -> Result<ToolResult, ToolError> {
+        let command = args.get_string("command")?;
+        self.validator
+            .validate(&command)
+            .map_err(|e| ToolError::PermissionDenied(format!("{}: {}", command, e)))?;
+        if let Some(name) = assigned_protected_variable(&command) {
+            return Err(ToolError::PermissionDenied(format!("{}: {} cannot be changed in a shell session", command, name)));
+        }
+
+        let timeout = match args.get_i64("timeout_secs") {
+            Ok(secs) => Duration::from_secs(secs.max(1) as u64),
+            Err(_) => self.limits.max_execution_time,
+        };
+        let key = args.task_id().unwrap_or(DEFAULT_SESSION).to_string();
+        if args.get_bool_or("restart", false) {
+            self.close_session(&key).await;
+        }
+
+        let session = self.session(&key).await;
+        let mut shell = session.lock().await;
+        if shell.is_none() {
+            tracing::debug!("Starting shell session for {}", key);
+            *shell = Some(ShellSession::spawn()?);
+        }
+        let Some(running) = shell.as_mut() else {
+            return Err(ToolError::ExecutionError("Shell session is not running".to_string()));
+        };
+
+        let output = match running.run(&command, timeout, self.limits.max_output_size).await {
+            Ok(output) => output,
+            Err(e) => {
+                // The shell is stuck or gone; the next call starts over
+                if let Some(shell) = shell.take() {
+                    shell.close().await;
+                }
+                return match e {
+                    ToolError::TimeoutError => Ok(ToolResult::error(format!(
+                        "`{}` timed out after {}s; the shell session was restarted, so its working directory \
+                         and environment were reset",
+                        command,
+                        timeout.as_secs()
+                    ))),
+                    e => Err(e),
+                };
+            }
+        };
+
+        let mut content = output.output.clone();
+        if output.truncated {
+            content.push_str(&format!("\n[output truncated at {} bytes]", self.limits.max_output_size));
+        }
+        let summary = format!("`{}` exited with {} in {}", command, output.exit_code, output.cwd);
+        let mut result = if output.exit_code == 0 {
+            ToolResult::text(content)
+        } else {
+            let mut result = ToolResult::error(summary.clone());
+            result.content = content;
+            result
+        };
+        result.summary = summary;
+        result.data = Some(serde_json::json!({
+            "exit_code": output.exit_code,
+            "cwd": output.cwd,
+            "truncated": output.truncated,
+        }));
+        Ok(result)
+    }
+
+    async fn task_finished(&self, task_id: &str) {
+        self.close_session(task_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn args(task: &str, value: serde_json::Value) -> ToolArgs {
+        ToolArgs::from_map(serde_json::from_value(value).unwrap()).with_task_id(Some(task.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_session_keeps_state_per_task() {
+        let tool = ShellSessionTool::new();
+        let workspace = TempDir::new("shell_session");
+        let dir = workspace.canonicalize().unwrap();
+
+        let cd = format!("cd '{}'", dir.display());
+        tool.execute(&args("a", serde_json::json!({"command": cd}))).await.unwrap();
+        tool.execute(&args("a", serde_json::json!({"command": "export GREETING=hello"}))).await.unwrap();
+
+        let result = tool.execute(&args("a", serde_json::json!({"command": "echo $GREETING; pwd"}))).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.content, format!("hello\n{}\n", dir.display()));
+        assert_eq!(result.data.as_ref().unwrap()["cwd"], dir.display().to_string());
+
+        // Another task has its own shell
+        let other = tool.execute(&args("b", serde_json::json!({"command": "echo \"[$GREETING]\""}))).await.unwrap();
+        assert_eq!(other.content, "[]\n");
+
+        // Exit codes, stderr and syntax errors do not end the session
+        let failed = tool.execute(&args("a", serde_json::json!({"command": "ls does-not-exist"}))).await.unwrap();
+        assert!(!failed.success);
+        assert_ne!(failed.data.as_ref().unwrap()["exit_code"], 0);
+        assert!(failed.content.contains("does-not-exist"));
+        let syntax = tool.execute(&args("a", serde_json::json!({"command": "echo 'unterminated"}))).await.unwrap();
+        assert!(!syntax.success);
+        let still = tool.execute(&args("a", serde_json::json!({"command": "echo $GREETING"}))).await.unwrap();
+        assert_eq!(still.content, "hello\n");
+
+        tool.task_finished("a").await;
+        let fresh = tool.execute(&args("a", serde_json::json!({"command": "echo \"[$GREETING]\""}))).await.unwrap();
+        assert_eq!(fresh.content, "[]\n");
+    }
+
+    #[tokio::test]
+    async fn test_validation_timeout_and_output_cap() {
+        let limits = ResourceLimits { max_output_size: 10, ..ResourceLimits::default() };
+        let tool = ShellSessionTool::new().with_limits(limits);
+
+        let denied = tool.execute(&args("t", serde_json::json!({"command": "rm -rf build"}))).await;
+        assert!(matches!(denied, Err(ToolError::PermissionDenied(_))));
+        let denied = tool.execute(&args("t", serde_json::json!({"command": "sleep 5"}))).await;
+        assert!(matches!(denied, Err(ToolError::PermissionDenied(_))));
+
+        // Builtins that run or rename unvalidated commands, and variables that
+        // change how whitelisted names resolve, are refused
+        for command in [
+            "source ./script.sh",
+            ". ./script.sh",
+            "alias ls='rm -rf build'",
+            "export PATH=/tmp/evil:$PATH",
+            "PATH=/tmp/evil ls",
+            "echo ok; export IFS=/",
+            "export LD_PRELOAD=/tmp/evil.so",
+        ] {
+            let denied = tool.execute(&args("t", serde_json::json!({"command": command}))).await;
+            assert!(matches!(denied, Err(ToolError::PermissionDenied(_))), "{} was allowed", command);
+        }
+        let allowed = tool.execute(&args("t", serde_json::json!({"command": "export MYPATH=1; echo $PATH"}))).await.unwrap();
+        assert!(allowed.success);
+
+        let long = tool.execute(&args("t", serde_json::json!({"command": "echo 0123456789abcdef"}))).await.unwrap();
+        assert!(long.content.starts_with("0123456789\n[output truncated"));
+        assert_eq!(long.data.as_ref().unwrap()["truncated"], true);
+
+        let tool = ShellSessionTool::new().with_validator({
+            let mut validator = CommandValidator::new();
+            validator.set_strict_mode(false);
+            validator
+        });
+        tool.execute(&args("t", serde_json::json!({"command": "export KEEP=1"}))).await.unwrap();
+        let slow = tool
+            .execute(&args("t", serde_json::json!({"command": "sleep 5", "timeout_secs": 1})))
+            .await
+            .unwrap();
+        assert!(slow.error.unwrap().contains("timed out"));
+        let after = tool.execute(&args("t", serde_json::json!({"command": "echo \"[$KEEP]\""}))).await.unwrap();
+        assert_eq!(after.content, "[]\n");
+    }
+}
@@ -0,0 +1,153 @@
+//! Workspace traversal shared by the file tools
+//!
+//! Walks a directory tree the way a developer would see it: `.gitignore`,
+//! `.ignore` and global git excludes are honoured (also outside a git
+//! repository), paths listed in [`DEFAULT_PROTECTED_PATHS`] are never entered,
+//! and optional include/exclude globs narrow the result further.
+
+use std::path::{Path, PathBuf};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use crate::errors::ToolError;
+use crate::execution::guardrails::DEFAULT_PROTECTED_PATHS;
+
+/// Files larger than this are not read as text
+const MAX_TEXT_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Number of leading bytes inspected for NUL bytes to detect binary files
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// Whether `relative` (with a trailing `/` for directories) is a protected path
+pub(crate) fn is_protected(relative: &str) -> bool {
+    DEFAULT_PROTECTED_PATHS
+        .iter()
+        .any(|protected| relative.starts_with(protected) || relative.contains(protected))
+}
+
+/// Builder for a filtered workspace walk
+#[derive(Debug, Clone)]
+pub(crate) struct WorkspaceWalk {
+    root: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_depth: Option<usize>,
+}
+
+impl WorkspaceWalk {
+    pub(crate) fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), include: Vec::new(), exclude: Vec::new(), max_depth: None }
+    }
+
+    /// Only yield files matching at least one of these globs (relative to the root)
+    pub(crate) fn with_include(mut self, globs: Vec<String>) -> Self {
+        self.include = globs;
+        self
+    }
+
+    /// Skip files and directories matching any of these globs
+    pub(crate) fn with_exclude(mut self, globs: Vec<String>) -> Self {
+        self.exclude = globs;
+        self
+    }
+
+    /// Descend at most `depth` levels below the root
+    pub(crate) fn with_max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Walk the tree, yielding entries (files and directories, not the root itself)
+    pub(crate) fn entries(&self) -> Result<impl Iterator<Item = ignore::DirEntry>, ToolError> {
+        if !self.root.is_dir() {
+            return Err(ToolError::InvalidParameters(format!(
+                "Not a directory: {}",
+                self.root.display()
+            )));
+        }
+
+        let mut overrides = OverrideBuilder::new(&self.root);
+        for glob in &self.include {
+            overrides.add(glob).map_err(|e| invalid_glob(glob, e))?;
+        }
+        for glob in &self.exclude {
+            overrides.add(&format!("!{}", glob)).map_err(|e| invalid_glob(glob, e))?;
+        }
+        let overrides = overrides.build().map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+
+        let root = self.root.clone();
+        let walk = WalkBuilder::new(&self.root)
+            .hidden(false)
+            .require_git(false)
+            .max_depth(self.max_depth)
+            .overrides(overrides)
+            .sort_by_file_path(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                let relative = relative_path(&root, entry.path());
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                !is_protected(&if is_dir { format!("{}/", relative) } else { relative })
+            })
+            .build();
+
+        Ok(walk.filter_map(Result::ok).filter(|entry| entry.depth() > 0))
+    }
+
+    /// Files in the tree, in path order
+    pub(crate) fn files(&self) -> Result<impl Iterator<Item = PathBuf>, ToolError> {
+        Ok(self.entries()?
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(ignore::DirEntry::into_path))
+    }
+}
+
+/// `path` relative to `root`, with `/` separators
+pub(crate) fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// File content as text, or `None` for unreadable, oversized and binary files
+pub(crate) fn read_text(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if metadata.len() > MAX_TEXT_FILE_SIZE {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Lower-case words of `text`, in order, breaking identifiers at non-alphanumeric characters and camelCase humps
+///
+/// Shared by the workspace index and the repository map so both match
+/// queries against code the same way.
+pub(crate) fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn invalid_glob(glob: &str, error: ignore::Error) -> ToolError {
+    ToolError::InvalidParameters(format!("Invalid glob '{}': {}", glob, error))
+}
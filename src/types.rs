@@ -31,6 +31,12 @@ pub struct TaskResult {
     pub details: Option<String>,
     pub execution_time: Option<u64>,
     pub task_plan: Option<TaskPlan>,
+    /// 本次运行的预算使用情况
+    #[serde(default)]
+    pub budget: Option<crate::execution::BudgetUsage>,
+    /// 任务ID（用于 `undo` 撤销本次改动）
+    #[serde(default)]
+    pub task_id: Option<String>,
 }
 
 /// Task plan generated by understanding engine